@@ -0,0 +1,3 @@
+fn sub(a: u32, b: u32) -> u32 {
+    a - b
+}
@@ -0,0 +1,22 @@
+package app
+
+import "strings"
+
+// Greet returns a greeting for the name
+func Greet(name string) string {
+	if name == "" {
+		return "hello"
+	}
+	return "hello " + name
+}
+
+// Parse splits the fields of a line
+func Parse(line string) []string {
+	fields := []string{}
+	for _, f := range strings.Split(line, ",") {
+		if f != "" {
+			fields = append(fields, strings.TrimSpace(f))
+		}
+	}
+	return fields
+}
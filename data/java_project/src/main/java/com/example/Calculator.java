@@ -0,0 +1,19 @@
+package com.example;
+
+public class Calculator {
+
+    public int add(int a, int b) {
+        return a + b;
+    }
+
+    public int divide(int a, int b) {
+        if (b == 0) {
+            throw new IllegalArgumentException("division by zero");
+        }
+        return a / b;
+    }
+
+    public int max(int a, int b) {
+        return a > b ? a : b;
+    }
+}
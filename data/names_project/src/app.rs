@@ -0,0 +1,26 @@
+use std::fmt;
+
+pub struct App {
+    name: String,
+}
+
+impl fmt::Display for App {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl fmt::Debug for App {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "App({})", self.name)
+    }
+}
+
+impl App {
+    pub fn new(name: &str) -> Self {
+        fn trim(name: &str) -> String {
+            name.trim().to_string()
+        }
+        Self { name: trim(name) }
+    }
+}
@@ -0,0 +1,8 @@
+def parse(value):
+    if value is None:
+        return 0
+    return int(value)
+
+
+def debug_dump(value):  # pragma: no cover
+    print(value)
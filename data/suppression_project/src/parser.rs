@@ -0,0 +1,40 @@
+// wcc:allow(crap, skunk) the parser is a state machine
+fn parse(input: &str) -> u32 {
+    let mut state = 0;
+    for c in input.chars() {
+        state = match (state, c) {
+            (0, 'a') => 1,
+            (1, 'b') => 2,
+            (2, 'c') => 3,
+            (3, _) => 3,
+            _ => 0,
+        };
+    }
+    if state == 3 {
+        1
+    } else if state == 2 {
+        2
+    } else {
+        0
+    }
+}
+
+fn run(input: &str) -> u32 { // wcc:ignore
+    if input.is_empty() {
+        return 0;
+    }
+    if input.len() > 10 {
+        return 1;
+    }
+    parse(input)
+}
+
+fn check(input: &str) -> bool {
+    if input.is_empty() {
+        return false;
+    }
+    if input.len() > 10 {
+        return true;
+    }
+    parse(input) > 0
+}
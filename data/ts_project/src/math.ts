@@ -0,0 +1,13 @@
+export function clamp(value: number, min: number, max: number): number {
+  if (value < min) {
+    return min;
+  }
+  if (value > max) {
+    return max;
+  }
+  return value;
+}
+
+export function sign(value: number): number {
+  return value < 0 ? -1 : 1;
+}
@@ -1,15 +1,29 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use tracing_subscriber::EnvFilter;
 
+use weighted_code_coverage::complexity::*;
 use weighted_code_coverage::error::*;
 use weighted_code_coverage::files::*;
 use weighted_code_coverage::functions::*;
+use weighted_code_coverage::history::*;
 use weighted_code_coverage::output::*;
+use weighted_code_coverage::utility::is_stdio;
 use weighted_code_coverage::utility::Complexity;
+use weighted_code_coverage::utility::CoverageReport;
+use weighted_code_coverage::utility::CsvFormat;
+use weighted_code_coverage::utility::FileDiscovery;
 use weighted_code_coverage::utility::JsonFormat;
 use weighted_code_coverage::utility::Mode;
+use weighted_code_coverage::utility::Options;
+use weighted_code_coverage::utility::Policy;
+use weighted_code_coverage::utility::SortBy;
+use weighted_code_coverage::utility::Space;
+use weighted_code_coverage::utility::TrendMetric;
+use weighted_code_coverage::utility::Validation;
 
 const fn thresholds_long_help() -> &'static str {
     "Set four  thresholds in this order: -t SIFIS_PLAIN, SIFIS_QUANTIZED, CRAP, SKUNK\n 
@@ -36,92 +50,358 @@ impl std::str::FromStr for Thresholds {
     }
 }
 
-fn run_functions(args: &Args) -> Result<()> {
+#[derive(Debug, PartialEq, Clone)]
+struct PrefixReplacement(String, String);
+
+impl std::str::FromStr for PrefixReplacement {
+    type Err = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or("Prefix replacement must be written as FROM=TO")?;
+        Ok(PrefixReplacement(from.into(), to.into()))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct ReportPath(Option<String>, PathBuf);
+
+impl std::str::FromStr for ReportPath {
+    type Err = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((label, path)) if !label.is_empty() => {
+                Ok(ReportPath(Some(label.into()), PathBuf::from(path)))
+            }
+            _ => Ok(ReportPath(None, PathBuf::from(s))),
+        }
+    }
+}
+
+// A single format applies to all the reports, otherwise there must be one format for each report
+// Without formats the format of each report is detected from its content
+fn get_reports(args: &Args) -> Result<Vec<CoverageReport>> {
+    if args.path_json.iter().filter(|r| is_stdio(&r.1)).count() > 1 {
+        return Err(Error::StdinError());
+    }
+    let formats = match args.json_format.len() {
+        0 => vec![None; args.path_json.len()],
+        1 => vec![Some(args.json_format[0]); args.path_json.len()],
+        n if n == args.path_json.len() => args.json_format.iter().copied().map(Some).collect(),
+        _ => return Err(Error::FormatsError()),
+    };
+    args.path_json
+        .iter()
+        .zip(formats)
+        .map(|(report, format)| {
+            let report = CoverageReport::new(&report.1).label(report.0.clone());
+            match format {
+                Some(format) => Ok(report.format(format)),
+                None => report.detect_format(),
+            }
+        })
+        .collect()
+}
+
+fn get_options(args: &Args) -> Result<Options> {
+    let providers = args
+        .complexity_sidecars
+        .iter()
+        .map(|path| -> Result<Arc<dyn ComplexityProvider>> { Ok(Arc::new(Sidecar::read(path)?)) })
+        .collect::<Result<Vec<Arc<dyn ComplexityProvider>>>>()?;
+    Ok(Options::default()
+        .discovery(args.discovery)
+        .source_roots(args.source_roots.clone())
+        .strip_prefixes(args.strip_prefixes.clone())
+        .replace_prefixes(
+            args.replace_prefixes
+                .iter()
+                .map(|r| (r.0.clone(), r.1.clone()))
+                .collect(),
+        )
+        .ignore_case(args.ignore_case)
+        .space_kinds(args.space_kinds.clone())
+        .rollup(args.rollup)
+        .exclude_tests(args.exclude_tests)
+        .keep_going(args.keep_going)
+        .validation(
+            Validation::default()
+                .length(args.on_length_mismatch)
+                .blank_hits(args.on_blank_hits)
+                .ranges(args.on_range_mismatch)
+                .complexity(args.on_complexity_mismatch),
+        )
+        .complexity_providers(providers))
+}
+
+// The table goes to stderr when a report is written to stdout, only one report can be written there
+fn get_console(args: &Args) -> Result<Console> {
+    if args.html_output.as_ref().map(is_stdio).unwrap_or(false) {
+        return Err(Error::StdoutError("html"));
+    }
+    let piped = [
+        ("csv", &args.path_csv),
+        ("json", &args.json_output),
+        ("markdown", &args.markdown_output),
+    ]
+    .into_iter()
+    .filter(|(_, path)| path.as_ref().map(is_stdio).unwrap_or(false))
+    .map(|(output, _)| output)
+    .collect::<Vec<&'static str>>();
+    match piped[..] {
+        [] => Ok(Console::Stdout),
+        [_] => Ok(Console::Stderr),
+        [_, output, ..] => Err(Error::StdoutError(output)),
+    }
+}
+
+// The project folder is required by clap when no subcommand is given
+fn get_project_folder(args: &Args) -> &PathBuf {
+    args.path_file
+        .as_ref()
+        .expect("the project folder is required without a subcommand")
+}
+
+fn get_filter(args: &Args) -> ReportFilter {
+    ReportFilter::default()
+        .sort_by(args.sort_by)
+        .complex_only(args.complex_only)
+        .coverage_below(args.coverage_below)
+        .top(args.top)
+}
+
+// Returns the number of files whose analysis failed
+fn run_functions(args: &Args) -> Result<usize> {
     let metric_to_use = args.complexity;
     let thresholds = &args.thresholds.0;
-    let (metrics, files_ignored, complex_files, project_coverage) = match args.json_format {
-        JsonFormat::Covdir => get_functions_metrics_concurrent_covdir(
-            &args.path_file,
-            &args.path_json,
-            metric_to_use,
-            args.n_threads.max(2),
-            thresholds,
-        )?,
-        JsonFormat::Coveralls => get_functions_metrics_concurrent(
-            &args.path_file,
-            &args.path_json,
-            metric_to_use,
-            args.n_threads.max(2),
-            thresholds,
-        )?,
-    };
+    let options = get_options(args)?;
+    let console = get_console(args)?;
+    let reports = get_reports(args)?;
+    let path_file = get_project_folder(args);
+    // A labelled report is merged like the others to attribute the coverage of the functions
+    let (metrics, files_ignored, complex_files, project_coverage, diagnostics) =
+        match reports.as_slice() {
+            [report] if report.format == JsonFormat::Covdir && report.label.is_none() => {
+                get_functions_metrics_concurrent_covdir(
+                    path_file,
+                    &report.path,
+                    metric_to_use,
+                    args.n_threads.max(2),
+                    thresholds,
+                    &options,
+                )?
+            }
+            _ => get_functions_metrics_concurrent(
+                path_file,
+                &reports,
+                metric_to_use,
+                args.n_threads.max(2),
+                thresholds,
+                &options,
+            )?,
+        };
+    // The history keeps all the files, the filters only change the reports
+    if let (Some(history), Some(revision)) = (&args.history, &args.revision) {
+        save_run(
+            history,
+            &Run::from_functions(path_file, revision, &metrics)?,
+        )?;
+    }
+    let metrics = filter_metrics_function(&metrics, &get_filter(args));
     if let Some(csv) = &args.path_csv {
-        print_metrics_to_csv_function(&metrics, &files_ignored, &csv, project_coverage)?;
+        match args.csv_format {
+            CsvFormat::Legacy => print_metrics_to_csv_function(
+                &metrics,
+                &files_ignored,
+                &diagnostics,
+                csv,
+                project_coverage,
+            )?,
+            CsvFormat::Tidy => print_metrics_to_tidy_csv_function(
+                &metrics,
+                &files_ignored,
+                &diagnostics,
+                project_coverage,
+                thresholds,
+                csv,
+            )?,
+        }
     }
     if let Some(json) = &args.json_output {
         print_metrics_to_json_function(
             &metrics,
             &files_ignored,
-            &json,
-            &&args.path_file,
+            &diagnostics,
+            json,
+            path_file,
             project_coverage,
         )?;
     };
-    get_metrics_output_function(&metrics, &files_ignored, &complex_files);
-    Ok(())
+    if let Some(html) = &args.html_output {
+        print_metrics_to_html_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            html,
+        )?;
+    };
+    if let Some(markdown) = &args.markdown_output {
+        print_metrics_to_markdown_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            args.markdown_top,
+            args.markdown_max_size,
+            markdown,
+        )?;
+    };
+    get_metrics_output_function(
+        &metrics,
+        &files_ignored,
+        &complex_files,
+        &diagnostics,
+        thresholds,
+        console,
+    )?;
+    Ok(diagnostics.files_failed.len())
 }
 
-fn run_files(args: &Args) -> Result<()> {
+// Returns the number of files whose analysis failed
+fn run_files(args: &Args) -> Result<usize> {
     let metric_to_use = args.complexity;
     let thresholds = &args.thresholds.0;
-    let (metrics, files_ignored, complex_files, project_coverage) = match args.json_format {
-        JsonFormat::Covdir => get_metrics_concurrent_covdir(
-            &args.path_file,
-            &args.path_json,
-            metric_to_use,
-            args.n_threads.max(2),
-            thresholds,
-        )?,
-        JsonFormat::Coveralls => get_metrics_concurrent(
-            &args.path_file,
-            &args.path_json,
-            metric_to_use,
-            args.n_threads.max(2),
-            thresholds,
-        )?,
-    };
+    let options = get_options(args)?;
+    let console = get_console(args)?;
+    let reports = get_reports(args)?;
+    let path_file = get_project_folder(args);
+    let (metrics, files_ignored, complex_files, project_coverage, diagnostics) =
+        match reports.as_slice() {
+            [report] if report.format == JsonFormat::Covdir => get_metrics_concurrent_covdir(
+                path_file,
+                &report.path,
+                metric_to_use,
+                args.n_threads.max(2),
+                thresholds,
+                &options,
+            )?,
+            _ => get_metrics_concurrent(
+                path_file,
+                &reports,
+                metric_to_use,
+                args.n_threads.max(2),
+                thresholds,
+                &options,
+            )?,
+        };
+    // The history keeps all the files, the filters only change the reports
+    if let (Some(history), Some(revision)) = (&args.history, &args.revision) {
+        save_run(history, &Run::from_files(path_file, revision, &metrics)?)?;
+    }
+    let metrics = filter_metrics(&metrics, &get_filter(args));
     if let Some(csv) = &args.path_csv {
-        print_metrics_to_csv(&metrics, &files_ignored, &csv, project_coverage)?;
+        match args.csv_format {
+            CsvFormat::Legacy => print_metrics_to_csv(
+                &metrics,
+                &files_ignored,
+                &diagnostics,
+                csv,
+                project_coverage,
+            )?,
+            CsvFormat::Tidy => print_metrics_to_tidy_csv(
+                &metrics,
+                &files_ignored,
+                &diagnostics,
+                project_coverage,
+                thresholds,
+                csv,
+            )?,
+        }
     }
     if let Some(json) = &args.json_output {
         print_metrics_to_json(
             &metrics,
             &files_ignored,
-            &json,
-            &&args.path_file,
+            &diagnostics,
+            json,
+            path_file,
             project_coverage,
         )?;
     };
-    get_metrics_output(&metrics, &files_ignored, &complex_files);
-    Ok(())
+    if let Some(html) = &args.html_output {
+        print_metrics_to_html(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            html,
+        )?;
+    };
+    if let Some(markdown) = &args.markdown_output {
+        print_metrics_to_markdown(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            args.markdown_top,
+            args.markdown_max_size,
+            markdown,
+        )?;
+    };
+    get_metrics_output(
+        &metrics,
+        &files_ignored,
+        &complex_files,
+        &diagnostics,
+        thresholds,
+        console,
+    )?;
+    Ok(diagnostics.files_failed.len())
 }
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about)]
+#[clap(author, version, about, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
     /// Path to the project folder
-    #[clap(short = 'p', long = "path_file", parse(from_os_str))]
-    path_file: PathBuf,
+    #[clap(short = 'p', long = "path_file", parse(from_os_str), required = true)]
+    path_file: Option<PathBuf>,
 
-    /// Path to the grcov json in coveralls/covdir format
-    #[clap(short = 'j', long = "path_json", parse(from_os_str))]
-    path_json: PathBuf,
-    /// Path where to save the output of the csv file
+    /// Path to the coverage report: grcov json in coveralls/covdir format, go coverage profile, JaCoCo xml, Istanbul json or coverage.py json, written as [LABEL=]PATH
+    /// The PATH - reads the report from stdin
+    /// Can be repeated to merge several coverage reports, the label tells which reports cover each function
+    #[clap(
+        short = 'j',
+        long = "path_json",
+        required = true,
+        multiple_occurrences(true)
+    )]
+    path_json: Vec<ReportPath>,
+    /// Path where to save the output of the csv file, - writes it to stdout
     #[clap(long = "csv", parse(from_os_str))]
     path_csv: Option<PathBuf>,
-    /// Path where to save the output of the json file
+    /// Choose the format of the csv output
+    #[structopt(long = "csv_format", required = false, possible_values = CsvFormat::variants(), default_value= CsvFormat::default())]
+    csv_format: CsvFormat,
+    /// Path where to save the output of the json file, - writes it to stdout
     #[clap(long = "json", parse(from_os_str))]
     json_output: Option<PathBuf>,
+    /// Path of the folder where to save the html report
+    #[clap(long = "html", parse(from_os_str))]
+    html_output: Option<PathBuf>,
+    /// Path where to save the markdown summary for pull request comments, - writes it to stdout
+    #[clap(long = "markdown", parse(from_os_str))]
+    markdown_output: Option<PathBuf>,
+    /// Number of worst entries listed for each metric in the markdown summary
+    #[clap(long = "markdown_top", default_value_t = MARKDOWN_TOP)]
+    markdown_top: usize,
+    /// Max size in bytes of the markdown summary, the sections that do not fit are not shown
+    #[clap(long = "markdown_max_size", default_value_t = MARKDOWN_MAX_SIZE)]
+    markdown_max_size: usize,
     /// Choose complexity metric to use
     #[structopt(long, short, required = false, possible_values = Complexity::variants(), default_value= Complexity::default())]
     complexity: Complexity,
@@ -129,9 +409,11 @@ struct Args {
     /// Number of threads to use for concurrency
     #[clap(long = "n_threads", short = 'n', default_value_t = 2)]
     n_threads: usize,
-    /// Specify the type of format used between coveralls and covdir
-    #[structopt(long, short='f', required = false, possible_values = JsonFormat::variants(), default_value= JsonFormat::default() )]
-    json_format: JsonFormat,
+    /// Specify the format of the coverage report between coveralls, covdir, coverprofile, jacoco, istanbul and coveragepy
+    /// Can be repeated to give the format of each coverage report in the same order
+    /// If not given the format of each report is detected from its content
+    #[structopt(long, short='f', required = false, possible_values = JsonFormat::variants(), multiple_occurrences(true))]
+    json_format: Vec<JsonFormat>,
     #[structopt(long, short, required = false,long_help=thresholds_long_help(),default_value="35.0,1.5,35.0,30.0")]
     thresholds: Thresholds,
     /// Output the generated paths as they are produced
@@ -140,9 +422,121 @@ struct Args {
     /// Choose mode to use for analysis
     #[structopt(long, short='m',  possible_values = Mode::variants(), default_value= Mode::default() )]
     mode: Mode,
+    /// Sort the entries of the reports, the metrics are sorted from the worst
+    #[structopt(long = "sort_by", possible_values = SortBy::variants())]
+    sort_by: Option<SortBy>,
+    /// Report only the complex entries
+    #[clap(long = "complex_only")]
+    complex_only: bool,
+    /// Report only the entries with a coverage percentage below the given value
+    #[clap(long = "coverage_below")]
+    coverage_below: Option<f64>,
+    /// Report only the first N entries
+    #[clap(long = "top")]
+    top: Option<usize>,
+    /// Choose how to find the files to analyze, walking the project folder or reading the coverage report
+    #[structopt(long, required = false, possible_values = FileDiscovery::variants(), default_value= FileDiscovery::default())]
+    discovery: FileDiscovery,
+    /// Folder where the files of the coverage report are searched, can be repeated (default: project folder)
+    #[clap(long = "source_root", parse(from_os_str), multiple_occurrences(true))]
+    source_roots: Vec<PathBuf>,
+    /// Prefix removed from the paths of the coverage report, can be repeated
+    #[clap(long = "strip_prefix", multiple_occurrences(true))]
+    strip_prefixes: Vec<String>,
+    /// Prefix of the paths of the coverage report to replace written as FROM=TO, can be repeated
+    #[clap(long = "replace_prefix", multiple_occurrences(true))]
+    replace_prefixes: Vec<PrefixReplacement>,
+    /// Match the paths of the coverage report with the files ignoring the case
+    #[clap(long = "ignore_case")]
+    ignore_case: bool,
+    /// Kind of the spaces reported in functions mode, can be repeated
+    #[structopt(long = "space_kind", required = false, possible_values = Space::variants(), default_value= Space::default(), multiple_occurrences(true))]
+    space_kinds: Vec<Space>,
+    /// Report only the outermost spaces of the chosen kinds, the nested ones are rolled up into them
+    #[clap(long = "rollup")]
+    rollup: bool,
+    /// Exclude the test files and the test code inside the files from the analysis
+    #[clap(long = "exclude_tests")]
+    exclude_tests: bool,
+    /// Skip the files whose analysis fails and report them instead of stopping at the first error
+    #[clap(long = "keep_going")]
+    keep_going: bool,
+    /// Exit with status 0 even if some files failed with --keep_going
+    #[clap(long = "allow_failures")]
+    allow_failures: bool,
+    /// What to do when the coverage array of a file is longer than the file
+    #[structopt(long = "on_length_mismatch", required = false, possible_values = Policy::variants(), default_value= Policy::default())]
+    on_length_mismatch: Policy,
+    /// What to do when blank or comment lines of a file are instrumented
+    #[structopt(long = "on_blank_hits", required = false, possible_values = Policy::variants(), default_value= "ignore")]
+    on_blank_hits: Policy,
+    /// What to do when functions of a file start after the end of its coverage array
+    #[structopt(long = "on_range_mismatch", required = false, possible_values = Policy::variants(), default_value= Policy::default())]
+    on_range_mismatch: Policy,
+    /// What to do when functions of a complexity sidecar end after the file or have code but no instrumented lines
+    #[structopt(long = "on_complexity_mismatch", required = false, possible_values = Policy::variants(), default_value= Policy::default())]
+    on_complexity_mismatch: Policy,
+    /// JSON or csv file with the complexity of the functions computed by another tool, can be repeated
+    /// It is used for the files it lists instead of rust-code-analysis
+    #[clap(
+        long = "complexity_sidecar",
+        parse(from_os_str),
+        multiple_occurrences(true)
+    )]
+    complexity_sidecars: Vec<PathBuf>,
+    /// Folder of the history where the metrics of this run are saved, it is created if missing
+    #[clap(long = "history", parse(from_os_str), requires = "revision")]
+    history: Option<PathBuf>,
+    /// Label of the revision saved with the run in the history, like a commit hash or a tag
+    #[clap(long = "revision", requires = "history")]
+    revision: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the trend of the metrics saved in a history, for the project and the chosen files
+    Trend(TrendArgs),
 }
 
-fn main() -> Result<()> {
+#[derive(clap::Args, Debug)]
+struct TrendArgs {
+    /// Folder of the history written with --history
+    #[clap(long = "history", parse(from_os_str))]
+    history: PathBuf,
+    /// Path of a file to show relative to the project folder, or its final part, can be repeated
+    #[clap(long = "file", multiple_occurrences(true))]
+    files: Vec<String>,
+    /// Metric to show, can be repeated (default: all but complexity)
+    #[clap(long = "metric", possible_values = TrendMetric::variants(), multiple_occurrences(true))]
+    metrics: Vec<TrendMetric>,
+    /// Number of the last runs shown
+    #[clap(long = "last", default_value_t = TREND_LAST)]
+    last: usize,
+}
+
+fn run_trend(args: &TrendArgs) -> Result<usize> {
+    let runs = read_runs(&args.history)?;
+    print_trend(
+        &runs,
+        &args.files,
+        &args.metrics,
+        args.last,
+        Console::Stdout,
+    )?;
+    Ok(0)
+}
+
+// Print an error followed by the chain of its causes
+fn report(error: &dyn std::error::Error) {
+    eprintln!("Error: {}", error);
+    let mut source = error.source();
+    while let Some(cause) = source {
+        eprintln!("  caused by: {}", cause);
+        source = cause.source();
+    }
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
     let filter_layer = EnvFilter::try_from_default_env()
         .or_else(|_| {
@@ -159,8 +553,18 @@ fn main() -> Result<()> {
         .with_env_filter(filter_layer)
         .with_writer(std::io::stderr)
         .init();
-    match args.mode {
-        Mode::Functions => run_functions(&args),
-        Mode::Files => run_files(&args),
+    let result = match (&args.command, args.mode) {
+        (Some(Command::Trend(trend)), _) => run_trend(trend),
+        (None, Mode::Functions) => run_functions(&args),
+        (None, Mode::Files) => run_files(&args),
+    };
+    match result {
+        // Some files failed with --keep_going
+        Ok(failed) if failed > 0 && !args.allow_failures => ExitCode::from(2),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            report(&error);
+            ExitCode::FAILURE
+        }
     }
 }
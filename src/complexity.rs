@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Once};
+
+use rust_code_analysis::{get_function_spaces, guess_language, FuncSpace, SpaceKind, LANG};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::error::*;
+use crate::utility::{normalize_path, read_report, Complexity};
+use crate::validate::is_blank_or_comment;
+
+// The missing Java metrics are reported once and not for each file
+static JAVA_WARNING: Once = Once::new();
+
+/// Metrics of a space used to compute the weighted coverage
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SpaceMetrics {
+    /// Cyclomatic complexity of the space without the nested spaces
+    pub cyclomatic: f64,
+    /// Cyclomatic complexity of the space and of the nested spaces
+    pub cyclomatic_sum: f64,
+    /// Cognitive complexity of the space without the nested spaces
+    pub cognitive: f64,
+    /// Cognitive complexity of the space and of the nested spaces
+    pub cognitive_sum: f64,
+    /// Physical lines of code
+    pub ploc: f64,
+}
+
+impl SpaceMetrics {
+    /// Complexity of the space without the nested spaces
+    pub fn complexity(&self, metric: Complexity) -> f64 {
+        match metric {
+            Complexity::Cyclomatic => self.cyclomatic,
+            Complexity::Cognitive => self.cognitive,
+        }
+    }
+
+    /// Complexity of the space and of the nested spaces
+    pub fn complexity_sum(&self, metric: Complexity) -> f64 {
+        match metric {
+            Complexity::Cyclomatic => self.cyclomatic_sum,
+            Complexity::Cognitive => self.cognitive_sum,
+        }
+    }
+}
+
+/// Space of a file, like a function or a class, with its metrics
+/// The root of a file is a space of kind unit containing all the others
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeSpace {
+    pub name: Option<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: SpaceKind,
+    pub spaces: Vec<CodeSpace>,
+    pub metrics: SpaceMetrics,
+}
+
+impl From<&FuncSpace> for CodeSpace {
+    fn from(space: &FuncSpace) -> Self {
+        Self {
+            name: space.name.clone(),
+            start_line: space.start_line,
+            end_line: space.end_line,
+            kind: space.kind,
+            spaces: space.spaces.iter().map(CodeSpace::from).collect(),
+            metrics: SpaceMetrics {
+                cyclomatic: space.metrics.cyclomatic.cyclomatic(),
+                cyclomatic_sum: space.metrics.cyclomatic.cyclomatic_sum(),
+                cognitive: space.metrics.cognitive.cognitive(),
+                cognitive_sum: space.metrics.cognitive.cognitive_sum(),
+                ploc: space.metrics.loc.ploc(),
+            },
+        }
+    }
+}
+
+/// Source of the spaces of a file with their complexity
+pub trait ComplexityProvider: fmt::Debug + Send + Sync {
+    /// Get the root space of a file, None if the provider knows nothing about it
+    /// The lines excluded from the analysis are blank in the data
+    fn root(&self, path: &Path, data: &[u8]) -> Result<Option<CodeSpace>>;
+
+    /// Whether the spaces are found parsing the source
+    /// The spaces of the other providers are checked against the source and the coverage
+    fn parses_source(&self) -> bool {
+        false
+    }
+}
+
+/// Complexity computed parsing the source with rust-code-analysis
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RustCodeAnalysis;
+
+impl ComplexityProvider for RustCodeAnalysis {
+    fn root(&self, path: &Path, data: &[u8]) -> Result<Option<CodeSpace>> {
+        let lang = match guess_language(data, path).0 {
+            None => return Ok(None),
+            Some(lang) => lang,
+        };
+        // Java is parsed but its metrics are not computed, the lines of code are counted here
+        let java = lang == LANG::Java;
+        if java {
+            JAVA_WARNING.call_once(|| {
+                warn!("The complexity of the Java files is not computed by rust-code-analysis, give it with a complexity sidecar")
+            });
+        }
+        debug!("{:?} is written in {:?}", path, lang);
+        let root = get_function_spaces(&lang, data.to_vec(), path, None).ok_or_else(|| {
+            Error::MetricsError {
+                file: path.to_path_buf(),
+            }
+        })?;
+        let mut root = CodeSpace::from(&root);
+        if java {
+            let source = String::from_utf8_lossy(data);
+            fill_ploc(&mut root, &source.lines().collect::<Vec<&str>>());
+        }
+        Ok(Some(root))
+    }
+
+    fn parses_source(&self) -> bool {
+        true
+    }
+}
+
+/// Function listed in a complexity sidecar
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SidecarFunction {
+    /// Path of the file, matched against the end of the paths of the analyzed files
+    pub file: String,
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub cyclomatic: f64,
+    /// Not all the tools compute it, 0 when missing
+    #[serde(default)]
+    pub cognitive: f64,
+    /// Physical lines of code, counted from the source when missing
+    #[serde(default)]
+    pub ploc: Option<f64>,
+}
+
+/// Complexity of the functions computed by another tool and written in a sidecar file
+/// It gives the complexity of files in languages rust-code-analysis does not support
+/// or replaces the one of rust-code-analysis for the files it lists
+#[derive(Clone, Debug, Default)]
+pub struct Sidecar {
+    // Functions of each file using the normalized path as key
+    files: HashMap<String, Vec<SidecarFunction>>,
+}
+
+impl Sidecar {
+    pub fn new(functions: Vec<SidecarFunction>) -> Result<Self> {
+        let mut files = HashMap::<String, Vec<SidecarFunction>>::new();
+        functions.into_iter().try_for_each(|f| -> Result<()> {
+            if f.start_line == 0 || f.start_line > f.end_line {
+                return Err(Error::sidecar(format!(
+                    "function {} of {} has the range ({}, {}), lines start from 1 and the end cannot be before the start",
+                    f.name, f.file, f.start_line, f.end_line
+                )));
+            }
+            files
+                .entry(normalize_path(&f.file, false))
+                .or_default()
+                .push(f);
+            Ok(())
+        })?;
+        Ok(Self { files })
+    }
+
+    /// Read a sidecar written as a JSON array of functions
+    pub fn from_json<A: AsRef<Path>>(path: A) -> Result<Self> {
+        let file = read_report(&path)?;
+        let functions = serde_json::from_str::<Vec<SidecarFunction>>(&file)
+            .map_err(|e| Error::from(e).with_file(&path))?;
+        Self::new(functions).map_err(|e| e.with_file(&path))
+    }
+
+    /// Read a sidecar written as a csv with a row for each function
+    /// The header names the columns like the fields of the JSON sidecar
+    pub fn from_csv<A: AsRef<Path>>(path: A) -> Result<Self> {
+        let file = read_report(&path)?;
+        let functions = csv::Reader::from_reader(file.as_bytes())
+            .deserialize::<SidecarFunction>()
+            .collect::<std::result::Result<Vec<SidecarFunction>, csv::Error>>()
+            .map_err(|e| Error::sidecar(e.to_string()).with_file(&path))?;
+        Self::new(functions).map_err(|e| e.with_file(&path))
+    }
+
+    /// Read a sidecar in csv format if its extension is csv, in JSON format otherwise
+    pub fn read<A: AsRef<Path>>(path: A) -> Result<Self> {
+        match path.as_ref().extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::from_csv(path),
+            _ => Self::from_json(path),
+        }
+    }
+
+    // Get the functions of a file, the longest path of the sidecar matching the end of the path is used
+    fn functions(&self, path: &Path) -> Option<&Vec<SidecarFunction>> {
+        let path = normalize_path(&path.display().to_string(), false);
+        let components = path.split('/').collect::<Vec<&str>>();
+        (0..components.len()).find_map(|i| self.files.get(&components[i..].join("/")))
+    }
+}
+
+// Count the lines with code between start and end
+fn count_ploc(lines: &[&str], start: usize, end: usize) -> f64 {
+    lines
+        .iter()
+        .take(end)
+        .skip(start.saturating_sub(1))
+        .filter(|line| !is_blank_or_comment(line))
+        .count() as f64
+}
+
+// Count the lines with code of a space and of the nested spaces
+fn fill_ploc(space: &mut CodeSpace, lines: &[&str]) {
+    space.spaces.iter_mut().for_each(|s| fill_ploc(s, lines));
+    space.metrics.ploc = count_ploc(lines, space.start_line, space.end_line);
+}
+
+// Move a space closed by the next ones into the space containing it or into the top level ones
+fn close_space(stack: &mut Vec<CodeSpace>, top: &mut Vec<CodeSpace>) {
+    if let Some(space) = stack.pop() {
+        match stack.last_mut() {
+            Some(parent) => parent.spaces.push(space),
+            None => top.push(space),
+        }
+    }
+}
+
+// Nest each space inside the smallest space containing it and return the top level ones
+// Spaces overlapping without one containing the other are an error
+fn nest_spaces(mut spaces: Vec<CodeSpace>) -> Result<Vec<CodeSpace>> {
+    spaces.sort_by(|a, b| {
+        a.start_line
+            .cmp(&b.start_line)
+            .then(b.end_line.cmp(&a.end_line))
+    });
+    let mut stack = Vec::<CodeSpace>::new();
+    let mut top = Vec::<CodeSpace>::new();
+    for space in spaces {
+        while stack
+            .last()
+            .map(|open| open.end_line < space.start_line)
+            .unwrap_or(false)
+        {
+            close_space(&mut stack, &mut top);
+        }
+        if let Some(open) = stack.last() {
+            if open.end_line < space.end_line {
+                return Err(Error::sidecar(format!(
+                    "the functions {} ({}, {}) and {} ({}, {}) overlap",
+                    open.name.as_deref().unwrap_or("-"),
+                    open.start_line,
+                    open.end_line,
+                    space.name.as_deref().unwrap_or("-"),
+                    space.start_line,
+                    space.end_line
+                )));
+            }
+        }
+        stack.push(space);
+    }
+    while !stack.is_empty() {
+        close_space(&mut stack, &mut top);
+    }
+    Ok(top)
+}
+
+// Add the complexity of the nested spaces to the sums of each space
+fn sum_nested(space: &mut CodeSpace) {
+    space.spaces.iter_mut().for_each(sum_nested);
+    space.metrics.cyclomatic_sum = space.metrics.cyclomatic
+        + space
+            .spaces
+            .iter()
+            .map(|s| s.metrics.cyclomatic_sum)
+            .sum::<f64>();
+    space.metrics.cognitive_sum = space.metrics.cognitive
+        + space
+            .spaces
+            .iter()
+            .map(|s| s.metrics.cognitive_sum)
+            .sum::<f64>();
+}
+
+impl ComplexityProvider for Sidecar {
+    // The functions are nested by their ranges, the complexity of each one is the one
+    // of its own lines, the sums include the nested functions like in rust-code-analysis
+    fn root(&self, path: &Path, data: &[u8]) -> Result<Option<CodeSpace>> {
+        let functions = match self.functions(path) {
+            Some(functions) => functions,
+            None => return Ok(None),
+        };
+        let source = String::from_utf8_lossy(data);
+        let lines = source.lines().collect::<Vec<&str>>();
+        let spaces = functions
+            .iter()
+            .map(|f| CodeSpace {
+                name: Some(f.name.to_string()),
+                start_line: f.start_line,
+                end_line: f.end_line,
+                kind: SpaceKind::Function,
+                spaces: Vec::new(),
+                metrics: SpaceMetrics {
+                    cyclomatic: f.cyclomatic,
+                    cyclomatic_sum: f.cyclomatic,
+                    cognitive: f.cognitive,
+                    cognitive_sum: f.cognitive,
+                    ploc: f
+                        .ploc
+                        .unwrap_or_else(|| count_ploc(&lines, f.start_line, f.end_line)),
+                },
+            })
+            .collect::<Vec<CodeSpace>>();
+        // Like in rust-code-analysis the unit has complexity 1
+        let mut root = CodeSpace {
+            name: Some(path.display().to_string()),
+            start_line: 1,
+            end_line: lines.len(),
+            kind: SpaceKind::Unit,
+            spaces: nest_spaces(spaces)?,
+            metrics: SpaceMetrics {
+                cyclomatic: 1.,
+                ploc: count_ploc(&lines, 1, lines.len()),
+                ..Default::default()
+            },
+        };
+        sum_nested(&mut root);
+        Ok(Some(root))
+    }
+}
+
+// Get the root space of a file from the first provider knowing it
+// rust-code-analysis is tried after the given providers
+// Returns the root and true if the provider does not parse the source
+pub(crate) fn get_code_root(
+    path: &Path,
+    data: &[u8],
+    providers: &[Arc<dyn ComplexityProvider>],
+) -> Result<(CodeSpace, bool)> {
+    let builtin: &dyn ComplexityProvider = &RustCodeAnalysis;
+    for provider in providers.iter().map(|p| p.as_ref()).chain([builtin]) {
+        if let Some(root) = provider.root(path, data)? {
+            return Ok((root, !provider.parses_source()));
+        }
+    }
+    Err(Error::LanguageError {
+        file: path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(file: &str, name: &str, start_line: usize, end_line: usize) -> SidecarFunction {
+        SidecarFunction {
+            file: file.into(),
+            name: name.into(),
+            start_line,
+            end_line,
+            cyclomatic: 3.,
+            cognitive: 2.,
+            ploc: None,
+        }
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let sidecar = Sidecar::new(vec![
+            function("./pkg/server.go", "Serve", 3, 6),
+            function("pkg/server.go", "main", 1, 1),
+        ])
+        .unwrap();
+        let data = b"func main() {}\n\nfunc Serve() {\n\t// listen\n\tlisten()\n}\n";
+        let root = sidecar
+            .root(Path::new("data/go/pkg/server.go"), data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(root.end_line, 6);
+        assert_eq!(root.spaces[0].name.as_deref(), Some("main"));
+        assert_eq!(root.spaces[1].metrics.ploc, 3.);
+        assert_eq!(root.metrics.cyclomatic_sum, 7.);
+        assert_eq!(root.metrics.complexity_sum(Complexity::Cognitive), 4.);
+        assert_eq!(root.metrics.ploc, 4.);
+
+        assert!(sidecar
+            .root(Path::new("data/go/cmd/server.go"), data)
+            .unwrap()
+            .is_none());
+
+        let error = Sidecar::new(vec![function("server.go", "main", 4, 2)]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("main of server.go has the range (4, 2)"));
+    }
+
+    #[test]
+    fn test_sidecar_nesting() {
+        let sidecar = Sidecar::new(vec![
+            function("app.py", "other", 12, 14),
+            function("app.py", "inner", 3, 5),
+            function("app.py", "outer", 1, 10),
+            function("app.py", "nested", 4, 5),
+            function("app.py", "second", 6, 8),
+        ])
+        .unwrap();
+        let data = "x = 1\n".repeat(14);
+        let root = sidecar
+            .root(Path::new("app.py"), data.as_bytes())
+            .unwrap()
+            .unwrap();
+        let names = |spaces: &[CodeSpace]| {
+            spaces
+                .iter()
+                .map(|s| s.name.clone().unwrap())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(names(&root.spaces), ["outer", "other"]);
+        let outer = &root.spaces[0];
+        assert_eq!(names(&outer.spaces), ["inner", "second"]);
+        assert_eq!(names(&outer.spaces[0].spaces), ["nested"]);
+        assert_eq!(outer.metrics.cyclomatic, 3.);
+        assert_eq!(outer.metrics.cyclomatic_sum, 12.);
+        assert_eq!(root.metrics.cyclomatic_sum, 16.);
+
+        let sidecar = Sidecar::new(vec![
+            function("app.py", "first", 1, 5),
+            function("app.py", "second", 3, 8),
+        ])
+        .unwrap();
+        let error = sidecar
+            .root(Path::new("app.py"), data.as_bytes())
+            .unwrap_err()
+            .with_file("app.py");
+        assert_eq!(
+            error.to_string(),
+            "Invalid complexity sidecar in app.py: the functions first (1, 5) and second (3, 8) overlap"
+        );
+    }
+
+    #[test]
+    fn test_sidecar_formats() {
+        let path = Path::new("./data/go_project/server.go");
+        let data = std::fs::read(path).unwrap();
+        let json = Sidecar::read("./data/go_project/complexity.json").unwrap();
+        let csv = Sidecar::read("./data/go_project/complexity.csv").unwrap();
+        let root = json.root(path, &data).unwrap().unwrap();
+        assert_eq!(root, csv.root(path, &data).unwrap().unwrap());
+        assert_eq!(root.spaces.len(), 2);
+        assert_eq!(root.metrics.cognitive_sum, 4.);
+    }
+
+    #[test]
+    fn test_get_code_root() {
+        let path = Path::new("pkg/server.go");
+        let data = b"func main() {}\n";
+        assert!(get_code_root(path, data, &[]).is_err());
+        let sidecar = Sidecar::new(vec![function("server.go", "main", 1, 1)]).unwrap();
+        let (root, external) = get_code_root(path, data, &[Arc::new(sidecar)]).unwrap();
+        assert_eq!(root.spaces.len(), 1);
+        assert!(external);
+
+        let (root, external) = get_code_root(Path::new("main.rs"), b"fn main() {}\n", &[]).unwrap();
+        assert_eq!(root.spaces[0].kind, SpaceKind::Function);
+        assert!(!external);
+
+        // Java falls back to rust-code-analysis without a sidecar
+        let java = b"class App {\n    void run() {}\n}\n";
+        let (root, external) = get_code_root(Path::new("App.java"), java, &[]).unwrap();
+        assert!(!external);
+        assert_eq!(root.metrics.ploc, 3.);
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use tracing::debug;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+use crate::error::*;
+
+// Level used for all the formats, a balance between speed and size
+const GZIP_LEVEL: u32 = 6;
+const ZSTD_LEVEL: i32 = 3;
+const XZ_LEVEL: u32 = 6;
+
+/// Compression formats of the coverage reports and of the outputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    // Detect the compression of a content from its first bytes
+    fn from_magic(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    // Get the compression of an output from the extension of its path
+    pub(crate) fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+}
+
+// Decompress a report if it starts with the magic bytes of gzip, zstd or xz
+// Other contents are returned as they are
+fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    let compression = match Compression::from_magic(&bytes) {
+        Some(compression) => compression,
+        None => return Ok(bytes),
+    };
+    debug!("Decompressing {:?} report...", compression);
+    let mut res = vec![];
+    match compression {
+        Compression::Gzip => MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut res)?,
+        Compression::Zstd => {
+            zstd::stream::read::Decoder::new(bytes.as_slice())?.read_to_end(&mut res)?
+        }
+        Compression::Xz => XzDecoder::new_multi_decoder(bytes.as_slice()).read_to_end(&mut res)?,
+    };
+    Ok(res)
+}
+
+// Read the text of a report, decompressing it if needed
+pub(crate) fn read_text<R: Read>(mut reader: R) -> Result<String> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    String::from_utf8(decompress(bytes)?)
+        .map_err(|e| Error::from(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+/// Writer of an output, compressed when its extension is .gz, .zst or .xz
+/// It must be finished to write the end of the stream and to get its errors
+pub(crate) enum CompressedWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(XzEncoder<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    // Write the end of the stream and flush the inner writer
+    pub(crate) fn finish(self) -> Result<()> {
+        let mut inner = match self {
+            CompressedWriter::Plain(inner) => inner,
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+            CompressedWriter::Xz(encoder) => encoder.finish()?,
+        };
+        inner.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(inner) => inner.write(buf),
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+            CompressedWriter::Xz(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::Plain(inner) => inner.flush(),
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+            CompressedWriter::Xz(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Wrap the writer of an output in an encoder when the extension of its path is .gz, .zst or .xz
+pub(crate) fn compressed_writer<W: Write>(path: &Path, inner: W) -> Result<CompressedWriter<W>> {
+    Ok(match Compression::from_extension(path) {
+        None => CompressedWriter::Plain(inner),
+        Some(Compression::Gzip) => {
+            CompressedWriter::Gzip(GzEncoder::new(inner, flate2::Compression::new(GZIP_LEVEL)))
+        }
+        Some(Compression::Zstd) => CompressedWriter::Zstd(zstd::Encoder::new(inner, ZSTD_LEVEL)?),
+        Some(Compression::Xz) => CompressedWriter::Xz(XzEncoder::new(inner, XZ_LEVEL)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "{\"source_files\": []}";
+
+    #[test]
+    fn test_decompress() {
+        // A folder for each process, the tests of several checkouts can run at the same time
+        let dir = std::env::temp_dir().join(format!("wcc_compress_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        [
+            "report.json.gz",
+            "report.json.zst",
+            "report.json.xz",
+            "report.json",
+        ]
+        .iter()
+        .for_each(|name| {
+            let path = dir.join(name);
+            let mut writer =
+                compressed_writer(&path, std::fs::File::create(&path).unwrap()).unwrap();
+            writer.write_all(TEXT.as_bytes()).unwrap();
+            writer.finish().unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(
+                Compression::from_magic(&bytes),
+                Compression::from_extension(&path)
+            );
+            assert_eq!(read_text(bytes.as_slice()).unwrap(), TEXT);
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_finish_errors() {
+        // The end of the stream can not be written
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::WriteZero.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        ["out.json.gz", "out.json.zst", "out.json.xz"]
+            .iter()
+            .for_each(|name| {
+                let writer = compressed_writer(Path::new(name), Full).unwrap();
+                assert!(matches!(
+                    writer.finish(),
+                    Err(Error::WrongFile(e)) if e.kind() == io::ErrorKind::WriteZero
+                ));
+            });
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        // A truncated gzip stream
+        let error = read_text([0x1f, 0x8b, 0x08].as_slice()).unwrap_err();
+        assert!(error
+            .with_file("cov.json.gz")
+            .to_string()
+            .contains("cov.json.gz"));
+    }
+}
@@ -1,34 +1,129 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::sync::PoisonError;
 
 use csv;
 use serde_json;
 use thiserror::Error;
 
+/// Position of a value inside a coverage report
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Location {
+    /// Index of a line in the coverage array of a file
+    Index(usize),
+    /// JSON pointer to the value inside the report
+    Pointer(String),
+    /// Line of a report written as text
+    Line(usize),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Index(i) => write!(f, "coverage index {}", i),
+            Location::Pointer(p) => write!(f, "{:?}", p),
+            Location::Line(l) => write!(f, "line {}", l),
+        }
+    }
+}
+
+// Escape a key to be used as a token of a JSON pointer
+pub(crate) fn pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+// Suffix with the file of the error, empty when it is still unknown
+fn in_file(file: &str) -> String {
+    if file.is_empty() {
+        String::new()
+    } else {
+        format!(" in {}", file)
+    }
+}
+
 /// Customized error messages using thiserror library
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Error while reading Files from project folder")]
     WrongFile(#[from] std::io::Error),
+    #[error("Error while reading {}", .file.display())]
+    #[non_exhaustive]
+    ReadingFileError {
+        file: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error while writing {}", .file.display())]
+    #[non_exhaustive]
+    WritingFileError {
+        file: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
     #[error("Error while reading json")]
     WrongJSONFile(#[from] serde_json::Error),
-    #[error("Error while converting JSON value to a type")]
-    ConversionError(),
-    #[error("Error while getting value from hashmap")]
-    HashMapError(),
-    #[error("Failing reading JSON from string")]
-    ReadingJSONError(),
-    #[error("Error while computing Metrics")]
-    MetricsError(),
-    #[error("Error while guessing language")]
-    LanguageError(),
+    #[error("Error while parsing the JSON report {}", .file.display())]
+    #[non_exhaustive]
+    ParsingJSONError {
+        file: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Error while parsing the XML report{}", in_file(.file))]
+    #[non_exhaustive]
+    ParsingXMLError {
+        file: String,
+        #[source]
+        source: roxmltree::Error,
+    },
+    #[error("Error while converting value at {location}{} to {expected}", in_file(.file))]
+    #[non_exhaustive]
+    ConversionError {
+        file: String,
+        location: Location,
+        expected: &'static str,
+    },
+    #[error("Error while getting {key} from hashmap")]
+    #[non_exhaustive]
+    HashMapError { key: String },
+    #[error("Failing reading JSON: {pointer:?} not found{}", in_file(.file))]
+    #[non_exhaustive]
+    ReadingJSONError { file: String, pointer: String },
+    #[error("Error while computing Metrics of {}", .file.display())]
+    #[non_exhaustive]
+    MetricsError { file: PathBuf },
+    #[error("Error while guessing language of {}, its complexity can be given with a sidecar", .file.display())]
+    #[non_exhaustive]
+    LanguageError { file: PathBuf },
     #[error("Error while writing on csv")]
     WritingError(#[from] csv::Error),
-    #[error("Error during concurrency")]
-    ConcurrentError(),
-    #[error("Json Type is not supported! Only coveralls and covdir are supported.")]
-    TypeError(),
-    #[error("Error while converting path to string")]
-    PathConversionError(),
+    #[error("Error during concurrency: {reason}")]
+    #[non_exhaustive]
+    ConcurrentError { reason: String },
+    #[error("Error while converting path {path:?} to string")]
+    #[non_exhaustive]
+    PathConversionError { path: PathBuf },
+    #[error("Space starting at line {line}{} has no name", in_file(.file))]
+    #[non_exhaustive]
+    SpaceNameError { file: String, line: usize },
+    #[error("Coverage of {file} does not match the source, {check} check failed: {detail}")]
+    #[non_exhaustive]
+    ValidationError {
+        file: String,
+        check: &'static str,
+        detail: String,
+    },
+    #[error("Cannot detect the format of the coverage report{}: {reason}", in_file(.file))]
+    #[non_exhaustive]
+    FormatDetectionError { file: String, reason: String },
+    #[error("Invalid complexity sidecar{}: {detail}", in_file(.file))]
+    #[non_exhaustive]
+    SidecarError { file: String, detail: String },
+    #[error("Error in the history: {reason}")]
+    #[non_exhaustive]
+    HistoryError { reason: String },
     #[error("Error while locking mutex")]
     MutexError(),
     #[error(
@@ -37,10 +132,139 @@ pub enum Error {
     ThresholdsError(),
     #[error("Error while sending job via sender")]
     SenderError(),
+    #[error("At least one coverage report must be given")]
+    NoCoverageError(),
+    #[error("The number of formats must be one or equal to the number of coverage reports")]
+    FormatsError(),
+    #[error("Only one coverage report can be read from the standard input")]
+    StdinError(),
+    #[error("The {0} output cannot be written to the standard output, only one of the json, csv and markdown outputs can")]
+    StdoutError(&'static str),
+    #[error("Error while formatting html")]
+    FormatError(#[from] std::fmt::Error),
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+impl Error {
+    pub(crate) fn conversion(location: Location, expected: &'static str) -> Self {
+        Error::ConversionError {
+            file: String::new(),
+            location,
+            expected,
+        }
+    }
+
+    pub(crate) fn xml(source: roxmltree::Error) -> Self {
+        Error::ParsingXMLError {
+            file: String::new(),
+            source,
+        }
+    }
+
+    pub(crate) fn reading_json(pointer: impl Into<String>) -> Self {
+        Error::ReadingJSONError {
+            file: String::new(),
+            pointer: pointer.into(),
+        }
+    }
+
+    pub(crate) fn path<A: AsRef<Path>>(path: A) -> Self {
+        Error::PathConversionError {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub(crate) fn sidecar(detail: impl Into<String>) -> Self {
+        Error::SidecarError {
+            file: String::new(),
+            detail: detail.into(),
+        }
+    }
+
+    pub(crate) fn detection(reason: impl Into<String>) -> Self {
+        Error::FormatDetectionError {
+            file: String::new(),
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn history(reason: impl Into<String>) -> Self {
+        Error::HistoryError {
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn space_name(line: usize) -> Self {
+        Error::SpaceNameError {
+            file: String::new(),
+            line,
+        }
+    }
+
+    /// Attach the file being processed to an error that does not know it yet
+    /// Errors already carrying a file are returned unchanged
+    pub fn with_file<A: AsRef<Path>>(self, path: A) -> Self {
+        let path = path.as_ref();
+        match self {
+            Error::WrongFile(source) => Error::ReadingFileError {
+                file: path.to_path_buf(),
+                source,
+            },
+            Error::WrongJSONFile(source) => Error::ParsingJSONError {
+                file: path.to_path_buf(),
+                source,
+            },
+            Error::ConversionError {
+                file,
+                location,
+                expected,
+            } if file.is_empty() => Error::ConversionError {
+                file: path.display().to_string(),
+                location,
+                expected,
+            },
+            Error::ParsingXMLError { file, source } if file.is_empty() => Error::ParsingXMLError {
+                file: path.display().to_string(),
+                source,
+            },
+            Error::ReadingJSONError { file, pointer } if file.is_empty() => {
+                Error::ReadingJSONError {
+                    file: path.display().to_string(),
+                    pointer,
+                }
+            }
+            Error::SpaceNameError { file, line } if file.is_empty() => Error::SpaceNameError {
+                file: path.display().to_string(),
+                line,
+            },
+            Error::FormatDetectionError { file, reason } if file.is_empty() => {
+                Error::FormatDetectionError {
+                    file: path.display().to_string(),
+                    reason,
+                }
+            }
+            Error::SidecarError { file, detail } if file.is_empty() => Error::SidecarError {
+                file: path.display().to_string(),
+                detail,
+            },
+            error => error,
+        }
+    }
+
+    /// Attach the output being written to an I/O error
+    /// The other errors are returned unchanged
+    pub fn with_output<A: AsRef<Path>>(self, path: A) -> Self {
+        match self {
+            Error::WrongFile(source) => Error::WritingFileError {
+                file: path.as_ref().to_path_buf(),
+                source,
+            },
+            error => error,
+        }
+    }
+}
+
 impl<T> From<PoisonError<T>> for Error {
     fn from(_item: PoisonError<T>) -> Self {
         Error::MutexError()
@@ -48,7 +272,47 @@ impl<T> From<PoisonError<T>> for Error {
 }
 
 impl From<Box<dyn std::any::Any + Send>> for Error {
-    fn from(_item: Box<dyn std::any::Any + Send>) -> Self {
-        Error::ConcurrentError()
+    fn from(item: Box<dyn std::any::Any + Send>) -> Self {
+        // The payload of a panic is a string in most of the cases
+        let reason = item
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| item.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "a thread panicked".into());
+        Error::ConcurrentError { reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_with_file() {
+        let error = Error::conversion(Location::Index(4), "an unsigned integer").with_file("a.rs");
+        assert_eq!(
+            error.to_string(),
+            "Error while converting value at coverage index 4 in a.rs to an unsigned integer"
+        );
+        // The first file attached is kept
+        let error = error.with_file("b.rs");
+        assert!(error.to_string().contains("in a.rs"));
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let error = Error::from(io).with_file("c.rs");
+        assert_eq!(error.to_string(), "Error while reading c.rs");
+        assert_eq!(error.source().unwrap().to_string(), "missing");
+
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let error = Error::from(io).with_output("out.csv");
+        assert_eq!(error.to_string(), "Error while writing out.csv");
+        assert_eq!(error.source().unwrap().to_string(), "denied");
+
+        let error = Error::reading_json("/source_files/1/name").with_file("cov.json");
+        assert_eq!(
+            error.to_string(),
+            "Failing reading JSON: \"/source_files/1/name\" not found in cov.json"
+        );
     }
 }
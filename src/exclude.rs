@@ -0,0 +1,513 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::functions::Suppression;
+
+// Markers of the suppression comments
+const IGNORE: &str = "wcc:ignore";
+const ALLOW: &str = "wcc:allow(";
+
+// Tokens starting the comments of the languages
+const C_COMMENTS: [&str; 2] = ["//", "/*"];
+const PYTHON_COMMENTS: [&str; 1] = ["#"];
+
+// Folders that contain only test code
+const TEST_FOLDERS: [&str; 3] = ["tests", "test", "__tests__"];
+
+// Extensions of the javascript and typescript files
+const JS_EXTENSIONS: [&str; 4] = ["js", "jsx", "ts", "tsx"];
+
+/// Check if a file contains only test code using the path conventions
+/// The path must be relative to the project folder
+pub(crate) fn is_test_file(path: &str) -> bool {
+    let path = Path::new(path);
+    let in_test_folder = path
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .any(|c| TEST_FOLDERS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+        })
+        .unwrap_or(false);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((&name, ""));
+    let test_name = match ext {
+        "py" => stem.starts_with("test_") || stem.ends_with("_test"),
+        "go" => stem.ends_with("_test"),
+        ext if JS_EXTENSIONS.contains(&ext) => stem.ends_with(".test") || stem.ends_with(".spec"),
+        _ => false,
+    };
+    in_test_folder || test_name
+}
+
+/// Get the ranges of lines with test code inside a source file, starting from 1
+/// Rust items marked with #[test] or #[cfg(test)], also inside all and any, and python test_* functions and Test* classes are found
+pub(crate) fn get_test_lines(path: &Path, data: &[u8]) -> Vec<(usize, usize)> {
+    let source = String::from_utf8_lossy(data);
+    let lines = source.lines().collect::<Vec<&str>>();
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("rs") => rust_test_lines(&lines),
+        Some("py") => python_test_lines(&lines),
+        _ => Vec::new(),
+    }
+}
+
+// Split the arguments of a cfg predicate at the commas outside parentheses and strings
+fn cfg_arguments(arguments: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut start = 0;
+    arguments.char_indices().for_each(|(i, c)| match c {
+        '"' => in_string = !in_string,
+        '(' if !in_string => depth += 1,
+        ')' if !in_string => depth -= 1,
+        ',' if !in_string && depth == 0 => {
+            res.push(arguments[start..i].trim());
+            start = i + 1;
+        }
+        _ => {}
+    });
+    res.push(arguments[start..].trim());
+    res
+}
+
+// Check if a cfg predicate enables code for the tests, e.g. test, all(test, unix) or any(test, doc)
+fn is_test_cfg(predicate: &str) -> bool {
+    let predicate = predicate.trim();
+    let arguments = |name: &str| {
+        predicate
+            .strip_prefix(name)
+            .map(str::trim_start)
+            .and_then(|p| p.strip_prefix('('))
+            .and_then(|p| p.strip_suffix(')'))
+    };
+    match (arguments("all"), arguments("any")) {
+        (Some(arguments), _) | (_, Some(arguments)) => {
+            cfg_arguments(arguments).iter().any(|p| is_test_cfg(p))
+        }
+        _ => predicate == "test",
+    }
+}
+
+// Check if an attribute marks test code, e.g. #[test], #[cfg(test)] or #[tokio::test]
+fn is_test_attribute(line: &str) -> bool {
+    let Some(attribute) = line.trim().strip_prefix("#[") else {
+        return false;
+    };
+    let name = attribute
+        .split(['(', ']'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let cfg = attribute
+        .strip_prefix("cfg(")
+        .and_then(|a| a.trim_end().strip_suffix(")]"));
+    name == "test" || name.ends_with("::test") || matches!(cfg, Some(cfg) if is_test_cfg(cfg))
+}
+
+fn rust_test_lines(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_test_attribute(lines[i]) {
+            i += 1;
+            continue;
+        }
+        // Include the attributes written before the test one
+        let mut start = i;
+        while start > 0 && lines[start - 1].trim().starts_with("#[") {
+            start -= 1;
+        }
+        let end = rust_item_end(lines, i);
+        ranges.push((start + 1, end + 1));
+        i = end + 1;
+    }
+    ranges
+}
+
+// Get the number of # of a raw string starting at an r, e.g. r"...", r#"..."# or br"..."
+// None when the r is not the start of a raw string, e.g. the end of an identifier
+fn raw_string_hashes(chars: &[char], i: usize) -> Option<usize> {
+    let is_ident = |c: &char| c.is_alphanumeric() || *c == '_';
+    let prefix = match i.checked_sub(1).map(|p| chars[p]) {
+        Some('b') => i.checked_sub(2).map(|p| chars[p]),
+        prefix => prefix,
+    };
+    if matches!(prefix, Some(c) if is_ident(&c)) {
+        return None;
+    }
+    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+    (chars.get(i + 1 + hashes) == Some(&'"')).then_some(hashes)
+}
+
+// Find the last line of the item starting at a line
+// The item ends with its closing brace or with a semicolon if it has no body
+fn rust_item_end(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0;
+    let mut brackets = 0;
+    let mut opened = false;
+    let mut in_string = false;
+    let mut in_comment = false;
+    // Number of # of the raw string, e.g. 1 for r#"...", the escapes are not parsed inside it
+    let mut in_raw_string: Option<usize> = None;
+    for (n, line) in lines.iter().enumerate().skip(start) {
+        let chars = line.chars().collect::<Vec<char>>();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if in_comment {
+                if c == '*' && next == Some('/') {
+                    in_comment = false;
+                    i += 1;
+                }
+            } else if let Some(hashes) = in_raw_string {
+                if c == '"' && chars[i + 1..].iter().take_while(|c| **c == '#').count() >= hashes {
+                    in_raw_string = None;
+                    i += hashes;
+                }
+            } else if in_string {
+                if c == '\\' {
+                    i += 1;
+                } else if c == '"' {
+                    in_string = false;
+                }
+            } else {
+                match (c, next) {
+                    ('/', Some('/')) => break,
+                    ('/', Some('*')) => {
+                        in_comment = true;
+                        i += 1;
+                    }
+                    ('r', _) => {
+                        if let Some(hashes) = raw_string_hashes(&chars, i) {
+                            in_raw_string = Some(hashes);
+                            i += hashes + 1;
+                        }
+                    }
+                    ('"', _) => in_string = true,
+                    // Skip the char literals, the lifetimes have no closing quote
+                    ('\'', _) if chars.get(i + 2) == Some(&'\'') => i += 2,
+                    ('\'', Some('\\')) => {
+                        while i + 1 < chars.len() && chars[i + 1] != '\'' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                    ('[', _) => brackets += 1,
+                    (']', _) => brackets -= 1,
+                    ('{', _) => {
+                        depth += 1;
+                        opened = true;
+                    }
+                    ('}', _) => {
+                        depth -= 1;
+                        if opened && depth == 0 {
+                            return n;
+                        }
+                    }
+                    (';', _) if !opened && brackets == 0 => return n,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+    }
+    lines.len() - 1
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_python_test(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix("async ").unwrap_or(line);
+    line.starts_with("def test_") || line.starts_with("class Test")
+}
+
+fn python_test_lines(lines: &[&str]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_python_test(lines[i]) {
+            i += 1;
+            continue;
+        }
+        let indent = indentation(lines[i]);
+        // Include the decorators written before the definition
+        let mut start = i;
+        while start > 0
+            && lines[start - 1].trim_start().starts_with('@')
+            && indentation(lines[start - 1]) == indent
+        {
+            start -= 1;
+        }
+        // The signature can be split in multiple lines
+        let mut parens = 0i32;
+        let mut end = i;
+        for (n, line) in lines.iter().enumerate().skip(i) {
+            parens += line.matches(['(', '[']).count() as i32;
+            parens -= line.matches([')', ']']).count() as i32;
+            end = n;
+            if parens <= 0 {
+                break;
+            }
+        }
+        // The body has a greater indentation, the blank lines at the end are not included
+        let mut n = end + 1;
+        while n < lines.len() && (lines[n].trim().is_empty() || indentation(lines[n]) > indent) {
+            if !lines[n].trim().is_empty() {
+                end = n;
+            }
+            n += 1;
+        }
+        ranges.push((start + 1, end + 1));
+        i = end + 1;
+    }
+    ranges
+}
+
+/// Replace the lines in the ranges with empty lines
+/// The number of lines does not change, so the coverage arrays are still aligned
+pub(crate) fn blank_lines(data: &[u8], ranges: &[(usize, usize)]) -> Vec<u8> {
+    data.split_inclusive(|b| *b == b'\n')
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let excluded = ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&(i + 1)));
+            match (excluded, line.last()) {
+                (false, _) => line,
+                (true, Some(b'\n')) => &line[line.len() - 1..],
+                (true, _) => &[],
+            }
+        })
+        .copied()
+        .collect()
+}
+
+/// Mark the lines in the ranges as not instrumented
+/// In covdir format not instrumented lines are -1 instead of null
+pub(crate) fn exclude_lines(arr: &mut [Value], ranges: &[(usize, usize)], is_covdir: bool) {
+    ranges.iter().for_each(|(start, end)| {
+        arr.iter_mut()
+            .skip(start.saturating_sub(1))
+            .take(end + 1 - start)
+            .for_each(|line| {
+                *line = if is_covdir {
+                    Value::from(-1)
+                } else {
+                    Value::Null
+                }
+            });
+    });
+}
+
+/// Get the tokens starting the comments in a source file from its extension
+/// The languages other than python use the comments of C
+pub(crate) fn comment_tokens(path: &Path) -> &'static [&'static str] {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("py") => &PYTHON_COMMENTS,
+        _ => &C_COMMENTS,
+    }
+}
+
+// Parse a suppression comment, e.g. // wcc:allow(crap, skunk) parser state machine
+// The text after the marker is the reason
+fn parse_suppression(line: &str, comments: &[&str]) -> Option<Suppression> {
+    let (index, marker) = [IGNORE, ALLOW]
+        .iter()
+        .filter_map(|marker| line.find(marker).map(|i| (i, *marker)))
+        .min()?;
+    // The marker must be inside a comment of the language, or inside a block comment of C
+    let before = &line[..index];
+    let in_block = comments.contains(&"/*") && before.trim_start().starts_with('*');
+    if !(in_block || comments.iter().any(|token| before.contains(token))) {
+        return None;
+    }
+    let rest = &line[index + marker.len()..];
+    let (metrics, rest) = if marker == ALLOW {
+        let (metrics, rest) = rest.split_once(')')?;
+        let metrics = metrics
+            .split(',')
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect::<Vec<String>>();
+        if metrics.is_empty() {
+            return None;
+        }
+        (metrics, rest)
+    } else {
+        (Vec::new(), rest)
+    };
+    let reason = rest
+        .trim_end_matches("*/")
+        .trim_start_matches(|c: char| c.is_whitespace() || c == ':' || c == '-')
+        .trim();
+    Some(Suppression::new(metrics, reason.into()))
+}
+
+// Check if a line can be between a suppression comment and its function
+fn is_attached(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "/*", "*", "#", "@"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// Get the suppression attached to a function starting at a line, starting from 1
+/// The comment can be written at the end of the first line of the function or right above it,
+/// also between the attributes, decorators and doc comments
+/// The markers are read only inside the comments of the language of the file
+pub(crate) fn get_suppression(
+    lines: &[&str],
+    start_line: usize,
+    comments: &[&str],
+) -> Option<Suppression> {
+    let first = start_line.checked_sub(1)?;
+    if let Some(suppression) = lines
+        .get(first)
+        .and_then(|l| parse_suppression(l, comments))
+    {
+        return Some(suppression);
+    }
+    lines[..first.min(lines.len())]
+        .iter()
+        .rev()
+        .take_while(|line| is_attached(line))
+        .find_map(|line| parse_suppression(line, comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_test_file() {
+        assert!(is_test_file("tests/integration.rs"));
+        assert!(is_test_file("src/__tests__/app.js"));
+        assert!(is_test_file("pkg/test_parser.py"));
+        assert!(is_test_file("pkg/parser_test.py"));
+        assert!(is_test_file("pkg/server_test.go"));
+        assert!(is_test_file("src/app.test.ts"));
+        assert!(is_test_file("src/app.spec.js"));
+        assert!(!is_test_file("src/testing.rs"));
+        assert!(!is_test_file("src/test_utils.rs"));
+        assert!(!is_test_file("src/app.ts"));
+    }
+
+    #[test]
+    fn test_test_lines() {
+        let rust = "fn main() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it_works() {\n        let s = \"}\";\n        assert_eq!(s, \"}\");\n    }\n}\n\n#[test]\nfn other() { let c = '{'; }\n";
+        assert_eq!(
+            get_test_lines(Path::new("a.rs"), rust.as_bytes()),
+            vec![(3, 10), (12, 13)]
+        );
+        let rust =
+            "#[cfg(test)]\nuse std::fs;\n\n#[tokio::test]\n#[ignore]\nasync fn slow() {\n}\n";
+        assert_eq!(
+            get_test_lines(Path::new("a.rs"), rust.as_bytes()),
+            vec![(1, 2), (4, 7)]
+        );
+        // The braces inside the raw strings do not close the items
+        let rust = r##"#[test]
+fn raw() {
+    let s = r#"}" \" {"#;
+    let b = br"}";
+    let r#type = r"\";
+}
+
+fn main() {}
+"##;
+        assert_eq!(
+            get_test_lines(Path::new("a.rs"), rust.as_bytes()),
+            vec![(1, 6)]
+        );
+        let rust = "#[cfg(all(test, feature = \"a,b\"))]\nmod a {}\n#[cfg(any(unix, all(test, not(miri))))]\nmod b {}\n#[cfg(not(test))]\nmod c {}\n#[cfg(all(not(test), unix))]\nmod d {}\n#[cfg(feature = \"test\")]\nmod e {}\n";
+        assert_eq!(
+            get_test_lines(Path::new("a.rs"), rust.as_bytes()),
+            vec![(1, 2), (3, 4)]
+        );
+        let python = "def f():\n    pass\n\n@pytest.mark.slow\ndef test_f(\n    x,\n):\n    assert f()\n\n    assert x\n\nclass TestF:\n    def test_g(self):\n        pass\n";
+        assert_eq!(
+            get_test_lines(Path::new("a.py"), python.as_bytes()),
+            vec![(4, 10), (12, 14)]
+        );
+    }
+
+    #[test]
+    fn test_get_suppression() {
+        let rust = comment_tokens(Path::new("a.rs"));
+        let lines = [
+            "// wcc:allow(crap, skunk) parser state machine",
+            "#[inline]",
+            "fn parse() {",
+            "}",
+            "fn run() { // wcc:ignore",
+            "}",
+            "",
+            "fn other() {",
+            "    let s = \"wcc:ignore\";",
+            "}",
+            "#[doc = \"wcc:ignore\"]",
+            "fn doc() {",
+            "}",
+            "/*",
+            " * wcc:ignore generated",
+            " */",
+            "fn generated() {",
+            "}",
+        ];
+        let suppression = get_suppression(&lines, 3, rust).unwrap();
+        assert_eq!(suppression.metrics, vec!["crap", "skunk"]);
+        assert_eq!(suppression.reason, "parser state machine");
+        let suppression = get_suppression(&lines, 5, rust).unwrap();
+        assert!(suppression.metrics.is_empty());
+        assert!(suppression.reason.is_empty());
+        assert!(get_suppression(&lines, 8, rust).is_none());
+        assert!(get_suppression(&lines, 9, rust).is_none());
+        // The attributes are not comments
+        assert!(get_suppression(&lines, 12, rust).is_none());
+        let suppression = get_suppression(&lines, 17, rust).unwrap();
+        assert_eq!(suppression.reason, "generated");
+
+        let python = comment_tokens(Path::new("a.py"));
+        let lines = [
+            "# wcc:allow() empty",
+            "def f():",
+            "    pass",
+            "def g():  # wcc:allow(crap) legacy",
+            "    pass",
+            "def h(s=\"// wcc:ignore\"):",
+            "    pass",
+        ];
+        assert!(get_suppression(&lines, 2, python).is_none());
+        let suppression = get_suppression(&lines, 4, python).unwrap();
+        assert_eq!(suppression.metrics, vec!["crap"]);
+        assert_eq!(suppression.reason, "legacy");
+        // The comments of C are not comments in python
+        assert!(get_suppression(&lines, 6, python).is_none());
+    }
+
+    #[test]
+    fn test_exclude_lines() {
+        let data = b"a\nb\nc\nd";
+        assert_eq!(blank_lines(data, &[(2, 3)]), b"a\n\n\nd");
+        assert_eq!(blank_lines(data, &[(4, 4)]), b"a\nb\nc\n");
+        let mut arr = vec![Value::from(1); 4];
+        exclude_lines(&mut arr, &[(2, 3)], false);
+        assert_eq!(
+            arr,
+            vec![Value::from(1), Value::Null, Value::Null, Value::from(1)]
+        );
+        let mut arr = vec![Value::from(1); 2];
+        exclude_lines(&mut arr, &[(2, 5)], true);
+        assert_eq!(arr, vec![Value::from(1), Value::from(-1)]);
+    }
+}
@@ -1,20 +1,19 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::fs;
 use std::path::*;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use crossbeam::channel::{unbounded, Receiver, Sender};
+use rust_code_analysis::read_file;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::error::*;
-use crate::metrics::crap::*;
-use crate::metrics::sifis::*;
-use crate::metrics::skunk::*;
+use crate::exclude::exclude_lines;
 use crate::utility::*;
+use crate::validate::validate_coverage;
 
 /// Struct containing all the metrics
 #[derive(Clone, Default, Debug, Serialize, Deserialize, Copy, PartialEq)]
@@ -25,6 +24,18 @@ pub struct Metrics {
     pub skunk: f64,
     pub is_complex: bool,
     pub coverage: f64,
+    /// Sum of the complexity used to compute the metrics
+    #[serde(default)]
+    pub complexity: f64,
+    /// Physical lines of code
+    #[serde(default)]
+    pub ploc: f64,
+    /// Instrumented lines with at least one hit
+    #[serde(default)]
+    pub covered_lines: f64,
+    /// Lines instrumented by the coverage tool
+    #[serde(default)]
+    pub instrumented_lines: f64,
 }
 
 impl Metrics {
@@ -43,6 +54,7 @@ impl Metrics {
             skunk,
             is_complex,
             coverage,
+            ..Default::default()
         }
     }
 
@@ -54,6 +66,10 @@ impl Metrics {
             skunk: f64::MAX,
             is_complex: false,
             coverage: 100.0,
+            complexity: f64::MAX,
+            ploc: f64::MAX,
+            covered_lines: f64::MAX,
+            instrumented_lines: f64::MAX,
         }
     }
 
@@ -81,6 +97,39 @@ impl Metrics {
         self.coverage = coverage;
         self
     }
+    pub fn complexity(mut self, complexity: f64) -> Self {
+        self.complexity = complexity;
+        self
+    }
+    pub fn ploc(mut self, ploc: f64) -> Self {
+        self.ploc = ploc;
+        self
+    }
+    pub fn covered_lines(mut self, covered_lines: f64) -> Self {
+        self.covered_lines = covered_lines;
+        self
+    }
+    pub fn instrumented_lines(mut self, instrumented_lines: f64) -> Self {
+        self.instrumented_lines = instrumented_lines;
+        self
+    }
+}
+
+/// Source file with the merged coverage of its lines, used to annotate the html report
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct SourceCoverage {
+    pub path: PathBuf,
+    /// Hits of each line, null or negative when the line is not instrumented
+    pub lines: Vec<Value>,
+}
+
+impl SourceCoverage {
+    pub fn new<A: AsRef<Path>>(path: A, lines: Vec<Value>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lines,
+        }
+    }
 }
 
 /// Struct with all the metrics computed for a single file
@@ -90,6 +139,9 @@ pub struct FileMetrics {
     pub metrics: Metrics,
     pub file: String,
     pub file_path: String,
+    /// Source and coverage of an analyzed file, none for the aggregates
+    #[serde(skip)]
+    pub source: Option<SourceCoverage>,
 }
 
 impl FileMetrics {
@@ -98,14 +150,21 @@ impl FileMetrics {
             metrics,
             file,
             file_path,
+            source: None,
         }
     }
 
+    pub fn source(mut self, source: SourceCoverage) -> Self {
+        self.source = Some(source);
+        self
+    }
+
     pub fn avg(m: Metrics) -> Self {
         Self {
             metrics: m,
             file: "AVG".into(),
             file_path: "-".into(),
+            source: None,
         }
     }
 
@@ -114,6 +173,7 @@ impl FileMetrics {
             metrics: m,
             file: "MIN".into(),
             file_path: "-".into(),
+            source: None,
         }
     }
 
@@ -122,94 +182,54 @@ impl FileMetrics {
             metrics: m,
             file: "MAX".into(),
             file_path: "-".into(),
+            source: None,
         }
     }
 }
 
-type Output = (Vec<FileMetrics>, Vec<String>, Vec<FileMetrics>, f64);
+type Output = (
+    Vec<FileMetrics>,
+    Vec<String>,
+    Vec<FileMetrics>,
+    f64,
+    Diagnostics,
+);
 
 /// This Function get the folder of the repo to analyzed and the path to the json obtained using grcov
 /// if the a file is not found in the json that files will be skipped
-/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage, diagnostics)
+/// The files are analyzed by a single thread with the default options
+/// Unlike get_metrics_concurrent, res has no PROJECT row and project_coverage is a ratio between 0 and 1
+#[deprecated(note = "use get_metrics_concurrent, it merges several reports and takes the options")]
 pub fn get_metrics<A: AsRef<Path>, B: AsRef<Path>>(
     files_path: A,
     json_path: B,
     metric: Complexity,
     thresholds: &[f64],
 ) -> Result<Output> {
-    if thresholds.len() != 4 {
-        return Err(Error::ThresholdsError());
-    }
-    let vec = read_files(files_path.as_ref())?;
-    let mut covered_lines = 0.;
-    let mut tot_lines = 0.;
-    let mut files_ignored: Vec<String> = Vec::<String>::new();
-    let mut res = Vec::<FileMetrics>::new();
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
+    let (mut res, files_ignored, complex_files, _, diagnostics) = get_metrics_concurrent(
+        files_path,
+        &[CoverageReport::new(json_path)],
+        metric,
+        1,
+        thresholds,
+        &Options::default(),
     )?;
-    for path in vec {
-        let p = Path::new(&path);
-        let file = p
-            .file_name()
-            .ok_or(Error::PathConversionError())?
-            .to_str()
-            .ok_or(Error::PathConversionError())?
-            .into();
-        let arr = if let Some(arr) = covs.get(&path) {
-            arr.to_vec()
-        } else {
-            files_ignored.push(path);
-            continue;
-        };
-        let root = get_root(p)?;
-        let (_covered_lines, _tot_lines) = get_covered_lines(&arr, root.start_line, root.end_line)?;
-        covered_lines += _covered_lines;
-        tot_lines += _tot_lines;
-        let (sifis_plain, _sum) = sifis_plain(&root, &arr, metric, false)?;
-        let (sifis_quantized, _sum) = sifis_quantized(&root, &arr, metric, false)?;
-        let crap = crap(&root, &arr, metric, None)?;
-        let skunk = skunk_nosmells(&root, &arr, metric, None)?;
-        let file_path = path.clone().split_off(
-            files_path
-                .as_ref()
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .len(),
-        );
-        let is_complex = check_complexity(sifis_plain, sifis_quantized, crap, skunk, thresholds);
-        let coverage = get_coverage_perc(&arr)? * 100.;
-        let metrics = Metrics::new(
-            sifis_plain,
-            sifis_quantized,
-            crap,
-            skunk,
-            is_complex,
-            f64::round(coverage * 100.0) / 100.0,
-        );
-        res.push(FileMetrics::new(metrics, file, file_path));
-    }
-    let complex_files = res
-        .iter()
-        .filter(|m| m.metrics.is_complex)
-        .cloned()
-        .collect::<Vec<FileMetrics>>();
-    let m = res
-        .iter()
-        .map(|metric| metric.metrics)
-        .collect::<Vec<Metrics>>();
-    let (avg, max, min) = get_cumulative_values(&m);
-    res.push(FileMetrics::avg(avg));
-    res.push(FileMetrics::max(max));
-    res.push(FileMetrics::min(min));
-
-    let project_coverage = covered_lines / tot_lines;
-    Ok((res, files_ignored, complex_files, project_coverage))
+    // The PROJECT row is the first aggregate, it gives the covered and instrumented lines
+    let project_coverage = match res.iter().position(|m| m.file_path == "-") {
+        Some(i) => {
+            let project = res.remove(i).metrics;
+            project.covered_lines / project.instrumented_lines
+        }
+        None => 0.,
+    };
+    Ok((
+        res,
+        files_ignored,
+        complex_files,
+        project_coverage,
+        diagnostics,
+    ))
 }
 
 // Job received by the consumer threads
@@ -218,23 +238,26 @@ struct JobItem {
     chunk: Vec<String>,
     covs: HashMap<String, Vec<Value>>,
     metric: Complexity,
-    prefix: usize,
+    roots: Vec<String>,
     thresholds: Vec<f64>,
+    options: Arc<Options>,
 }
 impl JobItem {
     fn new(
         chunk: Vec<String>,
         covs: HashMap<String, Vec<Value>>,
         metric: Complexity,
-        prefix: usize,
+        roots: Vec<String>,
         thresholds: Vec<f64>,
+        options: Arc<Options>,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            roots,
             thresholds,
+            options,
         }
     }
 }
@@ -243,8 +266,8 @@ impl fmt::Debug for JobItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, roots:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.roots, self.thresholds
         )
     }
 }
@@ -258,6 +281,18 @@ pub(crate) struct JobComposer {
     pub(crate) ploc_sum: f64,
     pub(crate) comp_sum: f64,
 }
+
+impl std::ops::AddAssign for JobComposer {
+    fn add_assign(&mut self, other: Self) {
+        self.covered_lines += other.covered_lines;
+        self.total_lines += other.total_lines;
+        self.sifis_plain_sum += other.sifis_plain_sum;
+        self.sifis_quantized_sum += other.sifis_quantized_sum;
+        self.ploc_sum += other.ploc_sum;
+        self.comp_sum += other.comp_sum;
+    }
+}
+
 pub(crate) type ComposerReceiver = Receiver<Option<JobComposer>>;
 pub(crate) type ComposerSender = Sender<Option<JobComposer>>;
 
@@ -295,6 +330,8 @@ pub(crate) fn composer(receiver: ComposerReceiver) -> Result<JobComposer> {
 pub struct Config {
     pub(crate) res: Arc<Mutex<Vec<FileMetrics>>>,
     pub(crate) files_ignored: Arc<Mutex<Vec<String>>>,
+    pub(crate) files_failed: Arc<Mutex<Vec<FileFailure>>>,
+    pub(crate) mismatches: Arc<Mutex<Vec<Mismatch>>>,
 }
 
 impl Config {
@@ -302,22 +339,115 @@ impl Config {
         Self {
             res: Arc::new(Mutex::new(Vec::<FileMetrics>::new())),
             files_ignored: Arc::new(Mutex::new(Vec::<String>::new())),
+            files_failed: Arc::new(Mutex::new(Vec::<FileFailure>::new())),
+            mismatches: Arc::new(Mutex::new(Vec::<Mismatch>::new())),
         }
     }
     fn clone(&self) -> Self {
         Self {
             res: Arc::clone(&self.res),
             files_ignored: Arc::clone(&self.files_ignored),
+            files_failed: Arc::clone(&self.files_failed),
+            mismatches: Arc::clone(&self.mismatches),
         }
     }
 }
 
+// Get the failed files sorted by path
+pub(crate) fn sorted_failures(files_failed: &Mutex<Vec<FileFailure>>) -> Result<Vec<FileFailure>> {
+    let mut files_failed = files_failed.lock()?.clone();
+    files_failed.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(files_failed)
+}
+
+// Get the mismatches sorted by path
+pub(crate) fn sorted_mismatches(mismatches: &Mutex<Vec<Mismatch>>) -> Result<Vec<Mismatch>> {
+    let mut mismatches = mismatches.lock()?.clone();
+    mismatches.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(mismatches)
+}
+
+// Record the failure of a file when failures are tolerated, otherwise stop the analysis
+pub(crate) fn record_failure(
+    files_failed: &Mutex<Vec<FileFailure>>,
+    file: &str,
+    error: Error,
+    keep_going: bool,
+) -> Result<()> {
+    if !keep_going {
+        return Err(error);
+    }
+    warn!("Skipping {}: {}", file, error);
+    files_failed.lock()?.push(FileFailure::new(file, &error));
+    Ok(())
+}
+
 type JobReceiver = Receiver<Option<JobItem>>;
 
+// Compute the metrics of a file of the job
+// None is returned when the file is skipped because its coverage does not match the source
+// Returns the metrics of the file and the values it adds to the project metrics
+fn file_metrics(
+    job: &JobItem,
+    file: &str,
+    mut arr: Vec<Value>,
+    mismatches: &Mutex<Vec<Mismatch>>,
+) -> Result<Option<(FileMetrics, JobComposer)>> {
+    let path = Path::new(file);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::path(path))?
+        .to_str()
+        .ok_or_else(|| Error::path(path))?
+        .into();
+    let data = read_file(path)?;
+    let (root, excluded, external) = get_root_excluding(
+        path,
+        &data,
+        job.options.exclude_tests,
+        &job.options.complexity_providers,
+    )?;
+    let source = String::from_utf8_lossy(&data);
+    if !validate_coverage(
+        file,
+        &source,
+        &arr,
+        &root,
+        false,
+        external,
+        &job.options.validation,
+        mismatches,
+    )? {
+        return Ok(None);
+    }
+    exclude_lines(&mut arr, &excluded, false);
+    let (covered_lines, tot_lines) = get_covered_lines(&arr, root.start_line, root.end_line)?;
+    debug!(
+        "File: {:?} covered lines: {}  total lines: {}",
+        file, covered_lines, tot_lines
+    );
+    let comp = root.metrics.complexity_sum(job.metric);
+    let file_path = get_file_path(file, &job.roots);
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
+        Tree::get_metrics_from_space(&root, &arr, job.metric, None, &job.thresholds)?;
+    let composer = JobComposer {
+        covered_lines,
+        total_lines: tot_lines,
+        sifis_plain_sum: sp_sum,
+        sifis_quantized_sum: sq_sum,
+        ploc_sum: root.metrics.ploc,
+        comp_sum: comp,
+    };
+    let metrics = FileMetrics::new(m, file_name, file_path).source(SourceCoverage::new(path, arr));
+    Ok(Some((metrics, composer)))
+}
+
 // Consumer function run by ead independent thread
 fn consumer(receiver: JobReceiver, sender_composer: ComposerSender, cfg: &Config) -> Result<()> {
     // Get all shared data
     let files_ignored = &cfg.files_ignored;
+    let files_failed = &cfg.files_failed;
+    let mismatches = &cfg.mismatches;
     let res = &cfg.res;
     let mut composer_output: JobComposer = JobComposer::default();
     while let Ok(job) = receiver.recv() {
@@ -326,54 +456,29 @@ fn consumer(receiver: JobReceiver, sender_composer: ComposerSender, cfg: &Config
         }
         // Cannot panic because of the check immediately above.
         let job = job.unwrap();
-        let chunk = job.chunk;
-        let covs = job.covs;
-        let metric = job.metric;
-        let prefix = job.prefix;
-        let thresholds = job.thresholds;
         // For each file in the chunk received
-        for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+        for file in &job.chunk {
             // Get the coverage vector from the coveralls file
             // if not present the file will be added to the files ignored
-            let arr = match covs.get(&file) {
+            let arr = match job.covs.get(file) {
                 Some(arr) => arr.to_vec(),
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(file.to_string());
                     continue;
                 }
             };
-            let root = get_root(path)?;
-            let (covered_lines, tot_lines) =
-                get_covered_lines(&arr, root.start_line, root.end_line)?;
-            debug!(
-                "File: {:?} covered lines: {}  total lines: {}",
-                file, covered_lines, tot_lines
-            );
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let file_path = file.clone().split_off(prefix);
             // Upgrade all the global variables and add metrics to the result and complex_files
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, &arr, metric, None, &thresholds)?;
-            let mut res = res.lock()?;
-            composer_output.covered_lines += covered_lines;
-            composer_output.total_lines += tot_lines;
-            composer_output.ploc_sum += ploc;
-            composer_output.sifis_plain_sum += sp_sum;
-            composer_output.sifis_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(FileMetrics::new(m, file_name, file_path));
+            // A failed file is not part of the project metrics
+            match file_metrics(&job, file, arr, mismatches).map_err(|e| e.with_file(file)) {
+                Ok(Some((metrics, composer))) => {
+                    res.lock()?.push(metrics);
+                    composer_output += composer;
+                }
+                // The coverage does not match the source and the file is skipped
+                Ok(None) => {}
+                Err(error) => record_failure(files_failed, file, error, job.options.keep_going)?,
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -393,31 +498,31 @@ fn chunk_vector(vec: Vec<String>, n_threads: usize) -> Vec<Vec<String>> {
         .collect::<Vec<Vec<String>>>()
 }
 
-/// This Function get the folder of the repo to analyzed and the path to the coveralls file obtained using grcov
+/// This Function get the folder of the repo to analyzed and the coverage reports obtained using grcov
+/// The reports are merged line by line before computing the metrics
 /// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
+/// The options choose if the files are taken from the project folder or from the coverage report
 /// If the a file is not found in the json that files will be skipped
-/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
-pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage, diagnostics)
+/// Files of the report that are not found on disk are listed in the diagnostics
+pub fn get_metrics_concurrent<A: AsRef<Path>>(
     files_path: A,
-    json_path: B,
+    reports: &[CoverageReport],
     metric: Complexity,
     n_threads: usize,
     thresholds: &[f64],
+    options: &Options,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
-    // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    // Read and merge all the coverage reports
+    let covs = read_coverage_reports(reports)?
+        .into_iter()
+        .map(|(name, lines)| (name, lines.arr))
+        .collect();
+    // Take all the files from the project folder or the coverage report
+    let (vec, covs, files_missing) = get_files(files_path.as_ref(), covs, options)?;
     let mut handlers = vec![];
     // Create a new vonfig with  all needed mutexes
     let cfg = Config::new();
@@ -437,12 +542,9 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    let roots = options.roots(files_path.as_ref())?;
+    // The options are shared by all the jobs
+    let options = Arc::new(options.clone());
     // Send all chunks to the consumers
     chunks
         .iter()
@@ -451,8 +553,9 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
                 chunk.to_vec(),
                 covs.clone(),
                 metric,
-                prefix,
+                roots.clone(),
                 thresholds.to_vec(),
+                Arc::clone(&options),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -480,7 +583,7 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     let mut res = cfg.res.lock()?;
     let composer_output = composer.join()??;
     let project_metric = FileMetrics::new(
-        get_project_metrics(composer_output, None)?,
+        get_project_metrics(composer_output)?,
         "PROJECT".into(),
         "-".into(),
     );
@@ -507,6 +610,11 @@ pub fn get_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         (*files_ignored).clone(),
         complex_files,
         f64::round(project_coverage * 100.) / 100.,
+        Diagnostics {
+            files_missing,
+            files_failed: sorted_failures(&cfg.files_failed)?,
+            mismatches: sorted_mismatches(&cfg.mismatches)?,
+        },
     ))
 }
 
@@ -515,8 +623,9 @@ struct JobItemCovDir {
     chunk: Vec<String>,
     covs: HashMap<String, Covdir>,
     metric: Complexity,
-    prefix: usize,
+    roots: Vec<String>,
     thresholds: Vec<f64>,
+    options: Arc<Options>,
 }
 
 impl JobItemCovDir {
@@ -524,15 +633,17 @@ impl JobItemCovDir {
         chunk: Vec<String>,
         covs: HashMap<String, Covdir>,
         metric: Complexity,
-        prefix: usize,
+        roots: Vec<String>,
         thresholds: Vec<f64>,
+        options: Arc<Options>,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            roots,
             thresholds,
+            options,
         }
     }
 }
@@ -540,14 +651,74 @@ impl fmt::Debug for JobItemCovDir {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, roots:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.roots, self.thresholds
         )
     }
 }
 
 type JobReceiverCovDir = Receiver<Option<JobItemCovDir>>;
 
+// Compute the metrics of a file of the covdir job
+// None is returned when the file is skipped because its coverage does not match the source
+// Returns the metrics of the file and the values it adds to the project metrics
+fn file_metrics_covdir(
+    job: &JobItemCovDir,
+    file: &str,
+    covdir: &Covdir,
+    mismatches: &Mutex<Vec<Mismatch>>,
+) -> Result<Option<(FileMetrics, JobComposer)>> {
+    let path = Path::new(file);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::path(path))?
+        .to_str()
+        .ok_or_else(|| Error::path(path))?
+        .into();
+    let mut arr = covdir.arr.to_vec();
+    let data = read_file(path)?;
+    let (root, excluded, external) = get_root_excluding(
+        path,
+        &data,
+        job.options.exclude_tests,
+        &job.options.complexity_providers,
+    )?;
+    let source = String::from_utf8_lossy(&data);
+    if !validate_coverage(
+        file,
+        &source,
+        &arr,
+        &root,
+        true,
+        external,
+        &job.options.validation,
+        mismatches,
+    )? {
+        return Ok(None);
+    }
+    // The coverage of the report includes the excluded lines
+    let coverage = if excluded.is_empty() {
+        Some(covdir.coverage)
+    } else {
+        exclude_lines(&mut arr, &excluded, true);
+        Some(get_covdir_coverage(&arr)?)
+    };
+    let comp = root.metrics.complexity_sum(job.metric);
+    let file_path = get_file_path(file, &job.roots);
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
+        Tree::get_metrics_from_space(&root, &arr, job.metric, coverage, &job.thresholds)?;
+    let composer = JobComposer {
+        covered_lines: m.covered_lines,
+        total_lines: m.instrumented_lines,
+        sifis_plain_sum: sp_sum,
+        sifis_quantized_sum: sq_sum,
+        ploc_sum: root.metrics.ploc,
+        comp_sum: comp,
+    };
+    let metrics = FileMetrics::new(m, file_name, file_path).source(SourceCoverage::new(path, arr));
+    Ok(Some((metrics, composer)))
+}
+
 // Consumer thread for the covdir format
 fn consumer_covdir(
     receiver: JobReceiverCovDir,
@@ -556,6 +727,8 @@ fn consumer_covdir(
 ) -> Result<()> {
     // Get all shared variables
     let files_ignored = &cfg.files_ignored;
+    let files_failed = &cfg.files_failed;
+    let mismatches = &cfg.mismatches;
     let res = &cfg.res;
     let mut composer_output = JobComposer::default();
     while let Ok(job) = receiver.recv() {
@@ -564,48 +737,29 @@ fn consumer_covdir(
         }
         // Cannot panic because of the check immediately above.
         let job = job.unwrap();
-        let chunk = job.chunk;
-        let covs = job.covs;
-        let metric = job.metric;
-        let prefix = job.prefix;
-        let thresholds = job.thresholds;
         // For each file in the chunk
-        for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+        for file in &job.chunk {
             // Get the coverage vector from the covdir file
             // If not present the file will be added to the files ignored
-            let covdir = match covs.get(&file) {
+            let covdir = match job.covs.get(file) {
                 Some(covdir) => covdir,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(file.to_string());
                     continue;
                 }
             };
-            let arr = &covdir.arr;
-            let coverage = Some(covdir.coverage);
-            let root = get_root(path)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let file_path = file.clone().split_off(prefix);
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, arr, metric, coverage, &thresholds)?;
-            let mut res = res.lock()?;
-            // Update all shared variables
-            composer_output.ploc_sum += ploc;
-            composer_output.sifis_plain_sum += sp_sum;
-            composer_output.sifis_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(FileMetrics::new(m, file_name, file_path));
+            // Update all shared variables, a failed file is not part of the project metrics
+            match file_metrics_covdir(&job, file, covdir, mismatches).map_err(|e| e.with_file(file))
+            {
+                Ok(Some((metrics, composer))) => {
+                    res.lock()?.push(metrics);
+                    composer_output += composer;
+                }
+                // The coverage does not match the source and the file is skipped
+                Ok(None) => {}
+                Err(error) => record_failure(files_failed, file, error, job.options.keep_going)?,
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -616,29 +770,26 @@ fn consumer_covdir(
 
 /// This Function get the folder of the repo to analyzed and the path to the covdir file obtained using grcov
 /// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
+/// The options choose if the files are taken from the project folder or from the coverage report
 /// If the a file is not found in the json that files will be skipped
-/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage, diagnostics)
+/// Files of the report that are not found on disk are listed in the diagnostics
 pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     files_path: A,
     json_path: B,
     metric: Complexity,
     n_threads: usize,
     thresholds: &[f64],
+    options: &Options,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Get all the files from project folder
-    let vec = read_files(files_path.as_ref())?;
     // Read covdir json and obtain all coverage information
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json_covdir(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    let file = read_report(&json_path)?;
+    let covs = read_json_covdir(file, "").map_err(|e| e.with_file(&json_path))?;
+    // Get all the files from the project folder or the coverage report
+    let (vec, covs, files_missing) = get_files(files_path.as_ref(), covs, options)?;
     let mut handlers = vec![];
     // Create a new Config all needed mutexes
     let cfg = Config::new();
@@ -659,19 +810,17 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer_covdir(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    let roots = options.roots(files_path.as_ref())?;
+    // The options are shared by all the jobs
+    let options = Arc::new(options.clone());
     chunks.iter().try_for_each(|chunk| {
         let job = JobItemCovDir::new(
             chunk.to_vec(),
             covs.clone(),
             metric,
-            prefix,
+            roots.clone(),
             thresholds.to_vec(),
+            Arc::clone(&options),
         );
         debug!("Sending job: {:?}", job);
         if let Err(_e) = sender.send(Some(job)) {
@@ -697,17 +846,14 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     }
     let mut files_ignored = cfg.files_ignored.lock()?;
     let mut res = cfg.res.lock()?;
-    let project_coverage = covs
-        .get(&("PROJECT_ROOT".to_string()))
-        .ok_or(Error::HashMapError())?
-        .coverage;
     // Get final  metrics for all the project
     let composer_output = composer.join()??;
     let project_metric = FileMetrics::new(
-        get_project_metrics(composer_output, Some(project_coverage))?,
+        get_project_metrics(composer_output)?,
         "PROJECT".into(),
         "-".into(),
     );
+    let project_coverage = project_metric.metrics.coverage;
     files_ignored.sort();
     res.sort_by(|a, b| a.file.cmp(&b.file));
     // Get AVG MIN MAX and complex files
@@ -729,7 +875,12 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         (*res).clone(),
         (*files_ignored).clone(),
         complex_files,
-        project_coverage,
+        f64::round(project_coverage * 100.) / 100.,
+        Diagnostics {
+            files_missing,
+            files_failed: sorted_failures(&cfg.files_failed)?,
+            mismatches: sorted_mismatches(&cfg.mismatches)?,
+        },
     ))
 }
 
@@ -737,24 +888,75 @@ pub fn get_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
 mod tests {
 
     use super::*;
+    use crate::complexity::Sidecar;
     use crate::utility::compare_float;
 
     const JSON: &str = "./data/seahorse/seahorse.json";
     const COVDIR: &str = "./data/seahorse/covdir.json";
     const PROJECT: &str = "./data/seahorse/";
     const IGNORED: &str = "./data/seahorse/src/action.rs";
+    const TEST_PROJECT: &str = "./data/test_project/";
+    const FAILING_PROJECT: &str = "./data/failing_project/";
+    const FAILING_JSON: &str = "./data/failing_project/coveralls.json";
+    const FAILING_COVDIR: &str = "./data/failing_project/covdir.json";
+    const JAVA_PROJECT: &str = "./data/java_project/";
+    const JACOCO_XML: &str = "./data/java_project/jacoco.xml";
+    const JAVA_SIDECAR: &str = "./data/java_project/complexity.json";
+    const TS_PROJECT: &str = "./data/ts_project/";
+    const ISTANBUL_JSON: &str = "./data/ts_project/coverage-final.json";
+    const PY_PROJECT: &str = "./data/py_project/";
+    const COVERAGEPY_JSON: &str = "./data/py_project/coverage.json";
+    const GO_PROJECT: &str = "./data/go_project/";
+    const GO_PROFILE: &str = "./data/go_project/cover.out";
+    const GO_SIDECAR: &str = "./data/go_project/complexity.json";
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_metrics_sequential() {
+        let (metrics, files_ignored, complex_files, project_coverage, _) =
+            get_metrics(PROJECT, JSON, Complexity::Cyclomatic, &[30., 1.5, 35., 30.]).unwrap();
+        let expected = get_metrics_concurrent(
+            PROJECT,
+            &[CoverageReport::new(JSON)],
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap();
+        // The same metrics without the PROJECT row
+        let project = expected.0.iter().find(|m| m.file == "PROJECT").unwrap();
+        assert_eq!(
+            metrics,
+            expected
+                .0
+                .iter()
+                .filter(|m| m.file != "PROJECT")
+                .cloned()
+                .collect::<Vec<FileMetrics>>()
+        );
+        assert_eq!(metrics.iter().filter(|m| m.file_path == "-").count(), 3);
+        assert_eq!(files_ignored, expected.1);
+        assert_eq!(complex_files, expected.2);
+        // The project coverage is a ratio
+        assert!(compare_float(
+            project_coverage * 100.,
+            project.metrics.coverage
+        ));
+    }
 
     #[test]
     fn test_metrics_coveralls_cyclomatic() {
         let json = Path::new(JSON);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+        let (metrics, files_ignored, _, _, _) = get_metrics_concurrent(
             project,
-            json,
+            &[CoverageReport::new(json)],
             Complexity::Cyclomatic,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -792,12 +994,13 @@ mod tests {
         let json = Path::new(JSON);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent(
+        let (metrics, files_ignored, _, _, _) = get_metrics_concurrent(
             project,
-            json,
+            &[CoverageReport::new(json)],
             Complexity::Cognitive,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -835,12 +1038,13 @@ mod tests {
         let covdir = Path::new(COVDIR);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent_covdir(
+        let (metrics, files_ignored, _, _, _) = get_metrics_concurrent_covdir(
             project,
             covdir,
             Complexity::Cyclomatic,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -878,12 +1082,13 @@ mod tests {
         let covdir = Path::new(COVDIR);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_metrics_concurrent_covdir(
+        let (metrics, files_ignored, _, _, _) = get_metrics_concurrent_covdir(
             project,
             covdir,
             Complexity::Cognitive,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let error = &metrics[3].metrics;
@@ -915,4 +1120,269 @@ mod tests {
         assert!(compare_float(cont.crap, 25.268980546875));
         assert!(compare_float(cont.skunk, 7.549999999999997));
     }
+
+    #[test]
+    fn test_metrics_coveralls_report_discovery() {
+        let options = Options::default()
+            .discovery(FileDiscovery::Report)
+            .source_roots(vec![PathBuf::from(TEST_PROJECT)]);
+        let (metrics, files_ignored, _, _, diagnostics) = get_metrics_concurrent(
+            PROJECT,
+            &[CoverageReport::new(JSON)],
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+
+        assert!(files_ignored.is_empty());
+        assert_eq!(diagnostics.files_missing.len(), 8);
+        assert!(diagnostics
+            .files_missing
+            .contains(&"examples/single_app.rs".to_string()));
+        assert_eq!(metrics.len(), 5);
+        assert_eq!(metrics[0].file, "flag.rs");
+        assert_eq!(metrics[0].file_path, "src/flag.rs");
+        assert!(compare_float(
+            metrics[0].metrics.sifis_plain,
+            34.696335078534034
+        ));
+    }
+
+    #[test]
+    fn test_metrics_coveralls_remapping() {
+        let options = Options::default().replace_prefixes(vec![("src/".into(), "SRC/".into())]);
+        let (_, files_ignored, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            &[CoverageReport::new(JSON)],
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(files_ignored.len(), 8);
+
+        let options = options.ignore_case(true);
+        let (metrics, files_ignored, _, _, _) = get_metrics_concurrent(
+            PROJECT,
+            &[CoverageReport::new(JSON)],
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(files_ignored.len(), 1);
+        assert!(compare_float(metrics[3].metrics.sifis_plain, 0.53125));
+    }
+
+    #[test]
+    fn test_metrics_merged_reports() {
+        let reports = [
+            CoverageReport::new(JSON),
+            CoverageReport::new(COVDIR).format(JsonFormat::Covdir),
+        ];
+        let (metrics, files_ignored, _, project_coverage, _) = get_metrics_concurrent(
+            PROJECT,
+            &reports,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap();
+        let error = &metrics[3].metrics;
+
+        assert_eq!(files_ignored.len(), 1);
+        assert!(compare_float(error.sifis_plain, 0.53125));
+        assert!(compare_float(error.crap, 257.94117647058823));
+        assert!(compare_float(project_coverage, 77.21));
+    }
+
+    #[test]
+    fn test_metrics_coverprofile() {
+        let report = [CoverageReport::new(GO_PROFILE).format(JsonFormat::Coverprofile)];
+        let options = Options::default().strip_prefixes(vec!["example.com/app".into()]);
+        // rust-code-analysis does not parse go, the complexity must be given
+        let error = get_metrics_concurrent(
+            GO_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("server.go"));
+
+        let sidecar = Sidecar::from_json(GO_SIDECAR).unwrap();
+        let (metrics, files_ignored, _, project_coverage, _) = get_metrics_concurrent(
+            GO_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &options.complexity_providers(vec![Arc::new(sidecar)]),
+        )
+        .unwrap();
+        let server = &metrics[0].metrics;
+        assert!(files_ignored.is_empty());
+        assert_eq!(metrics[0].file, "server.go");
+        assert!(compare_float(server.complexity, 6.));
+        assert!(compare_float(server.ploc, 17.));
+        assert!(compare_float(server.coverage, 25.));
+        assert!(compare_float(server.sifis_plain, 1.0588235294117647));
+        assert!(compare_float(server.crap, 21.1875));
+        assert!(compare_float(server.skunk, 18.));
+        assert!(compare_float(project_coverage, 25.));
+    }
+
+    #[test]
+    fn test_metrics_jacoco() {
+        let report = [CoverageReport::new(JACOCO_XML).format(JsonFormat::Jacoco)];
+        let sidecar = Sidecar::from_json(JAVA_SIDECAR).unwrap();
+        // The package path of the report is found under src/main/java
+        let (metrics, files_ignored, _, project_coverage, _) = get_metrics_concurrent(
+            JAVA_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default().complexity_providers(vec![Arc::new(sidecar)]),
+        )
+        .unwrap();
+        let calculator = &metrics[0].metrics;
+        assert!(files_ignored.is_empty());
+        assert_eq!(metrics[0].file, "Calculator.java");
+        assert_eq!(
+            metrics[0].file_path,
+            "src/main/java/com/example/Calculator.java"
+        );
+        assert!(compare_float(calculator.complexity, 6.));
+        assert!(compare_float(calculator.ploc, 15.));
+        assert!(compare_float(calculator.coverage, 66.67));
+        assert!(compare_float(calculator.sifis_plain, 1.6));
+        assert!(compare_float(calculator.crap, 7.333333333333334));
+        assert!(compare_float(project_coverage, 66.67));
+    }
+
+    #[test]
+    fn test_metrics_jacoco_without_sidecar() {
+        // rust-code-analysis is used for the complexity, the lines of code are counted from the source
+        let report = [CoverageReport::new(JACOCO_XML).format(JsonFormat::Jacoco)];
+        let (metrics, _, _, project_coverage, _) = get_metrics_concurrent(
+            JAVA_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap();
+        let calculator = &metrics[0].metrics;
+        assert_eq!(metrics[0].file, "Calculator.java");
+        assert!(compare_float(calculator.ploc, 15.));
+        assert!(calculator.sifis_plain.is_finite());
+        assert!(compare_float(project_coverage, 66.67));
+    }
+
+    #[test]
+    fn test_metrics_istanbul() {
+        let options = Options::default().strip_prefixes(vec!["/home/ci/app".into()]);
+        let (metrics, files_ignored, _, project_coverage, _) = get_metrics_concurrent(
+            TS_PROJECT,
+            &[CoverageReport::new(ISTANBUL_JSON).format(JsonFormat::Istanbul)],
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        let math = &metrics[0].metrics;
+        assert!(files_ignored.is_empty());
+        assert_eq!(metrics[0].file_path, "src/math.ts");
+        assert!(compare_float(math.complexity, 6.));
+        assert!(compare_float(math.coverage, 66.67));
+        assert!(compare_float(math.sifis_plain, 2.));
+        assert!(compare_float(math.crap, 7.333333333333334));
+        assert!(compare_float(project_coverage, 66.67));
+    }
+
+    #[test]
+    fn test_metrics_coveragepy() {
+        let (metrics, files_ignored, _, project_coverage, _) = get_metrics_concurrent(
+            PY_PROJECT,
+            &[CoverageReport::new(COVERAGEPY_JSON).format(JsonFormat::Coveragepy)],
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap();
+        let app = &metrics[0].metrics;
+        assert!(files_ignored.is_empty());
+        assert_eq!(metrics[0].file_path, "pkg/app.py");
+        // The lines excluded with pragma: no cover are not instrumented
+        assert!(compare_float(app.coverage, 75.));
+        assert!(compare_float(app.complexity, 4.));
+        assert!(compare_float(app.crap, 4.25));
+        assert!(compare_float(project_coverage, 75.));
+    }
+
+    #[test]
+    fn test_metrics_keep_going() {
+        let report = [CoverageReport::new(FAILING_JSON)];
+        let error = get_metrics_concurrent(
+            FAILING_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("coverage index 1 in"));
+
+        let (metrics, files_ignored, _, project_coverage, diagnostics) = get_metrics_concurrent(
+            FAILING_PROJECT,
+            &report,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default().keep_going(true),
+        )
+        .unwrap();
+        assert!(files_ignored.is_empty());
+        assert_eq!(diagnostics.files_failed.len(), 1);
+        assert!(diagnostics.files_failed[0].file.ends_with("src/bad.rs"));
+        assert!(diagnostics.files_failed[0]
+            .error
+            .contains("Error while converting value at coverage index 1"));
+        // The failed file is not part of the project metrics
+        assert_eq!(metrics[0].file, "good.rs");
+        assert_eq!(metrics[1].file, "PROJECT");
+        assert!(compare_float(metrics[1].metrics.instrumented_lines, 4.));
+        assert!(compare_float(project_coverage, 100.));
+    }
+
+    #[test]
+    fn test_metrics_covdir_keep_going() {
+        let (metrics, _, _, project_coverage, diagnostics) = get_metrics_concurrent_covdir(
+            FAILING_PROJECT,
+            FAILING_COVDIR,
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default().keep_going(true),
+        )
+        .unwrap();
+        assert_eq!(diagnostics.files_failed.len(), 1);
+        assert!(diagnostics.files_failed[0].file.ends_with("src/bad.rs"));
+        // The project coverage of the report counts the failed file, the computed one does not
+        assert_eq!(metrics[1].file, "PROJECT");
+        assert!(compare_float(metrics[1].metrics.coverage, 100.));
+        assert!(compare_float(project_coverage, 100.));
+    }
 }
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tracing::debug;
+
+use crate::error::*;
+
+// Last layout of the report known, coverage.py increases it when the layout of the files changes
+const LAST_FORMAT: u64 = 3;
+
+// Get a list of line numbers of a file, a missing list is empty
+fn line_numbers(file: &Value, key: &str, pointer: &str) -> Result<Vec<usize>> {
+    let at =
+        |expected| Error::conversion(Location::Pointer(format!("{}/{}", pointer, key)), expected);
+    match file.get(key) {
+        None => Ok(vec![]),
+        Some(lines) => lines
+            .as_array()
+            .ok_or_else(|| at("an array"))?
+            .iter()
+            .map(|line| {
+                line.as_u64()
+                    .filter(|line| *line > 0)
+                    .map(|line| line as usize)
+                    .ok_or_else(|| at("an array of line numbers"))
+            })
+            .collect(),
+    }
+}
+
+// This function read the content of the json report written by coverage.py
+// coverage.py does not count the executions, the executed lines have 1 hit and the missing lines 0
+// The excluded lines and the lines without code are not instrumented
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+pub(crate) fn read_coveragepy(file: &str) -> Result<HashMap<String, Vec<Value>>> {
+    debug!("Reading coverage.py json...");
+    let val: Value = serde_json::from_str(file)?;
+    // The reports written before coverage.py 6 have no format version
+    let format = match val.pointer("/meta/format") {
+        None => 0,
+        Some(format) => format
+            .as_u64()
+            .filter(|format| *format <= LAST_FORMAT)
+            .ok_or_else(|| {
+                Error::conversion(
+                    Location::Pointer("/meta/format".into()),
+                    "a known format version",
+                )
+            })?,
+    };
+    debug!(
+        "coverage.py {} report with format {}",
+        val.pointer("/meta/version")
+            .and_then(|version| version.as_str())
+            .unwrap_or("unknown"),
+        format
+    );
+    let files = val["files"]
+        .as_object()
+        .ok_or_else(|| Error::reading_json("/files"))?;
+    files
+        .iter()
+        .map(|(name, file)| -> Result<(String, Vec<Value>)> {
+            let pointer = format!("/files/{}", pointer_token(name));
+            let executed = line_numbers(file, "executed_lines", &pointer)?;
+            let missing = line_numbers(file, "missing_lines", &pointer)?;
+            let excluded = line_numbers(file, "excluded_lines", &pointer)?;
+            let len = executed.iter().chain(&missing).max().copied().unwrap_or(0);
+            let mut arr = vec![Value::Null; len];
+            missing
+                .iter()
+                .for_each(|line| arr[line - 1] = Value::from(0));
+            executed
+                .iter()
+                .for_each(|line| arr[line - 1] = Value::from(1));
+            excluded
+                .iter()
+                .filter(|line| **line <= len)
+                .for_each(|line| arr[line - 1] = Value::Null);
+            // The paths are written with the separators of the system where coverage.py ran
+            Ok((name.replace('\\', "/"), arr))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"{
+  "meta": {
+    "format": 3,
+    "version": "7.4.0",
+    "timestamp": "2024-01-10T10:00:00",
+    "branch_coverage": false,
+    "show_contexts": false
+  },
+  "files": {
+    "pkg\\app.py": {
+      "executed_lines": [1, 2, 4],
+      "summary": {"covered_lines": 3, "num_statements": 4, "percent_covered": 75.0},
+      "missing_lines": [6],
+      "excluded_lines": [8, 9]
+    },
+    "pkg/__init__.py": {
+      "executed_lines": [],
+      "missing_lines": [],
+      "excluded_lines": []
+    }
+  },
+  "totals": {"covered_lines": 3, "num_statements": 4, "percent_covered": 75.0}
+}"#;
+
+    #[test]
+    fn test_read_coveragepy() {
+        let covs = read_coveragepy(REPORT).unwrap();
+        assert_eq!(
+            covs["pkg/app.py"],
+            vec![
+                Value::from(1),
+                Value::from(1),
+                Value::Null,
+                Value::from(1),
+                Value::Null,
+                Value::from(0),
+            ]
+        );
+        assert!(covs["pkg/__init__.py"].is_empty());
+    }
+
+    #[test]
+    fn test_read_coveragepy_errors() {
+        let error = read_coveragepy(&REPORT.replace(r#""format": 3"#, r#""format": 9"#))
+            .unwrap_err()
+            .with_file("coverage.json");
+        assert_eq!(
+            error.to_string(),
+            "Error while converting value at \"/meta/format\" in coverage.json to a known format version"
+        );
+        let error = read_coveragepy(&REPORT.replace("[6]", "[\"6\"]")).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("/files/pkg\\\\app.py/missing_lines"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tracing::debug;
+
+use crate::error::*;
+
+// Block of a coverage profile with the range of its lines and its hits
+#[derive(Clone, Copy, Debug, Default)]
+struct Block {
+    start: usize,
+    end: usize,
+    count: u64,
+}
+
+// Parse a block written as file:startLine.startCol,endLine.endCol numStmts count
+// Returns the file, the range as written in the profile and the block, None if it has no statements
+fn parse_block(line: &str, number: usize) -> Result<Option<(&str, &str, Block)>> {
+    let at = |expected| Error::conversion(Location::Line(number), expected);
+    let (name, rest) = line
+        .rsplit_once(':')
+        .ok_or_else(|| at("file:range statements count"))?;
+    let (range, statements, count) = match rest.split_whitespace().collect::<Vec<&str>>()[..] {
+        [range, statements, count] => (range, statements, count),
+        _ => return Err(at("file:range statements count")),
+    };
+    let line_of = |position: &str| -> Result<usize> {
+        position
+            .split_once('.')
+            .and_then(|(line, _)| line.parse::<usize>().ok())
+            .ok_or_else(|| at("a line.column position"))
+    };
+    let (start, end) = range
+        .split_once(',')
+        .ok_or_else(|| at("a start,end range"))?;
+    let statements = statements
+        .parse::<u64>()
+        .map_err(|_| at("an unsigned integer"))?;
+    if statements == 0 {
+        return Ok(None);
+    }
+    let block = Block {
+        start: line_of(start)?,
+        end: line_of(end)?,
+        count: count
+            .parse::<u64>()
+            .map_err(|_| at("an unsigned integer"))?,
+    };
+    Ok(Some((name, range, block)))
+}
+
+// Get the array of covered lines of a file from its blocks
+// A line shared by more blocks takes the hits of the most covered one
+fn lines_from_blocks(blocks: &HashMap<&str, Block>) -> Vec<Value> {
+    let len = blocks.values().map(|b| b.end).max().unwrap_or(0);
+    let mut arr = vec![Value::Null; len];
+    blocks.values().for_each(|block| {
+        (block.start.max(1)..=block.end).for_each(|line| {
+            let hits = arr[line - 1].as_u64().unwrap_or(0).max(block.count);
+            arr[line - 1] = Value::from(hits);
+        })
+    });
+    arr
+}
+
+// This function read the content of the coverage profile written by go test -coverprofile
+// The blocks of statements are mapped to the lines they span
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+pub(crate) fn read_coverprofile(file: &str) -> Result<HashMap<String, Vec<Value>>> {
+    debug!("Reading go coverage profile...");
+    let mut lines = file
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let mode = lines
+        .next()
+        .and_then(|(_, line)| line.strip_prefix("mode:"))
+        .ok_or_else(|| Error::conversion(Location::Line(1), "a mode: header"))?
+        .trim()
+        .to_string();
+    // The same block is listed more times when the profiles of several packages are concatenated
+    let mut files = HashMap::<&str, HashMap<&str, Block>>::new();
+    lines.try_for_each(|(number, line)| -> Result<()> {
+        if line.starts_with("mode:") {
+            return Ok(());
+        }
+        if let Some((name, range, block)) = parse_block(line, number)? {
+            let entry = files
+                .entry(name)
+                .or_default()
+                .entry(range)
+                .or_insert(Block { count: 0, ..block });
+            entry.count = if mode == "set" {
+                entry.count.max(block.count)
+            } else {
+                entry.count + block.count
+            };
+        }
+        Ok(())
+    })?;
+    Ok(files
+        .iter()
+        .map(|(name, blocks)| (name.to_string(), lines_from_blocks(blocks)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "mode: count
+example.com/app/server.go:3.14,5.2 2 4
+example.com/app/server.go:5.2,7.3 1 0
+example.com/app/server.go:9.20,10.5 0 7
+example.com/app/util.go:1.1,2.2 1 0
+mode: count
+example.com/app/server.go:3.14,5.2 2 1
+";
+
+    #[test]
+    fn test_read_coverprofile() {
+        let covs = read_coverprofile(PROFILE).unwrap();
+        assert_eq!(covs.len(), 2);
+        let server = &covs["example.com/app/server.go"];
+        // The blocks without statements are not instrumented
+        assert_eq!(server.len(), 7);
+        assert_eq!(
+            server,
+            &vec![
+                Value::Null,
+                Value::Null,
+                Value::from(5),
+                Value::from(5),
+                Value::from(5),
+                Value::from(0),
+                Value::from(0),
+            ]
+        );
+        assert_eq!(covs["example.com/app/util.go"], vec![Value::from(0); 2]);
+
+        let set = read_coverprofile(&PROFILE.replace("count", "set")).unwrap();
+        assert_eq!(set["example.com/app/server.go"][2], Value::from(4));
+    }
+
+    #[test]
+    fn test_read_coverprofile_errors() {
+        let error = read_coverprofile("example.com/app/server.go:3.14,5.2 2 4").unwrap_err();
+        assert!(error.to_string().contains("line 1"));
+        let error = read_coverprofile("mode: set\n\nexample.com/app/server.go:3.14,5.2 2 x")
+            .unwrap_err()
+            .with_file("cover.out");
+        assert_eq!(
+            error.to_string(),
+            "Error while converting value at line 3 in cover.out to an unsigned integer"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use roxmltree::{Document, ParsingOptions};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::error::*;
+use crate::utility::JsonFormat;
+
+// Formats tried in order when the format of a report is not given
+const FORMATS: [JsonFormat; 6] = [
+    JsonFormat::Coveralls,
+    JsonFormat::Covdir,
+    JsonFormat::Coverprofile,
+    JsonFormat::Jacoco,
+    JsonFormat::Istanbul,
+    JsonFormat::Coveragepy,
+];
+
+// Content of a report parsed once to check all the formats
+enum Content<'a> {
+    Json(Value),
+    Xml(Document<'a>),
+    Text(&'a str),
+    // JSON or XML that cannot be parsed, with the error of the parser
+    Broken(&'static str, String),
+}
+
+impl<'a> Content<'a> {
+    fn parse(file: &'a str) -> Self {
+        let trimmed = file.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            return match serde_json::from_str(trimmed) {
+                Ok(json) => Content::Json(json),
+                Err(e) => Content::Broken("json", e.to_string()),
+            };
+        }
+        if trimmed.starts_with('<') {
+            let options = ParsingOptions {
+                allow_dtd: true,
+                ..ParsingOptions::default()
+            };
+            return match Document::parse_with_options(trimmed, options) {
+                Ok(doc) => Content::Xml(doc),
+                Err(e) => Content::Broken("xml", e.to_string()),
+            };
+        }
+        Content::Text(trimmed)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Content::Json(_) => "json",
+            Content::Xml(_) => "xml",
+            Content::Text(_) => "text",
+            Content::Broken(kind, _) => kind,
+        }
+    }
+}
+
+// Check if a report is written in a format, the error tells why it is not
+fn check(format: JsonFormat, content: &Content) -> std::result::Result<(), String> {
+    let not = |what: &str| Err(format!("{} is not {}", content.kind(), what));
+    match (format, content) {
+        (JsonFormat::Coveralls, Content::Json(json)) => match json.get("source_files") {
+            Some(files) if files.is_array() => Ok(()),
+            Some(_) => Err("source_files is not an array".into()),
+            None => Err("no source_files key".into()),
+        },
+        (JsonFormat::Covdir, Content::Json(json)) => {
+            match (json.get("children"), json.get("coveragePercent")) {
+                (Some(_), Some(_)) => Ok(()),
+                (None, _) => Err("no children key".into()),
+                (_, None) => Err("no coveragePercent key".into()),
+            }
+        }
+        (JsonFormat::Istanbul, Content::Json(json)) => match json.as_object() {
+            Some(files) if !files.is_empty() => {
+                match files
+                    .values()
+                    .find(|file| file.get("statementMap").is_none())
+                {
+                    None => Ok(()),
+                    Some(_) => Err("the values of the keys have no statementMap".into()),
+                }
+            }
+            _ => Err("not an object of files".into()),
+        },
+        (JsonFormat::Coveragepy, Content::Json(json)) => {
+            match (json.get("meta"), json.get("files")) {
+                (Some(_), Some(files)) if files.is_object() => Ok(()),
+                (None, _) => Err("no meta key".into()),
+                _ => Err("no files object".into()),
+            }
+        }
+        (JsonFormat::Jacoco, Content::Xml(doc)) => match doc.root_element().tag_name().name() {
+            "report" => Ok(()),
+            root => Err(format!("the root element is <{}>, not <report>", root)),
+        },
+        (JsonFormat::Coverprofile, Content::Text(text)) => match text.lines().next() {
+            Some(line) if line.starts_with("mode:") => Ok(()),
+            _ => Err("the first line is not a mode: header".into()),
+        },
+        (_, Content::Broken(kind, error)) => Err(format!("invalid {}: {}", kind, error)),
+        (JsonFormat::Jacoco, _) => not("xml"),
+        (JsonFormat::Coverprofile, _) => not("a text profile"),
+        _ => not("json"),
+    }
+}
+
+// Hint for the reports written in the formats that cannot be read
+fn unsupported(content: &Content) -> Option<&'static str> {
+    match content {
+        Content::Text(text)
+            if text
+                .lines()
+                .any(|line| line.starts_with("SF:") || line.starts_with("TN:")) =>
+        {
+            Some("it looks like an LCOV report, which is not supported: convert it with grcov -t coveralls")
+        }
+        Content::Xml(doc) if doc.root_element().has_tag_name("coverage") => {
+            Some("it looks like a Cobertura report, which is not supported")
+        }
+        _ => None,
+    }
+}
+
+// Detect the format of a report from its content
+// All the formats are tried, the report must match exactly one of them
+pub(crate) fn detect_format(file: &str) -> Result<JsonFormat> {
+    let content = Content::parse(file);
+    let (matched, rejected): (Vec<_>, Vec<_>) = FORMATS
+        .iter()
+        .map(|format| (*format, check(*format, &content)))
+        .partition(|(_, result)| result.is_ok());
+    match matched[..] {
+        [(format, _)] => {
+            debug!("Coverage report detected as {}", format.name());
+            Ok(format)
+        }
+        [] => {
+            let mut reason = rejected
+                .iter()
+                .map(|(format, result)| {
+                    format!(
+                        "{} ({})",
+                        format.name(),
+                        result
+                            .as_ref()
+                            .err()
+                            .map(String::as_str)
+                            .unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            reason = format!("no format matches, tried {}", reason);
+            if let Some(hint) = unsupported(&content) {
+                reason = format!("{}; {}", reason, hint);
+            }
+            Err(Error::detection(reason))
+        }
+        _ => Err(Error::detection(format!(
+            "more formats match ({}), choose one with -f",
+            matched
+                .iter()
+                .map(|(format, _)| format.name())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |path: &str| detect_format(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(detect("./data/data.json"), JsonFormat::Coveralls);
+        assert_eq!(detect("./data/seahorse/covdir.json"), JsonFormat::Covdir);
+        assert_eq!(
+            detect("./data/go_project/cover.out"),
+            JsonFormat::Coverprofile
+        );
+        assert_eq!(detect("./data/java_project/jacoco.xml"), JsonFormat::Jacoco);
+        assert_eq!(
+            detect("./data/ts_project/coverage-final.json"),
+            JsonFormat::Istanbul
+        );
+        assert_eq!(
+            detect("./data/py_project/coverage.json"),
+            JsonFormat::Coveragepy
+        );
+    }
+
+    #[test]
+    fn test_detect_format_errors() {
+        let error = detect_format("TN:\nSF:src/main.rs\nDA:1,1\nend_of_record\n")
+            .unwrap_err()
+            .with_file("lcov.info");
+        assert_eq!(
+            error.to_string(),
+            "Cannot detect the format of the coverage report in lcov.info: no format matches, \
+             tried coveralls (text is not json), covdir (text is not json), \
+             coverprofile (the first line is not a mode: header), jacoco (text is not xml), \
+             istanbul (text is not json), coveragepy (text is not json); \
+             it looks like an LCOV report, which is not supported: convert it with grcov -t coveralls"
+        );
+        let error = detect_format(r#"{"source_files": [], "children": {}, "coveragePercent": 0}"#)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .ends_with("more formats match (coveralls, covdir), choose one with -f"));
+        let error = detect_format(r#"{"source_files": ["#).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("coveralls (invalid json: EOF while parsing"));
+    }
+}
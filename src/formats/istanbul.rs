@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use crate::error::*;
+
+// Get the line where a location of a statement or a branch starts, None if the location is empty
+// Istanbul writes an empty location for the implicit else of an if
+fn start_line(location: &Value) -> Option<usize> {
+    location
+        .pointer("/start/line")
+        .and_then(|line| line.as_u64())
+        .map(|line| line as usize)
+        .filter(|line| *line > 0)
+}
+
+// Get an object of a file of the report, a missing map is empty
+fn object<'a>(
+    file: &'a Map<String, Value>,
+    key: &str,
+    pointer: &str,
+) -> Result<Option<&'a Map<String, Value>>> {
+    match file.get(key) {
+        None => Ok(None),
+        Some(value) => value
+            .as_object()
+            .map(Some)
+            .ok_or_else(|| Error::conversion(Location::Pointer(pointer.into()), "an object")),
+    }
+}
+
+// Get the hits of each line of a file from its statements and branches
+// A line takes the hits of the most covered statement or branch starting on it, like the lcov reporter of Istanbul
+fn lines_from_maps(file: &Map<String, Value>, pointer: &str) -> Result<Vec<Value>> {
+    let mut hits = HashMap::<usize, u64>::new();
+    let mut add = |line: Option<usize>, count: u64| {
+        if let Some(line) = line {
+            let entry = hits.entry(line).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+    };
+    let statements = object(file, "statementMap", &format!("{}/statementMap", pointer))?;
+    let counts = object(file, "s", &format!("{}/s", pointer))?;
+    if let (Some(statements), Some(counts)) = (statements, counts) {
+        statements
+            .iter()
+            .try_for_each(|(id, location)| -> Result<()> {
+                let at = format!("{}/s/{}", pointer, pointer_token(id));
+                let count = counts
+                    .get(id)
+                    .ok_or_else(|| Error::reading_json(&at))?
+                    .as_u64()
+                    .ok_or_else(|| {
+                        Error::conversion(Location::Pointer(at), "an unsigned integer")
+                    })?;
+                add(start_line(location), count);
+                Ok(())
+            })?;
+    }
+    let branches = object(file, "branchMap", &format!("{}/branchMap", pointer))?;
+    let counts = object(file, "b", &format!("{}/b", pointer))?;
+    if let (Some(branches), Some(counts)) = (branches, counts) {
+        branches.iter().try_for_each(|(id, branch)| -> Result<()> {
+            let at = format!("{}/b/{}", pointer, pointer_token(id));
+            let arms = counts
+                .get(id)
+                .ok_or_else(|| Error::reading_json(&at))?
+                .as_array()
+                .ok_or_else(|| Error::conversion(Location::Pointer(at.clone()), "an array"))?;
+            let locations = branch["locations"].as_array().map(Vec::as_slice);
+            arms.iter()
+                .enumerate()
+                .try_for_each(|(i, count)| -> Result<()> {
+                    let count = count.as_u64().ok_or_else(|| {
+                        Error::conversion(
+                            Location::Pointer(format!("{}/{}", at, i)),
+                            "an unsigned integer",
+                        )
+                    })?;
+                    add(locations.and_then(|l| l.get(i)).and_then(start_line), count);
+                    Ok(())
+                })
+        })?;
+    }
+    let len = hits.keys().max().copied().unwrap_or(0);
+    let mut arr = vec![Value::Null; len];
+    hits.into_iter()
+        .for_each(|(line, count)| arr[line - 1] = Value::from(count));
+    Ok(arr)
+}
+
+// This function read the content of the coverage-final.json written by Istanbul or NYC
+// The statements and the branches are mapped to the line where they start
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+pub(crate) fn read_istanbul(file: &str) -> Result<HashMap<String, Vec<Value>>> {
+    debug!("Reading istanbul json...");
+    let val: Value = serde_json::from_str(file)?;
+    let files = val
+        .as_object()
+        .ok_or_else(|| Error::conversion(Location::Pointer(String::new()), "an object"))?;
+    files
+        .iter()
+        .map(|(key, value)| -> Result<(String, Vec<Value>)> {
+            let pointer = format!("/{}", pointer_token(key));
+            let file = value.as_object().ok_or_else(|| {
+                Error::conversion(Location::Pointer(pointer.clone()), "an object")
+            })?;
+            // The path of the file is written again inside it, the key is used when it is missing
+            let name = match file.get("path") {
+                Some(path) => path.as_str().ok_or_else(|| {
+                    Error::conversion(Location::Pointer(format!("{}/path", pointer)), "a string")
+                })?,
+                None => key,
+            };
+            Ok((name.to_string(), lines_from_maps(file, &pointer)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"{
+  "/home/user/app/src/math.ts": {
+    "path": "/home/user/app/src/math.ts",
+    "statementMap": {
+      "0": {"start": {"line": 2, "column": 2}, "end": {"line": 2, "column": 15}},
+      "1": {"start": {"line": 3, "column": 4}, "end": {"line": 4, "column": 20}},
+      "2": {"start": {"line": 6, "column": 2}, "end": {"line": 6, "column": 11}},
+      "3": {"start": {"line": 6, "column": 12}, "end": {"line": 6, "column": 20}}
+    },
+    "fnMap": {},
+    "branchMap": {
+      "0": {
+        "type": "if",
+        "line": 2,
+        "locations": [
+          {"start": {"line": 2, "column": 2}, "end": {"line": 2, "column": 15}},
+          {"start": {}, "end": {}}
+        ]
+      },
+      "1": {
+        "type": "cond-expr",
+        "line": 8,
+        "locations": [
+          {"start": {"line": 8, "column": 9}, "end": {"line": 8, "column": 10}},
+          {"start": {"line": 9, "column": 9}, "end": {"line": 9, "column": 10}}
+        ]
+      }
+    },
+    "s": {"0": 5, "1": 2, "2": 0, "3": 3},
+    "f": {},
+    "b": {"0": [2, 3], "1": [0, 4]}
+  }
+}"#;
+
+    #[test]
+    fn test_read_istanbul() {
+        let covs = read_istanbul(REPORT).unwrap();
+        assert_eq!(
+            covs["/home/user/app/src/math.ts"],
+            vec![
+                Value::Null,
+                Value::from(5),
+                Value::from(2),
+                // A statement spanning more lines is given to its first line
+                Value::Null,
+                Value::Null,
+                Value::from(3),
+                Value::Null,
+                Value::from(0),
+                Value::from(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_istanbul_errors() {
+        let error = read_istanbul(&REPORT.replace(r#""3": 3"#, r#""3": -3"#))
+            .unwrap_err()
+            .with_file("coverage-final.json");
+        assert_eq!(
+            error.to_string(),
+            "Error while converting value at \"/~1home~1user~1app~1src~1math.ts/s/3\" in coverage-final.json to an unsigned integer"
+        );
+        let error = read_istanbul(&REPORT.replace(r#", "1": [0, 4]"#, "")).unwrap_err();
+        assert!(error.to_string().contains("/b/1"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use roxmltree::{Document, Node, ParsingOptions};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::error::*;
+
+// Counters of a line of a source file in a JaCoCo report
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Line {
+    nr: usize,
+    missed_instructions: u64,
+    covered_instructions: u64,
+    covered_branches: u64,
+}
+
+impl Line {
+    // A line without instructions has no code, like the closing brace of a method
+    fn is_instrumented(&self) -> bool {
+        self.nr > 0 && self.missed_instructions + self.covered_instructions > 0
+    }
+
+    // JaCoCo does not count the executions, a line is hit once if any of its instructions or branches ran
+    // The internal coverage has no branches, a covered branch only makes its line hit like in Istanbul
+    fn hits(&self) -> u64 {
+        u64::from(self.covered_instructions > 0 || self.covered_branches > 0)
+    }
+}
+
+// Get an attribute of a node as an unsigned integer, a missing counter is 0
+fn attribute(doc: &Document, node: Node, name: &str, required: bool) -> Result<u64> {
+    let at = || {
+        let position = doc.text_pos_at(node.range().start);
+        Error::conversion(Location::Line(position.row as usize), "an unsigned integer")
+    };
+    match node.attribute(name) {
+        Some(value) => value.parse::<u64>().map_err(|_| at()),
+        None if required => Err(at()),
+        None => Ok(0),
+    }
+}
+
+// Parse a <line nr mi ci cb> element, the missed branches are not needed
+fn parse_line(doc: &Document, node: Node) -> Result<Line> {
+    Ok(Line {
+        nr: attribute(doc, node, "nr", true)? as usize,
+        missed_instructions: attribute(doc, node, "mi", false)?,
+        covered_instructions: attribute(doc, node, "ci", false)?,
+        covered_branches: attribute(doc, node, "cb", false)?,
+    })
+}
+
+// Get the array of covered lines of a source file from its lines
+// The lines not listed in the report have no code and are not instrumented
+fn lines_from_counters(lines: &[Line]) -> Vec<Value> {
+    let instrumented = lines.iter().filter(|l| l.is_instrumented());
+    let len = instrumented.clone().map(|l| l.nr).max().unwrap_or(0);
+    let mut arr = vec![Value::Null; len];
+    instrumented.for_each(|line| {
+        let hits = arr[line.nr - 1].as_u64().unwrap_or(0).max(line.hits());
+        arr[line.nr - 1] = Value::from(hits);
+    });
+    arr
+}
+
+// This function read the content of the XML report written by JaCoCo
+// The source files are listed inside their package, the path of a file is the package name joined to the file name
+// Return a HashMap with all the files arrays of covered lines using the path to the file as key
+pub(crate) fn read_jacoco(file: &str) -> Result<HashMap<String, Vec<Value>>> {
+    debug!("Reading JaCoCo report...");
+    // JaCoCo reports declare the DTD of the report, it is never loaded
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(file, options).map_err(Error::xml)?;
+    // The same source file is listed once for each group of the report
+    let mut files = HashMap::<String, Vec<Line>>::new();
+    doc.descendants()
+        .filter(|node| node.has_tag_name("package"))
+        .try_for_each(|package| -> Result<()> {
+            let package_name = package.attribute("name").unwrap_or_default();
+            package
+                .children()
+                .filter(|node| node.has_tag_name("sourcefile"))
+                .try_for_each(|sourcefile| -> Result<()> {
+                    let name = sourcefile.attribute("name").unwrap_or_default();
+                    let path = if package_name.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}/{}", package_name.trim_end_matches('/'), name)
+                    };
+                    let lines = files.entry(path).or_default();
+                    sourcefile
+                        .children()
+                        .filter(|node| node.has_tag_name("line"))
+                        .try_for_each(|node| -> Result<()> {
+                            lines.push(parse_line(&doc, node)?);
+                            Ok(())
+                        })
+                })
+        })?;
+    Ok(files
+        .iter()
+        .map(|(path, lines)| (path.to_string(), lines_from_counters(lines)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<!DOCTYPE report PUBLIC "-//JACOCO//DTD Report 1.1//EN" "report.dtd">
+<report name="app">
+  <sessioninfo id="host-1" start="1" dump="2"/>
+  <package name="com/example">
+    <class name="com/example/App" sourcefilename="App.java"/>
+    <sourcefile name="App.java">
+      <line nr="3" mi="0" ci="3" mb="0" cb="0"/>
+      <line nr="5" mi="2" ci="0" mb="0" cb="0"/>
+      <line nr="6" mi="1" ci="4" mb="1" cb="1"/>
+      <counter type="LINE" missed="1" covered="2"/>
+    </sourcefile>
+  </package>
+  <package name="">
+    <sourcefile name="Main.java">
+      <line nr="1" mi="1" ci="0"/>
+      <line nr="2" mi="0" ci="0"/>
+    </sourcefile>
+  </package>
+</report>
+"#;
+
+    #[test]
+    fn test_read_jacoco() {
+        let covs = read_jacoco(REPORT).unwrap();
+        assert_eq!(covs.len(), 2);
+        assert_eq!(
+            covs["com/example/App.java"],
+            vec![
+                Value::Null,
+                Value::Null,
+                Value::from(1),
+                Value::Null,
+                Value::from(0),
+                Value::from(1),
+            ]
+        );
+        assert_eq!(covs["Main.java"], vec![Value::from(0)]);
+    }
+
+    #[test]
+    fn test_read_jacoco_branches() {
+        let report = r#"<report name="app">
+  <package name="">
+    <sourcefile name="Branches.java">
+      <line nr="1" mi="0" ci="2" mb="2" cb="0"/>
+      <line nr="2" mi="3" ci="0" mb="0" cb="1"/>
+      <line nr="3" mi="3" ci="0" mb="2" cb="0"/>
+    </sourcefile>
+  </package>
+  <group name="other">
+    <package name="">
+      <sourcefile name="Branches.java">
+        <line nr="3" mi="0" ci="3" mb="0" cb="2"/>
+      </sourcefile>
+    </package>
+  </group>
+</report>"#;
+        let covs = read_jacoco(report).unwrap();
+        // A covered branch makes the line hit even without covered instructions,
+        // missed branches alone do not, and the most covered group wins
+        assert_eq!(
+            covs["Branches.java"],
+            vec![Value::from(1), Value::from(1), Value::from(1)]
+        );
+        let covs =
+            read_jacoco(&report.replace(r#"ci="3" mb="0" cb="2""#, r#"ci="0" mb="2" cb="0""#))
+                .unwrap();
+        assert_eq!(covs["Branches.java"][2], Value::from(0));
+    }
+
+    #[test]
+    fn test_read_jacoco_errors() {
+        let error = read_jacoco("<report><package>").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Error while parsing the XML report"));
+        let error = read_jacoco(&REPORT.replace(r#"nr="5""#, r#"nr="x""#))
+            .unwrap_err()
+            .with_file("jacoco.xml");
+        assert_eq!(
+            error.to_string(),
+            "Error while converting value at line 9 in jacoco.xml to an unsigned integer"
+        );
+    }
+}
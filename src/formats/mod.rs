@@ -0,0 +1,10 @@
+mod coveragepy;
+mod coverprofile;
+mod detect;
+mod istanbul;
+mod jacoco;
+pub(crate) use coveragepy::*;
+pub(crate) use coverprofile::*;
+pub(crate) use detect::*;
+pub(crate) use istanbul::*;
+pub(crate) use jacoco::*;
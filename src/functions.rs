@@ -1,18 +1,19 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::fs;
 use std::path::*;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 use crossbeam::channel::{unbounded, Receiver};
+use rust_code_analysis::read_file;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use tracing::debug;
 
 use crate::error::*;
+use crate::exclude::{comment_tokens, exclude_lines, get_suppression};
 use crate::files::*;
 use crate::utility::*;
+use crate::validate::validate_coverage;
 
 /// Struct with all the metrics computed for the root
 #[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
@@ -24,6 +25,9 @@ pub struct RootMetrics {
     pub start_line: usize,
     pub end_line: usize,
     pub functions: Vec<FunctionMetrics>,
+    /// Source and coverage of an analyzed file, none for the aggregates
+    #[serde(skip)]
+    pub source: Option<SourceCoverage>,
 }
 impl RootMetrics {
     pub fn new(
@@ -41,9 +45,15 @@ impl RootMetrics {
             start_line,
             end_line,
             functions,
+            source: None,
         }
     }
 
+    pub fn source(mut self, source: SourceCoverage) -> Self {
+        self.source = Some(source);
+        self
+    }
+
     pub fn avg(m: Metrics) -> Self {
         Self {
             metrics: m,
@@ -52,6 +62,7 @@ impl RootMetrics {
             start_line: 0,
             end_line: 0,
             functions: Vec::<FunctionMetrics>::new(),
+            source: None,
         }
     }
 
@@ -63,6 +74,7 @@ impl RootMetrics {
             start_line: 0,
             end_line: 0,
             functions: Vec::<FunctionMetrics>::new(),
+            source: None,
         }
     }
 
@@ -74,10 +86,61 @@ impl RootMetrics {
             start_line: 0,
             end_line: 0,
             functions: Vec::<FunctionMetrics>::new(),
+            source: None,
         }
     }
 }
 
+/// Suppression of the complexity checks written in the source of a function
+/// with a `wcc:ignore` or `wcc:allow(metric, ...)` comment
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Suppression {
+    /// Metrics whose thresholds are not checked, all of them when empty
+    pub metrics: Vec<String>,
+    /// Text written after the annotation
+    pub reason: String,
+}
+
+impl Suppression {
+    pub fn new(metrics: Vec<String>, reason: String) -> Self {
+        Self { metrics, reason }
+    }
+
+    /// Describe the suppression with the metrics and the reason, e.g. allow(crap): parser
+    pub fn describe(&self) -> String {
+        let kind = if self.metrics.is_empty() {
+            "ignore".to_string()
+        } else {
+            format!("allow({})", self.metrics.join(", "))
+        };
+        if self.reason.is_empty() {
+            kind
+        } else {
+            format!("{}: {}", kind, self.reason)
+        }
+    }
+
+    // Exceeded thresholds of the metrics that are not suppressed, none when all of them are
+    pub(crate) fn exceeded_thresholds(
+        &self,
+        metrics: &Metrics,
+        thresholds: &[f64],
+    ) -> Vec<&'static str> {
+        if self.metrics.is_empty() {
+            return vec![];
+        }
+        get_exceeded_thresholds(metrics, thresholds)
+            .into_iter()
+            .filter(|name| !self.metrics.iter().any(|m| m == name))
+            .collect()
+    }
+
+    // Check the thresholds of the metrics that are not suppressed
+    pub(crate) fn is_complex(&self, metrics: &Metrics, thresholds: &[f64]) -> bool {
+        !self.exceeded_thresholds(metrics, thresholds).is_empty()
+    }
+}
+
 /// Struct with all the metrics computed for a single function
 #[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
 #[allow(dead_code)]
@@ -87,6 +150,18 @@ pub struct FunctionMetrics {
     pub file_path: String,
     pub start_line: usize,
     pub end_line: usize,
+    /// Name qualified with the enclosing spaces, e.g. Flag::new
+    #[serde(default)]
+    pub qualified_name: String,
+    /// Kinds of the enclosing spaces and of the function, e.g. impl, function
+    #[serde(default)]
+    pub kind_chain: Vec<String>,
+    /// Labels of the coverage reports with at least one hit inside the function
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub covered_by: Vec<String>,
+    /// Suppression written in the source, the function is never complex for the suppressed metrics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppression: Option<Suppression>,
 }
 impl FunctionMetrics {
     pub fn new(
@@ -102,35 +177,76 @@ impl FunctionMetrics {
             file_path,
             start_line,
             end_line,
+            qualified_name: String::new(),
+            kind_chain: Vec::new(),
+            covered_by: Vec::new(),
+            suppression: None,
+        }
+    }
+
+    pub fn qualified_name(mut self, qualified_name: String) -> Self {
+        self.qualified_name = qualified_name;
+        self
+    }
+
+    pub fn kind_chain(mut self, kind_chain: Vec<String>) -> Self {
+        self.kind_chain = kind_chain;
+        self
+    }
+
+    pub fn covered_by(mut self, covered_by: Vec<String>) -> Self {
+        self.covered_by = covered_by;
+        self
+    }
+
+    // Set the suppression and check again the complexity of the function
+    pub(crate) fn suppression(
+        mut self,
+        suppression: Option<Suppression>,
+        thresholds: &[f64],
+    ) -> Self {
+        if let Some(s) = &suppression {
+            self.metrics.is_complex = s.is_complex(&self.metrics, thresholds);
         }
+        self.suppression = suppression;
+        self
     }
 }
 
-type Output = (Vec<RootMetrics>, Vec<String>, Vec<FunctionMetrics>, f64);
+type Output = (
+    Vec<RootMetrics>,
+    Vec<String>,
+    Vec<FunctionMetrics>,
+    f64,
+    Diagnostics,
+);
 
 // job received by the consumer threads
 #[derive(Clone)]
 struct JobItem {
     chunk: Vec<String>,
-    covs: HashMap<String, Vec<Value>>,
+    covs: HashMap<String, LinesCoverage>,
     metric: Complexity,
-    prefix: usize,
+    roots: Vec<String>,
     thresholds: Vec<f64>,
+    options: Arc<Options>,
 }
 impl JobItem {
     fn new(
         chunk: Vec<String>,
-        covs: HashMap<String, Vec<Value>>,
+        covs: HashMap<String, LinesCoverage>,
         metric: Complexity,
-        prefix: usize,
+        roots: Vec<String>,
         thresholds: Vec<f64>,
+        options: Arc<Options>,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            roots,
             thresholds,
+            options,
         }
     }
 }
@@ -139,8 +255,8 @@ impl fmt::Debug for JobItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, roots:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.roots, self.thresholds
         )
     }
 }
@@ -150,6 +266,8 @@ impl fmt::Debug for JobItem {
 pub struct FunctionConfig {
     pub(crate) res: Arc<Mutex<Vec<RootMetrics>>>,
     pub(crate) files_ignored: Arc<Mutex<Vec<String>>>,
+    pub(crate) files_failed: Arc<Mutex<Vec<FileFailure>>>,
+    pub(crate) mismatches: Arc<Mutex<Vec<Mismatch>>>,
 }
 
 impl FunctionConfig {
@@ -157,6 +275,8 @@ impl FunctionConfig {
         Self {
             res: Arc::new(Mutex::new(Vec::<RootMetrics>::new())),
             files_ignored: Arc::new(Mutex::new(Vec::<String>::new())),
+            files_failed: Arc::new(Mutex::new(Vec::<FileFailure>::new())),
+            mismatches: Arc::new(Mutex::new(Vec::<Mismatch>::new())),
         }
     }
 
@@ -164,12 +284,120 @@ impl FunctionConfig {
         Self {
             res: Arc::clone(&self.res),
             files_ignored: Arc::clone(&self.files_ignored),
+            files_failed: Arc::clone(&self.files_failed),
+            mismatches: Arc::clone(&self.mismatches),
         }
     }
 }
 
 type JobReceiver = Receiver<Option<JobItem>>;
 
+// Compute the metrics of a file of the job and of its functions
+// None is returned when the file is skipped because its coverage does not match the source
+// Returns the metrics of the file and the values it adds to the project metrics
+fn root_metrics(
+    job: &JobItem,
+    file: &str,
+    lines_coverage: &LinesCoverage,
+    mismatches: &Mutex<Vec<Mismatch>>,
+) -> Result<Option<(RootMetrics, JobComposer)>> {
+    let path = Path::new(file);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::path(path))?
+        .to_str()
+        .ok_or_else(|| Error::path(path))?
+        .into();
+    let mut arr = lines_coverage.arr.to_vec();
+    let data = read_file(path)?;
+    let (root, excluded, external) = get_root_excluding(
+        path,
+        &data,
+        job.options.exclude_tests,
+        &job.options.complexity_providers,
+    )?;
+    let source = String::from_utf8_lossy(&data);
+    let lines = source.lines().collect::<Vec<&str>>();
+    let comments = comment_tokens(path);
+    if !validate_coverage(
+        file,
+        &source,
+        &arr,
+        &root,
+        false,
+        external,
+        &job.options.validation,
+        mismatches,
+    )? {
+        return Ok(None);
+    }
+    exclude_lines(&mut arr, &excluded, false);
+    let (covered_lines, tot_lines) = get_covered_lines(&arr, root.start_line, root.end_line)?;
+    debug!(
+        "File: {:?} covered lines: {}  total lines: {}",
+        file, covered_lines, tot_lines
+    );
+    let spaces = get_spaces(&root, &job.options.kinds(), job.options.rollup)?;
+    let qualified_names = get_qualified_names(&spaces)?;
+    let comp = root.metrics.complexity_sum(job.metric);
+    let functions = spaces
+        .iter()
+        .zip(qualified_names)
+        .map(|(el, qualified_name)| -> Result<FunctionMetrics> {
+            let space = el.space;
+            let file_path = el.path.to_string();
+            let (m, _): (Metrics, (f64, f64)) =
+                Tree::get_metrics_from_space(space, &arr, job.metric, None, &job.thresholds)?;
+            let function_name = format!(
+                "{} ({}, {})",
+                space
+                    .name
+                    .as_ref()
+                    .ok_or(Error::space_name(space.start_line))?,
+                space.start_line,
+                space.end_line
+            );
+            let covered_by =
+                get_covered_by(&lines_coverage.suites, space.start_line, space.end_line)?;
+            Ok(FunctionMetrics::new(
+                m,
+                function_name,
+                file_path,
+                space.start_line,
+                space.end_line,
+            )
+            .qualified_name(qualified_name)
+            .kind_chain(el.kind_chain())
+            .covered_by(covered_by)
+            .suppression(
+                get_suppression(&lines, space.start_line, comments),
+                &job.thresholds,
+            ))
+        })
+        .collect::<Result<Vec<FunctionMetrics>>>()?;
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
+        Tree::get_metrics_from_space(&root, &arr, job.metric, None, &job.thresholds)?;
+    let file_path = get_file_path(file, &job.roots);
+    let composer = JobComposer {
+        covered_lines,
+        total_lines: tot_lines,
+        sifis_plain_sum: sp_sum,
+        sifis_quantized_sum: sq_sum,
+        ploc_sum: root.metrics.ploc,
+        comp_sum: comp,
+    };
+    let root_metrics = RootMetrics::new(
+        m,
+        file_name,
+        file_path,
+        root.start_line,
+        root.end_line,
+        functions,
+    )
+    .source(SourceCoverage::new(file, arr));
+    Ok(Some((root_metrics, composer)))
+}
+
 // Consumer function run by ead independent thread
 fn consumer(
     receiver: JobReceiver,
@@ -178,6 +406,8 @@ fn consumer(
 ) -> Result<()> {
     // Get all shared data
     let files_ignored = &cfg.files_ignored;
+    let files_failed = &cfg.files_failed;
+    let mismatches = &cfg.mismatches;
     let res = &cfg.res;
     let mut composer_output: JobComposer = JobComposer::default();
     while let Ok(job) = receiver.recv() {
@@ -186,83 +416,31 @@ fn consumer(
         }
         // Cannot panic because of the check immediately above.
         let job = job.unwrap();
-        let chunk = job.chunk;
-        let covs = job.covs;
-        let metric = job.metric;
-        let prefix = job.prefix;
-        let thresholds = job.thresholds;
         // For each file in the chunk received
-        for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+        for file in &job.chunk {
             // Get the coverage vector from the coveralls file
             // if not present the file will be added to the files ignored
-            let arr = match covs.get(&file) {
-                Some(arr) => arr.to_vec(),
+            let lines_coverage = match job.covs.get(file) {
+                Some(lines) => lines,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(file.to_string());
                     continue;
                 }
             };
-            let root = get_root(path)?;
-            let (covered_lines, tot_lines) =
-                get_covered_lines(&arr, root.start_line, root.end_line)?;
-            debug!(
-                "File: {:?} covered lines: {}  total lines: {}",
-                file, covered_lines, tot_lines
-            );
-            let spaces = get_spaces(&root)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let mut functions = Vec::<FunctionMetrics>::new();
-            spaces.iter().try_for_each(|el| -> Result<()> {
-                let space = el.0;
-                let file_path = el.1.to_string();
-                let (m, _): (Metrics, (f64, f64)) =
-                    Tree::get_metrics_from_space(space, &arr, metric, None, &thresholds)?;
-                let function_name = format!(
-                    "{} ({}, {})",
-                    space.name.as_ref().ok_or(Error::PathConversionError())?,
-                    space.start_line,
-                    space.end_line
-                );
-                functions.push(FunctionMetrics::new(
-                    m,
-                    function_name,
-                    file_path,
-                    space.start_line,
-                    space.end_line,
-                ));
-                Ok(())
-            })?;
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, &arr, metric, None, &thresholds)?;
-            let file_path = file.clone().split_off(prefix);
             // Upgrade all the global variables and add metrics to the result and complex_files
-            let mut res = res.lock()?;
-            composer_output.covered_lines += covered_lines;
-            composer_output.total_lines += tot_lines;
-            composer_output.ploc_sum += ploc;
-            composer_output.sifis_plain_sum += sp_sum;
-            composer_output.sifis_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(RootMetrics::new(
-                m,
-                file_name,
-                file_path,
-                root.start_line,
-                root.end_line,
-                functions,
-            ));
+            // A failed file is not part of the project metrics
+            match root_metrics(&job, file, lines_coverage, mismatches)
+                .map_err(|e| e.with_file(file))
+            {
+                Ok(Some((metrics, composer))) => {
+                    res.lock()?.push(metrics);
+                    composer_output += composer;
+                }
+                // The coverage does not match the source and the file is skipped
+                Ok(None) => {}
+                Err(error) => record_failure(files_failed, file, error, job.options.keep_going)?,
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -282,31 +460,29 @@ fn chunk_vector(vec: Vec<String>, n_threads: usize) -> Vec<Vec<String>> {
         .collect::<Vec<Vec<String>>>()
 }
 
-/// This Function get the folder of the repo to analyzed and the path to the coveralls file obtained using grcov
+/// This Function get the folder of the repo to analyzed and the coverage reports obtained using grcov
+/// The reports are merged line by line before computing the metrics
+/// Each function lists the labels of the reports that cover it
 /// It also takes as arguments the complexity metrics that must be used between cognitive or cyclomatic
+/// The options choose if the files are taken from the project folder or from the coverage report
 /// If the a file is not found in the json that files will be skipped
-/// It returns the  tuple (res, files_ignored, complex_files, project_coverage)
-pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
+/// It returns the  tuple (res, files_ignored, complex_files, project_coverage, diagnostics)
+/// Files of the report that are not found on disk are listed in the diagnostics
+pub fn get_functions_metrics_concurrent<A: AsRef<Path>>(
     files_path: A,
-    json_path: B,
+    reports: &[CoverageReport],
     metric: Complexity,
     n_threads: usize,
     thresholds: &[f64],
+    options: &Options,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
-    // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    // Read and merge all the coverage reports
+    let covs = read_coverage_reports(reports)?;
+    // Take all the files from the project folder or the coverage report
+    let (vec, covs, files_missing) = get_files(files_path.as_ref(), covs, options)?;
     let mut handlers = vec![];
     // Create a new vonfig with  all needed mutexes
     let cfg = FunctionConfig::new();
@@ -326,12 +502,9 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    let roots = options.roots(files_path.as_ref())?;
+    // The options are shared by all the jobs
+    let options = Arc::new(options.clone());
     // Send all chunks to the consumers
     chunks
         .iter()
@@ -340,8 +513,9 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
                 chunk.to_vec(),
                 covs.clone(),
                 metric,
-                prefix,
+                roots.clone(),
                 thresholds.to_vec(),
+                Arc::clone(&options),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -369,7 +543,7 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
     let mut res = cfg.res.lock()?;
     let composer_output = composer.join()??;
     let project_metric = RootMetrics::new(
-        get_project_metrics(composer_output, None)?,
+        get_project_metrics(composer_output)?,
         "PROJECT".into(),
         "-".into(),
         0,
@@ -385,7 +559,6 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         .flat_map(|m| m.functions.clone())
         .filter(|m| m.metrics.is_complex)
         .collect::<Vec<FunctionMetrics>>();
-    println!("{:?}", complex_files);
     let m = res
         .iter()
         .map(|metric| metric.metrics)
@@ -400,6 +573,11 @@ pub fn get_functions_metrics_concurrent<A: AsRef<Path>, B: AsRef<Path>>(
         (*files_ignored).clone(),
         complex_files,
         f64::round(project_coverage * 100.) / 100.,
+        Diagnostics {
+            files_missing,
+            files_failed: sorted_failures(&cfg.files_failed)?,
+            mismatches: sorted_mismatches(&cfg.mismatches)?,
+        },
     ))
 }
 
@@ -408,8 +586,9 @@ struct JobItemCovDir {
     chunk: Vec<String>,
     covs: HashMap<String, Covdir>,
     metric: Complexity,
-    prefix: usize,
+    roots: Vec<String>,
     thresholds: Vec<f64>,
+    options: Arc<Options>,
 }
 
 impl JobItemCovDir {
@@ -417,15 +596,17 @@ impl JobItemCovDir {
         chunk: Vec<String>,
         covs: HashMap<String, Covdir>,
         metric: Complexity,
-        prefix: usize,
+        roots: Vec<String>,
         thresholds: Vec<f64>,
+        options: Arc<Options>,
     ) -> Self {
         Self {
             chunk,
             covs,
             metric,
-            prefix,
+            roots,
             thresholds,
+            options,
         }
     }
 }
@@ -433,14 +614,118 @@ impl fmt::Debug for JobItemCovDir {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Job: chunks:{:?}, metric:{}, prefix:{:?}, thresholds: {:?}",
-            self.chunk, self.metric, self.prefix, self.thresholds
+            "Job: chunks:{:?}, metric:{}, roots:{:?}, thresholds: {:?}",
+            self.chunk, self.metric, self.roots, self.thresholds
         )
     }
 }
 
 type JobReceiverCovDir = Receiver<Option<JobItemCovDir>>;
 
+// Compute the metrics of a file of the covdir job and of its functions
+// None is returned when the file is skipped because its coverage does not match the source
+// Returns the metrics of the file and the values it adds to the project metrics
+fn root_metrics_covdir(
+    job: &JobItemCovDir,
+    file: &str,
+    covdir: &Covdir,
+    mismatches: &Mutex<Vec<Mismatch>>,
+) -> Result<Option<(RootMetrics, JobComposer)>> {
+    let path = Path::new(file);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::path(path))?
+        .to_str()
+        .ok_or_else(|| Error::path(path))?
+        .into();
+    let mut arr = covdir.arr.to_vec();
+    let data = read_file(path)?;
+    let (root, excluded, external) = get_root_excluding(
+        path,
+        &data,
+        job.options.exclude_tests,
+        &job.options.complexity_providers,
+    )?;
+    let source = String::from_utf8_lossy(&data);
+    let lines = source.lines().collect::<Vec<&str>>();
+    let comments = comment_tokens(path);
+    if !validate_coverage(
+        file,
+        &source,
+        &arr,
+        &root,
+        true,
+        external,
+        &job.options.validation,
+        mismatches,
+    )? {
+        return Ok(None);
+    }
+    // The coverage of the report includes the excluded lines
+    let coverage = if excluded.is_empty() {
+        Some(covdir.coverage)
+    } else {
+        exclude_lines(&mut arr, &excluded, true);
+        Some(get_covdir_coverage(&arr)?)
+    };
+    let spaces = get_spaces(&root, &job.options.kinds(), job.options.rollup)?;
+    let qualified_names = get_qualified_names(&spaces)?;
+    let comp = root.metrics.complexity_sum(job.metric);
+    let functions = spaces
+        .iter()
+        .zip(qualified_names)
+        .map(|(el, qualified_name)| -> Result<FunctionMetrics> {
+            let space = el.space;
+            let file_path = el.path.to_string();
+            let function_name = format!(
+                "{} ({}, {})",
+                space
+                    .name
+                    .as_ref()
+                    .ok_or(Error::space_name(space.start_line))?,
+                space.start_line,
+                space.end_line
+            );
+            let (m, _): (Metrics, (f64, f64)) =
+                Tree::get_metrics_from_space(space, &arr, job.metric, coverage, &job.thresholds)?;
+            Ok(FunctionMetrics::new(
+                m,
+                function_name,
+                file_path,
+                space.start_line,
+                space.end_line,
+            )
+            .qualified_name(qualified_name)
+            .kind_chain(el.kind_chain())
+            .suppression(
+                get_suppression(&lines, space.start_line, comments),
+                &job.thresholds,
+            ))
+        })
+        .collect::<Result<Vec<FunctionMetrics>>>()?;
+    let file_path = get_file_path(file, &job.roots);
+    let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
+        Tree::get_metrics_from_space(&root, &arr, job.metric, coverage, &job.thresholds)?;
+    let composer = JobComposer {
+        covered_lines: m.covered_lines,
+        total_lines: m.instrumented_lines,
+        sifis_plain_sum: sp_sum,
+        sifis_quantized_sum: sq_sum,
+        ploc_sum: root.metrics.ploc,
+        comp_sum: comp,
+    };
+    let root_metrics = RootMetrics::new(
+        m,
+        file_name,
+        file_path,
+        root.start_line,
+        root.end_line,
+        functions,
+    )
+    .source(SourceCoverage::new(file, arr));
+    Ok(Some((root_metrics, composer)))
+}
+
 // Consumer function run by ead independent thread
 fn consumer_covdir(
     receiver: JobReceiverCovDir,
@@ -449,6 +734,8 @@ fn consumer_covdir(
 ) -> Result<()> {
     // Get all shared data
     let files_ignored = &cfg.files_ignored;
+    let files_failed = &cfg.files_failed;
+    let mismatches = &cfg.mismatches;
     let res = &cfg.res;
     let mut composer_output = JobComposer::default();
     while let Ok(job) = receiver.recv() {
@@ -457,77 +744,29 @@ fn consumer_covdir(
         }
         // Cannot panic because of the check immediately above.
         let job = job.unwrap();
-        let chunk = job.chunk;
-        let covs = job.covs;
-        let metric = job.metric;
-        let prefix = job.prefix;
-        let thresholds = job.thresholds;
         // For each file in the chunk received
-        for file in chunk {
-            let path = Path::new(&file);
-            let file_name = path
-                .file_name()
-                .ok_or(Error::PathConversionError())?
-                .to_str()
-                .ok_or(Error::PathConversionError())?
-                .into();
+        for file in &job.chunk {
             // Get the coverage vector from the covdir file
             // If not present the file will be added to the files ignored
-            let covdir = match covs.get(&file) {
+            let covdir = match job.covs.get(file) {
                 Some(covdir) => covdir,
                 None => {
                     let mut f = files_ignored.lock()?;
-                    f.push(file);
+                    f.push(file.to_string());
                     continue;
                 }
             };
-            let arr = &covdir.arr;
-            let coverage = Some(covdir.coverage);
-            let root = get_root(path)?;
-            let spaces = get_spaces(&root)?;
-            let ploc = root.metrics.loc.ploc();
-            let comp = match metric {
-                Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-                Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-            };
-            let mut functions = Vec::<FunctionMetrics>::new();
-            spaces.iter().try_for_each(|el| -> Result<()> {
-                let space = el.0;
-                let file_path = el.1.to_string();
-                let function_name = format!(
-                    "{} ({}, {})",
-                    space.name.as_ref().ok_or(Error::ConversionError())?,
-                    space.start_line,
-                    space.end_line
-                );
-                let (m, _): (Metrics, (f64, f64)) =
-                    Tree::get_metrics_from_space(space, arr, metric, coverage, &thresholds)?;
-                functions.push(FunctionMetrics::new(
-                    m,
-                    function_name,
-                    file_path,
-                    space.start_line,
-                    space.end_line,
-                ));
-                Ok(())
-            })?;
-            let file_path = file.clone().split_off(prefix);
-            let (m, (sp_sum, sq_sum)): (Metrics, (f64, f64)) =
-                Tree::get_metrics_from_space(&root, arr, metric, coverage, &thresholds)?;
-            // Upgrade all the global variables and add metrics to the result and complex_files
-            let mut res = res.lock()?;
-            composer_output.ploc_sum += ploc;
-            composer_output.sifis_plain_sum += sp_sum;
-            composer_output.sifis_quantized_sum += sq_sum;
-            composer_output.comp_sum += comp;
-            res.push(RootMetrics::new(
-                m,
-                file_name,
-                file_path,
-                root.start_line,
-                root.end_line,
-                functions,
-            ));
+            // Upgrade all the global variables, a failed file is not part of the project metrics
+            match root_metrics_covdir(&job, file, covdir, mismatches).map_err(|e| e.with_file(file))
+            {
+                Ok(Some((metrics, composer))) => {
+                    res.lock()?.push(metrics);
+                    composer_output += composer;
+                }
+                // The coverage does not match the source and the file is skipped
+                Ok(None) => {}
+                Err(error) => record_failure(files_failed, file, error, job.options.keep_going)?,
+            }
         }
     }
     if let Err(_e) = sender_composer.send(Some(composer_output)) {
@@ -537,27 +776,24 @@ fn consumer_covdir(
     Ok(())
 }
 
+/// Get the metrics of the functions from a covdir report
+/// The report has no label, the functions are not attributed to any report
 pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     files_path: A,
     json_path: B,
     metric: Complexity,
     n_threads: usize,
     thresholds: &[f64],
+    options: &Options,
 ) -> Result<Output> {
     if thresholds.len() != 4 {
         return Err(Error::ThresholdsError());
     }
-    // Take all the files starting from the given project folder
-    let vec = read_files(files_path.as_ref())?;
     // Read coveralls file to string and then get all the coverage vectors
-    let file = fs::read_to_string(json_path)?;
-    let covs = read_json_covdir(
-        file,
-        files_path
-            .as_ref()
-            .to_str()
-            .ok_or(Error::PathConversionError())?,
-    )?;
+    let file = read_report(&json_path)?;
+    let covs = read_json_covdir(file, "").map_err(|e| e.with_file(&json_path))?;
+    // Take all the files from the project folder or the coverage report
+    let (vec, covs, files_missing) = get_files(files_path.as_ref(), covs, options)?;
     let mut handlers = vec![];
     // Create a new config with  all needed mutexes
     let cfg = FunctionConfig::new();
@@ -577,12 +813,9 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         let h = thread::spawn(move || -> Result<()> { consumer_covdir(r, s, &config) });
         handlers.push(h);
     }
-    let prefix = files_path
-        .as_ref()
-        .to_str()
-        .ok_or(Error::PathConversionError())?
-        .to_string()
-        .len();
+    let roots = options.roots(files_path.as_ref())?;
+    // The options are shared by all the jobs
+    let options = Arc::new(options.clone());
     // Send all chunks to the consumers
     chunks
         .iter()
@@ -591,8 +824,9 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
                 chunk.to_vec(),
                 covs.clone(),
                 metric,
-                prefix,
+                roots.clone(),
                 thresholds.to_vec(),
+                Arc::clone(&options),
             );
             debug!("Sending job: {:?}", job);
             if let Err(_e) = sender.send(Some(job)) {
@@ -618,19 +852,16 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
     }
     let mut files_ignored = cfg.files_ignored.lock()?;
     let mut res = cfg.res.lock()?;
-    let project_coverage = covs
-        .get(&("PROJECT_ROOT".to_string()))
-        .ok_or(Error::HashMapError())?
-        .coverage;
     let composer_output = composer.join()??;
     let project_metric = RootMetrics::new(
-        get_project_metrics(composer_output, Some(project_coverage))?,
+        get_project_metrics(composer_output)?,
         "PROJECT".into(),
         "-".into(),
         0,
         0,
         Vec::<FunctionMetrics>::new(),
     );
+    let project_coverage = project_metric.metrics.coverage;
     files_ignored.sort();
     res.sort_by(|a, b| a.file_name.cmp(&b.file_name));
     // Get AVG MIN MAX and complex files
@@ -653,6 +884,11 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
         (*files_ignored).clone(),
         complex_files,
         f64::round(project_coverage * 100.) / 100.,
+        Diagnostics {
+            files_missing,
+            files_failed: sorted_failures(&cfg.files_failed)?,
+            mismatches: sorted_mismatches(&cfg.mismatches)?,
+        },
     ))
 }
 
@@ -660,24 +896,27 @@ pub fn get_functions_metrics_concurrent_covdir<A: AsRef<Path>, B: AsRef<Path>>(
 mod tests {
 
     use super::*;
+    use crate::complexity::Sidecar;
     use crate::utility::compare_float;
 
     const JSON: &str = "./data/seahorse/seahorse.json";
     const COVDIR: &str = "./data/seahorse/covdir.json";
     const PROJECT: &str = "./data/seahorse/";
     const IGNORED: &str = "./data/seahorse/src/action.rs";
+    const TEST_PROJECT: &str = "./data/test_project/";
 
     #[test]
     fn test_metrics_coveralls_cyclomatic() {
         let json = Path::new(JSON);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_functions_metrics_concurrent(
+        let (metrics, files_ignored, _, _, _) = get_functions_metrics_concurrent(
             project,
-            json,
+            &[CoverageReport::new(json)],
             Complexity::Cyclomatic,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -729,12 +968,13 @@ mod tests {
         let json = Path::new(JSON);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_functions_metrics_concurrent(
+        let (metrics, files_ignored, _, _, _) = get_functions_metrics_concurrent(
             project,
-            json,
+            &[CoverageReport::new(json)],
             Complexity::Cognitive,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -786,12 +1026,13 @@ mod tests {
         let covdir = Path::new(COVDIR);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_functions_metrics_concurrent_covdir(
+        let (metrics, files_ignored, _, _, _) = get_functions_metrics_concurrent_covdir(
             project,
             covdir,
             Complexity::Cyclomatic,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -846,12 +1087,13 @@ mod tests {
         let covdir = Path::new(COVDIR);
         let project = Path::new(PROJECT);
         let ignored = Path::new(IGNORED);
-        let (metrics, files_ignored, _, _) = get_functions_metrics_concurrent_covdir(
+        let (metrics, files_ignored, _, _, _) = get_functions_metrics_concurrent_covdir(
             project,
             covdir,
             Complexity::Cognitive,
             8,
             &[30., 1.5, 35., 30.],
+            &Options::default(),
         )
         .unwrap();
         let ma = &metrics[7].metrics;
@@ -902,4 +1144,236 @@ mod tests {
         assert!(compare_float(cont_bool_flag.crap, 1.000430368875));
         assert!(compare_float(cont_bool_flag.skunk, 0.3019999999999999));
     }
+
+    #[test]
+    fn test_metrics_covdir_report_discovery() {
+        let options = Options::default()
+            .discovery(FileDiscovery::Report)
+            .source_roots(vec![PathBuf::from(TEST_PROJECT), PathBuf::from(PROJECT)]);
+        let (metrics, files_ignored, _, _, diagnostics) = get_functions_metrics_concurrent_covdir(
+            PROJECT,
+            COVDIR,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+
+        assert!(files_ignored.is_empty());
+        assert!(diagnostics.files_missing.is_empty());
+        let flag = metrics.iter().find(|m| m.file_name == "flag.rs").unwrap();
+        assert_eq!(flag.file_path, "src/flag.rs");
+        assert_eq!(flag.functions.len(), 16);
+        assert!(metrics.iter().any(|m| m.file_name == "app.rs"));
+        assert!(!metrics.iter().any(|m| m.file_name == "action.rs"));
+    }
+
+    #[test]
+    fn test_metrics_coverage_attribution() {
+        let reports = [
+            CoverageReport::new(JSON).label(Some("unit".into())),
+            CoverageReport::new(COVDIR)
+                .format(JsonFormat::Covdir)
+                .label(Some("integration".into())),
+        ];
+        let (metrics, _, _, _, _) = get_functions_metrics_concurrent(
+            PROJECT,
+            &reports,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &Options::default(),
+        )
+        .unwrap();
+        let functions = metrics
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .collect::<Vec<&FunctionMetrics>>();
+
+        assert!(functions.iter().any(|f| f.covered_by.is_empty()));
+        functions.iter().for_each(|f| {
+            if f.metrics.coverage > 0. {
+                assert_eq!(f.covered_by, vec!["unit", "integration"]);
+            } else {
+                assert!(f.covered_by.is_empty());
+            }
+        });
+    }
+
+    #[test]
+    fn test_metrics_space_kinds() {
+        let get = |options: &Options| {
+            let (metrics, _, _, _, _) = get_functions_metrics_concurrent(
+                TEST_PROJECT,
+                &[CoverageReport::new(JSON)],
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                options,
+            )
+            .unwrap();
+            metrics[0].functions.clone()
+        };
+
+        let functions = get(&Options::default().rollup(true));
+        assert_eq!(functions.len(), 13);
+        assert!(!functions
+            .iter()
+            .any(|f| f.function_name.starts_with("<anonymous>")));
+
+        let functions = get(&Options::default()
+            .space_kinds(vec![Space::Impl, Space::Function])
+            .rollup(true));
+        assert_eq!(functions.len(), 9);
+        let flag = functions
+            .iter()
+            .find(|f| f.qualified_name == "Flag")
+            .unwrap();
+        assert_eq!(flag.kind_chain, vec!["impl"]);
+        assert_eq!((flag.start_line, flag.end_line), (36, 148));
+
+        let functions = get(&Options::default().space_kinds(vec![Space::Impl]));
+        assert_eq!(functions.len(), 1);
+    }
+
+    #[test]
+    fn test_metrics_exclude_tests() {
+        let (metrics, _, _, _, _) = get_functions_metrics_concurrent(
+            TEST_PROJECT,
+            &[CoverageReport::new(JSON)],
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &Options::default().exclude_tests(true),
+        )
+        .unwrap();
+        let flag = &metrics[0];
+
+        assert_eq!(flag.functions.len(), 8);
+        assert!(flag.functions.iter().all(|f| f.end_line < 150));
+        assert_eq!(flag.end_line, 261);
+        assert!(flag.metrics.ploc < 191.);
+        assert!(flag.metrics.instrumented_lines < 154.);
+    }
+
+    #[test]
+    fn test_metrics_suppression() {
+        let (metrics, _, complex_functions, _, _) = get_functions_metrics_concurrent(
+            "./data/suppression_project/",
+            &[CoverageReport::new(
+                "./data/suppression_project/coveralls.json",
+            )],
+            Complexity::Cyclomatic,
+            8,
+            &[5., 1.5, 5., 5.],
+            &Options::default(),
+        )
+        .unwrap();
+        let function = |name: &str| {
+            metrics[0]
+                .functions
+                .iter()
+                .find(|f| f.qualified_name == name)
+                .unwrap()
+        };
+
+        let parse = function("parse");
+        assert!(!parse.metrics.is_complex);
+        assert_eq!(
+            parse.suppression,
+            Some(Suppression::new(
+                vec!["crap".into(), "skunk".into()],
+                "the parser is a state machine".into()
+            ))
+        );
+        let run = function("run");
+        assert!(!run.metrics.is_complex);
+        assert_eq!(run.suppression.as_ref().unwrap().describe(), "ignore");
+        let check = function("check");
+        assert!(check.metrics.is_complex);
+        assert!(check.suppression.is_none());
+        assert_eq!(complex_functions.len(), 1);
+    }
+
+    #[test]
+    fn test_metrics_keep_going() {
+        let (metrics, _, _, _, diagnostics) = get_functions_metrics_concurrent(
+            "./data/failing_project/",
+            &[CoverageReport::new("./data/failing_project/coveralls.json")],
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default().keep_going(true),
+        )
+        .unwrap();
+        assert_eq!(diagnostics.files_failed.len(), 1);
+        assert!(diagnostics.files_failed[0].file.ends_with("src/bad.rs"));
+        assert_eq!(metrics[0].file_name, "good.rs");
+        assert_eq!(metrics[0].functions.len(), 2);
+        assert_eq!(metrics[1].file_name, "PROJECT");
+    }
+
+    #[test]
+    fn test_metrics_covdir_keep_going() {
+        let (metrics, _, _, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent_covdir(
+                "./data/failing_project/",
+                "./data/failing_project/covdir.json",
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                &Options::default().keep_going(true),
+            )
+            .unwrap();
+        assert_eq!(diagnostics.files_failed.len(), 1);
+        assert_eq!(metrics[1].file_name, "PROJECT");
+        // The failed file is not part of the project coverage
+        assert!(compare_float(project_coverage, 100.));
+    }
+
+    #[test]
+    fn test_metrics_istanbul() {
+        let (metrics, _, _, _, _) = get_functions_metrics_concurrent(
+            "./data/ts_project/",
+            &[CoverageReport::new("./data/ts_project/coverage-final.json")
+                .format(JsonFormat::Istanbul)],
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &Options::default().strip_prefixes(vec!["/home/ci/app".into()]),
+        )
+        .unwrap();
+        let functions = &metrics[0].functions;
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].function_name, "clamp (1, 9)");
+        assert!(compare_float(functions[0].metrics.coverage, 80.));
+        assert!(compare_float(functions[0].metrics.complexity, 3.));
+        assert_eq!(functions[1].qualified_name, "sign");
+        assert!(compare_float(functions[1].metrics.coverage, 0.));
+    }
+
+    #[test]
+    fn test_metrics_coverprofile() {
+        let sidecar = Sidecar::from_json("./data/go_project/complexity.json").unwrap();
+        let options = Options::default()
+            .strip_prefixes(vec!["example.com/app".into()])
+            .complexity_providers(vec![Arc::new(sidecar)]);
+        let (metrics, _, _, _, _) = get_functions_metrics_concurrent(
+            "./data/go_project/",
+            &[CoverageReport::new("./data/go_project/cover.out").format(JsonFormat::Coverprofile)],
+            Complexity::Cyclomatic,
+            2,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        let functions = &metrics[0].functions;
+        assert_eq!(functions.len(), 2);
+        assert_eq!(functions[0].function_name, "Greet (6, 11)");
+        assert!(compare_float(functions[0].metrics.coverage, 60.));
+        assert!(compare_float(functions[0].metrics.complexity, 2.));
+        assert_eq!(functions[1].qualified_name, "Parse");
+        assert!(compare_float(functions[1].metrics.coverage, 0.));
+    }
 }
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::error::*;
+use crate::files::{FileMetrics, Metrics};
+use crate::functions::RootMetrics;
+use crate::utility::{normalize_path, Mode};
+
+// Layout of the runs saved in the history, increased when it changes
+const HISTORY_FORMAT: u64 = 1;
+
+// Max length of the revision written in the name of the file of a run
+const MAX_REVISION_LEN: usize = 40;
+
+/// Metrics of a run of weighted-code-coverage saved in the history
+/// The files are keyed by their path relative to the project folder
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Run {
+    pub format: u64,
+    pub revision: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub mode: String,
+    pub project: Metrics,
+    pub files: BTreeMap<String, Metrics>,
+}
+
+// Key of a file in the history, the paths of the runs of the same project must be comparable
+fn file_key(project_folder: &Path, file_path: &str) -> String {
+    let folder = normalize_path(&project_folder.to_string_lossy(), false);
+    let path = normalize_path(file_path, false);
+    Path::new(&path)
+        .strip_prefix(&folder)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or(path)
+        .trim_start_matches('/')
+        .to_string()
+}
+
+// Get the metrics of the project from the aggregates
+fn project_metrics<'a>(
+    mut aggregates: impl Iterator<Item = (&'a str, &'a Metrics)>,
+) -> Result<Metrics> {
+    aggregates
+        .find(|(name, _)| *name == "PROJECT")
+        .map(|(_, metrics)| *metrics)
+        .ok_or_else(|| Error::HashMapError {
+            key: "PROJECT".into(),
+        })
+}
+
+impl Run {
+    fn new(revision: &str, mode: Mode, project: Metrics, files: BTreeMap<String, Metrics>) -> Self {
+        Self {
+            format: HISTORY_FORMAT,
+            revision: revision.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            mode: match mode {
+                Mode::Files => "files".into(),
+                Mode::Functions => "functions".into(),
+            },
+            project,
+            files,
+        }
+    }
+
+    /// Run of files mode, the metrics must not be filtered to keep all the files
+    pub fn from_files<A: AsRef<Path>>(
+        project_folder: A,
+        revision: &str,
+        metrics: &[FileMetrics],
+    ) -> Result<Self> {
+        let (aggregates, files): (Vec<&FileMetrics>, Vec<&FileMetrics>) =
+            metrics.iter().partition(|m| m.file_path == "-");
+        let project = project_metrics(aggregates.iter().map(|m| (m.file.as_str(), &m.metrics)))?;
+        let files = files
+            .iter()
+            .map(|m| (file_key(project_folder.as_ref(), &m.file_path), m.metrics))
+            .collect();
+        Ok(Self::new(revision, Mode::Files, project, files))
+    }
+
+    /// Run of functions mode, the metrics of each file are the ones of its root space
+    pub fn from_functions<A: AsRef<Path>>(
+        project_folder: A,
+        revision: &str,
+        metrics: &[RootMetrics],
+    ) -> Result<Self> {
+        let (aggregates, files): (Vec<&RootMetrics>, Vec<&RootMetrics>) =
+            metrics.iter().partition(|m| m.file_path == "-");
+        let project = project_metrics(
+            aggregates
+                .iter()
+                .map(|m| (m.file_name.as_str(), &m.metrics)),
+        )?;
+        let files = files
+            .iter()
+            .map(|m| (file_key(project_folder.as_ref(), &m.file_path), m.metrics))
+            .collect();
+        Ok(Self::new(revision, Mode::Functions, project, files))
+    }
+
+    // Name of the file of the run, the timestamp first to list the runs in order
+    fn file_name(&self) -> String {
+        let revision = self
+            .revision
+            .chars()
+            .take(MAX_REVISION_LEN)
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        format!("{:013}_{}.json", self.timestamp, revision)
+    }
+}
+
+/// Save a run in the history folder, creating it if needed
+/// The history is append-only, a run is never overwritten
+/// Return the path of the file of the run
+pub fn save_run<A: AsRef<Path>>(history: A, run: &Run) -> Result<PathBuf> {
+    let history = history.as_ref();
+    fs::create_dir_all(history).map_err(|e| Error::from(e).with_file(history))?;
+    let path = history.join(run.file_name());
+    let file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(Error::history(format!(
+                "a run of revision {} was already saved at {}",
+                run.revision, run.timestamp
+            )))
+        }
+        Err(e) => return Err(Error::from(e).with_file(&path)),
+    };
+    let mut writer = BufWriter::new(file);
+    let written = serde_json::to_writer_pretty(&mut writer, run)
+        .map_err(io::Error::from)
+        .and_then(|_| writer.flush());
+    drop(writer);
+    if let Err(e) = written {
+        // A partial run could not be read back, so it is removed
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Cannot remove the partial run {}: {}", path.display(), e);
+        }
+        return Err(Error::from(e).with_output(&path));
+    }
+    info!(
+        "Run of revision {} saved in {}",
+        run.revision,
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Read all the runs of the history folder sorted from the oldest
+pub fn read_runs<A: AsRef<Path>>(history: A) -> Result<Vec<Run>> {
+    let history = history.as_ref();
+    let mut paths = fs::read_dir(history)
+        .map_err(|e| Error::from(e).with_file(history))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<PathBuf>>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+    let mut runs = paths
+        .iter()
+        .map(|path| -> Result<Run> {
+            debug!("Reading run {}...", path.display());
+            let file = fs::read_to_string(path).map_err(|e| Error::from(e).with_file(path))?;
+            let run: Run =
+                serde_json::from_str(&file).map_err(|e| Error::from(e).with_file(path))?;
+            if run.format > HISTORY_FORMAT {
+                return Err(Error::conversion(
+                    Location::Pointer("/format".into()),
+                    "a known history format",
+                )
+                .with_file(path));
+            }
+            Ok(run)
+        })
+        .collect::<Result<Vec<Run>>>()?;
+    if runs.is_empty() {
+        return Err(Error::history(format!(
+            "no run saved in {}",
+            history.display()
+        )));
+    }
+    // The sort is stable, the runs saved at the same time keep the order of their files
+    runs.sort_by_key(|run| run.timestamp);
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_key() {
+        let folder = Path::new("./data/seahorse/");
+        assert_eq!(file_key(folder, "src/app.rs"), "src/app.rs");
+        assert_eq!(file_key(folder, "/src/app.rs"), "src/app.rs");
+        assert_eq!(file_key(folder, "data/seahorse/src/app.rs"), "src/app.rs");
+        assert_eq!(
+            file_key(folder, "data/seahorse2/app.rs"),
+            "data/seahorse2/app.rs"
+        );
+    }
+
+    #[test]
+    fn test_save_run() {
+        let history = std::env::temp_dir().join("wcc_history_test");
+        let _ = fs::remove_dir_all(&history);
+        let metrics = [
+            FileMetrics::new(
+                Metrics::default().coverage(50.),
+                "app.rs".into(),
+                "src/app.rs".into(),
+            ),
+            FileMetrics::new(
+                Metrics::default().coverage(60.),
+                "PROJECT".into(),
+                "-".into(),
+            ),
+            FileMetrics::avg(Metrics::default()),
+        ];
+        let run = Run::from_files("./data/seahorse/", "abc/123", &metrics).unwrap();
+        assert_eq!(run.project.coverage, 60.);
+        assert_eq!(run.files.len(), 1);
+        assert_eq!(run.files["src/app.rs"].coverage, 50.);
+
+        let path = save_run(&history, &run).unwrap();
+        assert!(path.to_string_lossy().ends_with("_abc_123.json"));
+        // The history is append-only
+        let error = save_run(&history, &run).unwrap_err();
+        assert!(error.to_string().contains("already saved"));
+        let mut older = run.clone();
+        older.timestamp -= 1000;
+        older.revision = "abc/122".into();
+        save_run(&history, &older).unwrap();
+
+        let runs = read_runs(&history).unwrap();
+        fs::remove_dir_all(&history).unwrap();
+        assert_eq!(runs, vec![older, run]);
+        assert!(read_runs(&history).is_err());
+    }
+}
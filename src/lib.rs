@@ -1,6 +1,12 @@
+pub mod complexity;
+mod compress;
 pub mod error;
+mod exclude;
 pub mod files;
+mod formats;
 pub mod functions;
+pub mod history;
 pub mod metrics;
 pub mod output;
 pub mod utility;
+mod validate;
@@ -1,26 +1,25 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
+use crate::complexity::CodeSpace;
 use crate::error::*;
-use crate::utility::{get_coverage_perc, get_covered_lines, Complexity};
+use crate::utility::{get_covered_lines, Complexity};
 
 // Calculate the CRAP value  for the given file
+// Only the tests use it, the analysis computes the files metrics from their spaces
 // (https://testing.googleblog.com/2011/02/this-code-is-crap.html#:~:text=CRAP%20is%20short%20for%20Change,partner%20in%20crime%20Bob%20Evans.)
 // Return the value in case of success and an specif error in case of fails
+#[cfg(test)]
 pub(crate) fn crap(
-    root: &FuncSpace,
+    root: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = root.metrics.complexity_sum(metric);
     let cov = if let Some(coverage) = coverage {
         coverage / 100.0
     } else {
-        get_coverage_perc(covs)?
+        crate::utility::get_coverage_perc(covs)?
     };
     Ok(((comp.powf(2.)) * ((1.0 - cov).powf(3.))) + comp)
 }
@@ -29,15 +28,12 @@ pub(crate) fn crap(
 // (https://testing.googleblog.com/2011/02/this-code-is-crap.html#:~:text=CRAP%20is%20short%20for%20Change,partner%20in%20crime%20Bob%20Evans.)
 // Return the value in case of success and an specif error in case of fails
 pub(crate) fn crap_function(
-    space: &FuncSpace,
+    space: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = space.metrics.complexity_sum(metric);
 
     let cov = if let Some(coverage) = coverage {
         coverage / 100.0
@@ -1,15 +1,15 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
+use crate::complexity::CodeSpace;
 use crate::error::*;
 use crate::utility::Complexity;
 
 const THRESHOLD: f64 = 15.;
 // This function find the minimum space for a line i in the file
 // It returns the space
-fn get_min_space(root: &FuncSpace, i: usize) -> FuncSpace {
-    let mut min_space: FuncSpace = root.clone();
-    let mut stack: Vec<FuncSpace> = vec![root.clone()];
+fn get_min_space(root: &CodeSpace, i: usize) -> CodeSpace {
+    let mut min_space: CodeSpace = root.clone();
+    let mut stack: Vec<CodeSpace> = vec![root.clone()];
     while let Some(space) = stack.pop() {
         for s in space.spaces.into_iter() {
             if i >= s.start_line && i <= s.end_line {
@@ -23,51 +23,57 @@ fn get_min_space(root: &FuncSpace, i: usize) -> FuncSpace {
 
 // Calculate the SIFIS plain value  for the given file
 // Return the value in case of success and an specif error in case of fails
+#[cfg(test)]
 pub(crate) fn sifis_plain(
-    root: &FuncSpace,
+    root: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = root.metrics.loc.ploc();
-    let comp = match metric {
-        Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-    };
-    let sum = covs.iter().try_fold(0., |acc, line| -> Result<f64> {
-        // Check if the line is null
-        let is_null = if is_covdir {
-            line.as_i64().ok_or(Error::ConversionError())? == -1
-        } else {
-            line.is_null()
-        };
-        let sum;
-        if !is_null {
-            // If the line is not null and is covered (cov>0) the add the complexity  to the sum
-            let cov = line.as_u64().ok_or(Error::ConversionError())?;
-            if cov > 0 {
-                sum = acc + comp;
+    let ploc = root.metrics.ploc;
+    let comp = root.metrics.complexity_sum(metric);
+    let sum = covs
+        .iter()
+        .enumerate()
+        .try_fold(0., |acc, (i, line)| -> Result<f64> {
+            // Check if the line is null
+            let is_null = if is_covdir {
+                line.as_i64()
+                    .ok_or(Error::conversion(Location::Index(i), "an integer"))?
+                    == -1
+            } else {
+                line.is_null()
+            };
+            let sum;
+            if !is_null {
+                // If the line is not null and is covered (cov>0) the add the complexity  to the sum
+                let cov = line
+                    .as_u64()
+                    .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
+                if cov > 0 {
+                    sum = acc + comp;
+                } else {
+                    sum = acc;
+                }
             } else {
                 sum = acc;
             }
-        } else {
-            sum = acc;
-        }
-        Ok(sum)
-    })?;
+            Ok(sum)
+        })?;
     Ok((sum / ploc, sum))
 }
 
 // Calculate the SIFIS quantized value  for the given file
 // Return the value in case of success and an specif error in case of fails
 // If the complexity of the block/file is 0 the value if sifis quantized is the coverage of the file
+#[cfg(test)]
 pub(crate) fn sifis_quantized(
-    root: &FuncSpace,
+    root: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = root.metrics.loc.ploc();
+    let ploc = root.metrics.ploc;
     let sum =
     //For each line find the minimum space and get complexity value then sum 1 if comp>threshold  else sum 1
         covs.iter()
@@ -75,21 +81,18 @@ pub(crate) fn sifis_quantized(
             .try_fold(0., |acc, (i, line)| -> Result<f64> {
                 // Check if the line is null
                 let is_null = if is_covdir {
-                    line.as_i64().ok_or(Error::ConversionError())? == -1
+                    line.as_i64().ok_or(Error::conversion(Location::Index(i), "an integer"))? == -1
                 } else {
                     line.is_null()
                 };
                 let sum;
                 if !is_null {
                     // Get line
-                    let cov = line.as_u64().ok_or(Error::ConversionError())?;
+                    let cov = line.as_u64().ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
                     if cov > 0 {
                         // If the line is covered get the space of the line and then check if the complexity is below the threshold
-                        let min_space: FuncSpace = get_min_space(root, i);
-                        let comp = match metric {
-                            Complexity::Cyclomatic => min_space.metrics.cyclomatic.cyclomatic(),
-                            Complexity::Cognitive => min_space.metrics.cognitive.cognitive(),
-                        };
+                        let min_space: CodeSpace = get_min_space(root, i);
+                        let comp = min_space.metrics.complexity(metric);
                         if comp > THRESHOLD {
                             sum = acc + 2.;
                         } else {
@@ -109,32 +112,33 @@ pub(crate) fn sifis_quantized(
 // Calculate the SIFIS plain value for a function
 // Return the value in case of success and an specif error in case of fails
 pub(crate) fn sifis_plain_function(
-    space: &FuncSpace,
+    space: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = space.metrics.loc.ploc();
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let ploc = space.metrics.ploc;
+    let comp = space.metrics.complexity_sum(metric);
     let sum = covs
         .iter()
         .enumerate()
         .try_fold(0., |acc, (i, line)| -> Result<f64> {
             // Check if the line is null
             let is_null = if is_covdir {
-                line.as_i64().ok_or(Error::ConversionError())? == -1
+                line.as_i64()
+                    .ok_or(Error::conversion(Location::Index(i), "an integer"))?
+                    == -1
             } else {
                 line.is_null()
             };
             let sum;
-            let start = space.start_line - 1;
+            let start = space.start_line.saturating_sub(1);
             let end = space.end_line;
             if !is_null && (start..end).contains(&i) {
                 // If the line is not null and is covered (cov>0) the add the complexity  to the sum
-                let cov = line.as_u64().ok_or(Error::ConversionError())?;
+                let cov = line
+                    .as_u64()
+                    .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
                 if cov > 0 {
                     sum = acc + comp;
                 } else {
@@ -152,12 +156,12 @@ pub(crate) fn sifis_plain_function(
 // Return the value in case of success and an specif error in case of fails
 // If the complexity of the block/file is 0 the value if sifis quantized is the coverage of the file
 pub(crate) fn sifis_quantized_function(
-    space: &FuncSpace,
+    space: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     is_covdir: bool,
 ) -> Result<(f64, f64)> {
-    let ploc = space.metrics.loc.ploc();
+    let ploc = space.metrics.ploc;
     let sum =
     //For each line find the minimum space and get complexity value then sum 1 if comp>threshold  else sum 1
         covs.iter()
@@ -165,23 +169,20 @@ pub(crate) fn sifis_quantized_function(
             .try_fold(0., |acc, (i, line)| -> Result<f64> {
                 // Check if the line is null
                 let is_null = if is_covdir {
-                    line.as_i64().ok_or(Error::ConversionError())? == -1
+                    line.as_i64().ok_or(Error::conversion(Location::Index(i), "an integer"))? == -1
                 } else {
                     line.is_null()
                 };
                 let sum;
-                let start = space.start_line-1;
+                let start = space.start_line.saturating_sub(1);
                 let end =space.end_line;
                 if !is_null && (start..end).contains(&i) {
                     // Get line
-                    let cov = line.as_u64().ok_or(Error::ConversionError())?;
+                    let cov = line.as_u64().ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
                     if cov > 0 {
                         // If the line is covered get the space of the line and then check if the complexity is below the threshold
-                        let min_space: FuncSpace = get_min_space(space, i);
-                        let comp = match metric {
-                            Complexity::Cyclomatic => min_space.metrics.cyclomatic.cyclomatic(),
-                            Complexity::Cognitive => min_space.metrics.cognitive.cognitive(),
-                        };
+                        let min_space: CodeSpace = get_min_space(space, i);
+                        let comp = min_space.metrics.complexity(metric);
                         if comp > THRESHOLD {
                             sum = acc + 2.;
                         } else {
@@ -1,28 +1,26 @@
-use rust_code_analysis::FuncSpace;
 use serde_json::Value;
 
+use crate::complexity::CodeSpace;
 use crate::error::*;
-use crate::utility::{get_coverage_perc, get_covered_lines, Complexity};
+use crate::utility::{get_covered_lines, Complexity};
 
 const COMPLEXITY_FACTOR: f64 = 25.0;
 // Calculate the Skunkscore value  for the given file
 // https://www.fastruby.io/blog/code-quality/intruducing-skunk-stink-score-calculator.html
 // In this implementation the code smells are ignored.
 // Return the value in case of success and an specif error in case of fails
+#[cfg(test)]
 pub(crate) fn skunk_nosmells(
-    root: &FuncSpace,
+    root: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => root.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => root.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = root.metrics.complexity_sum(metric);
     let cov = if let Some(coverage) = coverage {
         coverage
     } else {
-        get_coverage_perc(covs)? * 100.
+        crate::utility::get_coverage_perc(covs)? * 100.
     };
     Ok(if cov == 100. {
         comp / COMPLEXITY_FACTOR
@@ -36,15 +34,12 @@ pub(crate) fn skunk_nosmells(
 // In this implementation the code smells are ignored.
 // Return the value in case of success and an specif error in case of fails
 pub(crate) fn skunk_nosmells_function(
-    space: &FuncSpace,
+    space: &CodeSpace,
     covs: &[Value],
     metric: Complexity,
     coverage: Option<f64>,
 ) -> Result<f64> {
-    let comp = match metric {
-        Complexity::Cyclomatic => space.metrics.cyclomatic.cyclomatic_sum(),
-        Complexity::Cognitive => space.metrics.cognitive.cognitive_sum(),
-    };
+    let comp = space.metrics.complexity_sum(metric);
     let cov = if let Some(coverage) = coverage {
         coverage / 100.0
     } else {
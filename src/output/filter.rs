@@ -0,0 +1,225 @@
+use std::cmp::Ordering;
+
+use crate::files::{FileMetrics, Metrics};
+use crate::functions::{FunctionMetrics, RootMetrics};
+use crate::utility::SortBy;
+
+/// Choose which entries are written in the reports and in which order
+/// The aggregates (PROJECT, AVG, MAX, MIN) are always kept at the end
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReportFilter {
+    pub sort_by: Option<SortBy>,
+    pub complex_only: bool,
+    pub coverage_below: Option<f64>,
+    pub top: Option<usize>,
+}
+
+impl ReportFilter {
+    pub fn sort_by(mut self, sort_by: Option<SortBy>) -> Self {
+        self.sort_by = sort_by;
+        self
+    }
+    pub fn complex_only(mut self, complex_only: bool) -> Self {
+        self.complex_only = complex_only;
+        self
+    }
+    pub fn coverage_below(mut self, coverage_below: Option<f64>) -> Self {
+        self.coverage_below = coverage_below;
+        self
+    }
+    pub fn top(mut self, top: Option<usize>) -> Self {
+        self.top = top;
+        self
+    }
+
+    // Check if some entries can be removed
+    fn is_restrictive(&self) -> bool {
+        self.complex_only || self.coverage_below.is_some() || self.top.is_some()
+    }
+
+    fn keep(&self, metrics: &Metrics) -> bool {
+        let below = match self.coverage_below {
+            Some(coverage) => metrics.coverage < coverage,
+            None => true,
+        };
+        (!self.complex_only || metrics.is_complex) && below
+    }
+
+    // Compare two entries putting the worst first for the metrics
+    fn compare(
+        &self,
+        (a, a_name, a_line): (&Metrics, &str, usize),
+        (b, b_name, b_line): (&Metrics, &str, usize),
+    ) -> Ordering {
+        match self.sort_by {
+            None => Ordering::Equal,
+            Some(SortBy::Name) => a_name.cmp(b_name),
+            Some(SortBy::Line) => a_line.cmp(&b_line),
+            Some(SortBy::SifisPlain) => b.sifis_plain.total_cmp(&a.sifis_plain),
+            Some(SortBy::SifisQuantized) => b.sifis_quantized.total_cmp(&a.sifis_quantized),
+            Some(SortBy::Crap) => b.crap.total_cmp(&a.crap),
+            Some(SortBy::Skunk) => b.skunk.total_cmp(&a.skunk),
+            Some(SortBy::Coverage) => a.coverage.total_cmp(&b.coverage),
+            Some(SortBy::Complexity) => b.complexity.total_cmp(&a.complexity),
+        }
+    }
+}
+
+fn file_key(m: &FileMetrics) -> (&Metrics, &str, usize) {
+    (&m.metrics, &m.file, 0)
+}
+
+fn root_key(m: &RootMetrics) -> (&Metrics, &str, usize) {
+    (&m.metrics, &m.file_name, m.start_line)
+}
+
+fn function_key(m: &FunctionMetrics) -> (&Metrics, &str, usize) {
+    (&m.metrics, &m.function_name, m.start_line)
+}
+
+/// Filter, sort and limit the files using the report filter
+pub fn filter_metrics(metrics: &[FileMetrics], filter: &ReportFilter) -> Vec<FileMetrics> {
+    let (aggregates, mut files): (Vec<FileMetrics>, Vec<FileMetrics>) =
+        metrics.iter().cloned().partition(|m| m.file_path == "-");
+    files.retain(|m| filter.keep(&m.metrics));
+    files.sort_by(|a, b| filter.compare(file_key(a), file_key(b)));
+    if let Some(top) = filter.top {
+        files.truncate(top);
+    }
+    files.extend(aggregates);
+    files
+}
+
+/// Filter, sort and limit the functions using the report filter
+/// The top functions are chosen in the whole project and listed inside their files,
+/// the files without any function left are removed when a filter or a limit is used
+pub fn filter_metrics_function(metrics: &[RootMetrics], filter: &ReportFilter) -> Vec<RootMetrics> {
+    let (aggregates, roots): (Vec<&RootMetrics>, Vec<&RootMetrics>) =
+        metrics.iter().partition(|m| m.file_path == "-");
+    let mut functions = roots
+        .iter()
+        .enumerate()
+        .flat_map(|(i, root)| root.functions.iter().map(move |f| (i, f)))
+        .filter(|(_, f)| filter.keep(&f.metrics))
+        .collect::<Vec<(usize, &FunctionMetrics)>>();
+    functions.sort_by(|(_, a), (_, b)| filter.compare(function_key(a), function_key(b)));
+    if let Some(top) = filter.top {
+        functions.truncate(top);
+    }
+    let mut res = roots
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !filter.is_restrictive() || functions.iter().any(|(j, _)| i == j))
+        .map(|(i, root)| {
+            let mut root = (*root).clone();
+            root.functions = functions
+                .iter()
+                .filter(|(j, _)| *j == i)
+                .map(|(_, f)| (*f).clone())
+                .collect();
+            root
+        })
+        .collect::<Vec<RootMetrics>>();
+    res.sort_by(|a, b| filter.compare(root_key(a), root_key(b)));
+    res.extend(aggregates.into_iter().cloned());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, crap: f64, coverage: f64, is_complex: bool) -> FileMetrics {
+        FileMetrics::new(
+            Metrics::default()
+                .crap(crap)
+                .coverage(coverage)
+                .is_complex(is_complex),
+            name.into(),
+            format!("src/{}", name),
+        )
+    }
+
+    fn function(name: &str, start_line: usize, crap: f64) -> FunctionMetrics {
+        FunctionMetrics::new(
+            Metrics::default().crap(crap).is_complex(crap > 10.),
+            name.into(),
+            format!("/{}", name),
+            start_line,
+            start_line + 1,
+        )
+    }
+
+    #[test]
+    fn test_filter_files() {
+        let metrics = vec![
+            file("a.rs", 5., 90., false),
+            file("b.rs", 50., 20., true),
+            file("c.rs", 30., 60., true),
+            FileMetrics::avg(Metrics::default()),
+        ];
+
+        let res = filter_metrics(&metrics, &ReportFilter::default());
+        assert_eq!(res, metrics);
+
+        let filter = ReportFilter::default().sort_by(Some(SortBy::Crap));
+        let res = filter_metrics(&metrics, &filter);
+        let names = res.iter().map(|m| m.file.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["b.rs", "c.rs", "a.rs", "AVG"]);
+
+        let filter = ReportFilter::default()
+            .sort_by(Some(SortBy::Coverage))
+            .coverage_below(Some(80.))
+            .top(Some(1));
+        let res = filter_metrics(&metrics, &filter);
+        let names = res.iter().map(|m| m.file.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["b.rs", "AVG"]);
+    }
+
+    #[test]
+    fn test_filter_functions() {
+        let root = |name: &str, functions| {
+            RootMetrics::new(
+                Metrics::default(),
+                name.into(),
+                format!("src/{}", name),
+                1,
+                100,
+                functions,
+            )
+        };
+        let metrics = vec![
+            root("a.rs", vec![function("f", 20, 30.), function("g", 2, 1.)]),
+            root("b.rs", vec![function("h", 10, 50.), function("i", 1, 20.)]),
+            root("c.rs", vec![]),
+            RootMetrics::avg(Metrics::default()),
+        ];
+
+        let filter = ReportFilter::default().sort_by(Some(SortBy::Line));
+        let res = filter_metrics_function(&metrics, &filter);
+        assert_eq!(res.len(), 4);
+        assert_eq!(res[0].functions[0].function_name, "g");
+
+        let filter = ReportFilter::default()
+            .sort_by(Some(SortBy::Crap))
+            .complex_only(true)
+            .top(Some(2));
+        let res = filter_metrics_function(&metrics, &filter);
+        let names = res
+            .iter()
+            .map(|m| {
+                (
+                    m.file_name.as_str(),
+                    m.functions
+                        .iter()
+                        .map(|f| f.function_name.as_str())
+                        .collect::<Vec<&str>>(),
+                )
+            })
+            .collect::<Vec<(&str, Vec<&str>)>>();
+        assert_eq!(
+            names,
+            vec![("a.rs", vec!["f"]), ("b.rs", vec!["h"]), ("AVG", vec![])]
+        );
+    }
+}
@@ -0,0 +1,559 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::*;
+
+use tracing::{debug, warn};
+
+use crate::error::*;
+use crate::files::{FileMetrics, Metrics, SourceCoverage};
+use crate::functions::{FunctionMetrics, RootMetrics, Suppression};
+use crate::utility::*;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}
+table{border-collapse:collapse;font-size:14px}
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left}
+th{background:#eee;cursor:pointer;user-select:none}
+tr.project td{font-weight:bold}
+tr.function td:nth-child(2){padding-left:2em}
+.complex{background:#f8d7da}
+.suppressed{background:#fff3cd}
+.simple{background:#d4edda}
+.source{font-family:monospace;white-space:pre;border:none}
+.source td{border:none;padding:0 8px}
+.covered{background:#e6ffed}
+.uncovered{background:#ffeef0}
+.fn-complex{border-left:4px solid #d73a49}
+.fn-simple{border-left:4px solid #28a745}
+.fn-suppressed{border-left:4px solid #e0a800}
+.hits{color:#666;text-align:right}";
+
+// Sort the rows of the table by the clicked column, numeric when possible
+const SCRIPT: &str = "document.querySelectorAll('th').forEach(function(th,i){
+th.addEventListener('click',function(){
+var body=th.closest('table').tBodies[0];
+var asc=th.dataset.order!=='asc';th.dataset.order=asc?'asc':'desc';
+var rows=Array.from(body.rows);
+rows.sort(function(a,b){
+var x=a.cells[i].dataset.value||a.cells[i].textContent;
+var y=b.cells[i].dataset.value||b.cells[i].textContent;
+var r=(isNaN(x)||isNaN(y))?x.localeCompare(y):x-y;
+return asc?r:-r;});
+rows.forEach(function(r){body.appendChild(r);});});});";
+
+// Row of the index table
+struct Row<'a> {
+    kind: &'a str,
+    name: &'a str,
+    path: &'a str,
+    lines: Option<(usize, usize)>,
+    metrics: &'a Metrics,
+    suppression: Option<&'a Suppression>,
+    // Labels of the reports covering the row, only in functions mode
+    covered_by: Option<&'a [String]>,
+    link: Option<String>,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn verdict(metrics: &Metrics, suppression: Option<&Suppression>) -> &'static str {
+    match (metrics.is_complex, suppression) {
+        (true, _) => "complex",
+        (false, Some(_)) => "suppressed",
+        (false, None) => "simple",
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}<script>{}</script>\n</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body,
+        SCRIPT
+    )
+}
+
+// The reason of the suppression is shown as the title of the verdict
+fn metrics_cells(
+    out: &mut String,
+    metrics: &Metrics,
+    suppression: Option<&Suppression>,
+) -> Result<()> {
+    write!(
+        out,
+        "<td data-value=\"{0}\">{0:.3}</td><td data-value=\"{1}\">{1:.3}</td><td data-value=\"{2}\">{2:.3}</td><td data-value=\"{3}\">{3:.3}</td><td data-value=\"{4}\">{4:.2}</td><td>{5:.2}</td><td>{6:.2}</td><td>{7:.2}</td><td>{8:.2}</td><td class=\"{9}\" title=\"{10}\">{9}</td>",
+        metrics.sifis_plain,
+        metrics.sifis_quantized,
+        metrics.crap,
+        metrics.skunk,
+        metrics.coverage,
+        metrics.complexity,
+        metrics.ploc,
+        metrics.covered_lines,
+        metrics.instrumented_lines,
+        verdict(metrics, suppression),
+        suppression
+            .map(|s| escape(&s.describe()))
+            .unwrap_or_default()
+    )?;
+    Ok(())
+}
+
+// Cell with the labels of the reports covering a function
+fn covered_by_cell(out: &mut String, covered_by: &[String]) -> Result<()> {
+    write!(out, "<td>{}</td>", escape(&covered_by.join(", ")))?;
+    Ok(())
+}
+
+const COVERED_BY_HEADER: &str = "<th>COVERED BY</th>";
+
+const METRICS_HEADER: &str =
+    "<th>WCC PLAIN</th><th>WCC QUANTIZED</th><th>CRAP</th><th>SKUNKSCORE</th><th>COVERAGE</th><th>COMPLEXITY</th><th>PLOC</th><th>COVERED</th><th>INSTRUMENTED</th><th>VERDICT</th>";
+
+fn index_page(
+    rows: &[Row],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "<h1>Weighted code coverage</h1>")?;
+    writeln!(out, "<p>Project coverage: {:.2}%</p>", project_coverage)?;
+    let covered_by = rows.iter().any(|row| row.covered_by.is_some());
+    writeln!(
+        out,
+        "<table>\n<thead><tr><th>KIND</th><th>NAME</th><th>PATH</th><th>LINES</th>{}{}</tr></thead>\n<tbody>",
+        METRICS_HEADER,
+        if covered_by { COVERED_BY_HEADER } else { "" }
+    )?;
+    rows.iter().try_for_each(|row| -> Result<()> {
+        let name = match &row.link {
+            Some(link) => format!("<a href=\"{}\">{}</a>", link, escape(row.name)),
+            None => escape(row.name),
+        };
+        let lines = row
+            .lines
+            .map(|(start, end)| format!("{}-{}", start, end))
+            .unwrap_or_default();
+        write!(
+            out,
+            "<tr class=\"{0}\"><td>{0}</td><td>{1}</td><td>{2}</td><td data-value=\"{3}\">{4}</td>",
+            row.kind,
+            name,
+            escape(row.path),
+            row.lines.map(|(start, _)| start).unwrap_or_default(),
+            lines
+        )?;
+        metrics_cells(&mut out, row.metrics, row.suppression)?;
+        if covered_by {
+            covered_by_cell(&mut out, row.covered_by.unwrap_or_default())?;
+        }
+        writeln!(out, "</tr>")?;
+        Ok(())
+    })?;
+    writeln!(out, "</tbody>\n</table>")?;
+    let lists = [
+        ("Files ignored", files_ignored),
+        ("Files missing", diagnostics.files_missing.as_slice()),
+    ];
+    lists
+        .iter()
+        .filter(|(_, files)| !files.is_empty())
+        .try_for_each(|(title, files)| -> Result<()> {
+            writeln!(out, "<h2>{} ({})</h2>\n<ul>", title, files.len())?;
+            files
+                .iter()
+                .try_for_each(|f| writeln!(out, "<li>{}</li>", escape(f)))?;
+            writeln!(out, "</ul>")?;
+            Ok(())
+        })?;
+    if !diagnostics.files_failed.is_empty() {
+        writeln!(
+            out,
+            "<h2>Files failed ({})</h2>\n<ul>",
+            diagnostics.files_failed.len()
+        )?;
+        diagnostics.files_failed.iter().try_for_each(|f| {
+            writeln!(out, "<li>{}: {}</li>", escape(&f.file), escape(&f.error))
+        })?;
+        writeln!(out, "</ul>")?;
+    }
+    if !diagnostics.mismatches.is_empty() {
+        writeln!(
+            out,
+            "<h2>Mismatches ({})</h2>\n<ul>",
+            diagnostics.mismatches.len()
+        )?;
+        diagnostics.mismatches.iter().try_for_each(|m| {
+            let skipped = if m.skipped { ", skipped" } else { "" };
+            writeln!(
+                out,
+                "<li>{} ({}{}): {}</li>",
+                escape(&m.file),
+                m.check,
+                skipped,
+                escape(&m.detail)
+            )
+        })?;
+        writeln!(out, "</ul>")?;
+    }
+    Ok(page("Weighted code coverage", &out))
+}
+
+// Page with the source of a file annotated with the hits of each line
+// Each line is marked with the verdict of the innermost function containing it
+fn file_page(
+    file_path: &str,
+    metrics: &Metrics,
+    functions: &[FunctionMetrics],
+    source: &SourceCoverage,
+    code: &str,
+) -> Result<String> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<p><a href=\"../index.html\">Index</a></p>\n<h1>{}</h1>",
+        escape(file_path)
+    )?;
+    // The files mode has no functions and no covering reports
+    let covered_by = !functions.is_empty();
+    writeln!(
+        out,
+        "<table>\n<thead><tr><th>NAME</th><th>LINES</th>{}{}</tr></thead>\n<tbody>",
+        METRICS_HEADER,
+        if covered_by { COVERED_BY_HEADER } else { "" }
+    )?;
+    write!(
+        out,
+        "<tr class=\"project\"><td>{}</td><td></td>",
+        escape(file_path)
+    )?;
+    metrics_cells(&mut out, metrics, None)?;
+    if covered_by {
+        covered_by_cell(&mut out, &[])?;
+    }
+    writeln!(out, "</tr>")?;
+    functions.iter().try_for_each(|f| -> Result<()> {
+        write!(
+            out,
+            "<tr><td><a href=\"#L{0}\">{1}</a></td><td data-value=\"{0}\">{0}-{2}</td>",
+            f.start_line,
+            escape(&f.function_name),
+            f.end_line
+        )?;
+        metrics_cells(&mut out, &f.metrics, f.suppression.as_ref())?;
+        covered_by_cell(&mut out, &f.covered_by)?;
+        writeln!(out, "</tr>")?;
+        Ok(())
+    })?;
+    writeln!(out, "</tbody>\n</table>\n<table class=\"source\">")?;
+    code.lines()
+        .enumerate()
+        .try_for_each(|(i, line)| -> Result<()> {
+            let n = i + 1;
+            let (hits, class) = match source.lines.get(i).and_then(|v| v.as_u64()) {
+                Some(0) => ("0".to_string(), "uncovered"),
+                Some(hits) => (hits.to_string(), "covered"),
+                None => (String::new(), ""),
+            };
+            let function = functions
+                .iter()
+                .filter(|f| (f.start_line..=f.end_line).contains(&n))
+                .min_by_key(|f| f.end_line - f.start_line)
+                .map(|f| format!("fn-{}", verdict(&f.metrics, f.suppression.as_ref())))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "<tr id=\"L{0}\" class=\"{1}\"><td class=\"hits {2}\">{0}</td><td class=\"hits\">{3}</td><td>{4}</td></tr>",
+                n,
+                class,
+                function,
+                hits,
+                escape(line)
+            )?;
+            Ok(())
+        })?;
+    writeln!(out, "</table>")?;
+    Ok(page(file_path, &out))
+}
+
+// Read the source of an analyzed file, it is decoded lossily as in the analysis
+// A file that cannot be read anymore has no page
+fn read_source(source: &SourceCoverage) -> Option<String> {
+    match fs::read(&source.path) {
+        Ok(data) => Some(String::from_utf8_lossy(&data).into_owned()),
+        Err(e) => {
+            warn!(
+                "Cannot read {}, its html page is not written: {}",
+                source.path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+// Write the index and one page for each file with its source
+fn write_report(
+    rows: &[Row],
+    pages: Vec<(String, String)>,
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    html_path: &Path,
+) -> Result<()> {
+    debug!("Writing html report to: {:?}", html_path);
+    let files = html_path.join("files");
+    fs::create_dir_all(&files).map_err(|e| Error::from(e).with_output(&files))?;
+    pages.into_iter().try_for_each(|(link, content)| {
+        let page = html_path.join(link);
+        fs::write(&page, content).map_err(|e| Error::from(e).with_output(&page))
+    })?;
+    let index = index_page(rows, files_ignored, diagnostics, project_coverage)?;
+    let index_path = html_path.join("index.html");
+    fs::write(&index_path, index).map_err(|e| Error::from(e).with_output(&index_path))
+}
+
+fn file_kind(file_path: &str) -> &'static str {
+    if file_path == "-" {
+        "project"
+    } else {
+        "file"
+    }
+}
+
+/// Write a static html report of the files metrics in the html_path folder
+/// The source of each analyzed file is annotated with the hits of the merged coverage
+pub fn print_metrics_to_html<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    html_path: A,
+) -> Result<()> {
+    let mut pages = vec![];
+    let rows = metrics
+        .iter()
+        .enumerate()
+        .map(|(i, m)| -> Result<Row> {
+            let source = m.source.as_ref();
+            let link =
+                match source.and_then(|source| read_source(source).map(|code| (source, code))) {
+                    Some((source, code)) => {
+                        let link = format!("files/{}.html", i);
+                        pages.push((
+                            link.clone(),
+                            file_page(&m.file_path, &m.metrics, &[], source, &code)?,
+                        ));
+                        Some(link)
+                    }
+                    None => None,
+                };
+            Ok(Row {
+                kind: file_kind(&m.file_path),
+                name: &m.file,
+                path: &m.file_path,
+                lines: None,
+                metrics: &m.metrics,
+                suppression: None,
+                covered_by: None,
+                link,
+            })
+        })
+        .collect::<Result<Vec<Row>>>()?;
+    write_report(
+        &rows,
+        pages,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        html_path.as_ref(),
+    )
+}
+
+/// Write a static html report of the functions metrics in the html_path folder
+/// The source of each analyzed file is annotated with the hits of the merged coverage
+pub fn print_metrics_to_html_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    html_path: A,
+) -> Result<()> {
+    let mut pages = vec![];
+    let mut rows = vec![];
+    metrics
+        .iter()
+        .enumerate()
+        .try_for_each(|(i, m)| -> Result<()> {
+            let source = m.source.as_ref();
+            let link =
+                match source.and_then(|source| read_source(source).map(|code| (source, code))) {
+                    Some((source, code)) => {
+                        let link = format!("files/{}.html", i);
+                        pages.push((
+                            link.clone(),
+                            file_page(&m.file_path, &m.metrics, &m.functions, source, &code)?,
+                        ));
+                        Some(link)
+                    }
+                    None => None,
+                };
+            let kind = file_kind(&m.file_path);
+            rows.push(Row {
+                kind,
+                name: &m.file_name,
+                path: &m.file_path,
+                lines: (kind == "file").then_some((m.start_line, m.end_line)),
+                metrics: &m.metrics,
+                suppression: None,
+                covered_by: Some(&[]),
+                link: link.clone(),
+            });
+            m.functions.iter().for_each(|f| {
+                rows.push(Row {
+                    kind: "function",
+                    name: &f.function_name,
+                    path: &m.file_path,
+                    lines: Some((f.start_line, f.end_line)),
+                    metrics: &f.metrics,
+                    suppression: f.suppression.as_ref(),
+                    covered_by: Some(&f.covered_by),
+                    link: link
+                        .as_ref()
+                        .map(|link| format!("{}#L{}", link, f.start_line)),
+                });
+            });
+            Ok(())
+        })?;
+    write_report(
+        &rows,
+        pages,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        html_path.as_ref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::*;
+    use crate::functions::*;
+    use serde_json::Value;
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const FOLDER: &str = "./data/test_project/";
+
+    #[test]
+    fn test_functions_html() {
+        let reports = [CoverageReport::new(JSON).label(Some("unit".into()))];
+        let options = Options::default();
+        let (metrics, files_ignored, _, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                FOLDER,
+                &reports,
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                &options,
+            )
+            .unwrap();
+        let html_path = Path::new("./data/test_project/html_fun");
+        print_metrics_to_html_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            html_path,
+        )
+        .unwrap();
+        let index = fs::read_to_string(html_path.join("index.html")).unwrap();
+        let flag = metrics
+            .iter()
+            .position(|m| m.file_name == "flag.rs")
+            .unwrap();
+        let page = fs::read_to_string(html_path.join(format!("files/{}.html", flag))).unwrap();
+        fs::remove_dir_all(html_path).unwrap();
+
+        assert!(index.contains(&format!("<a href=\"files/{}.html\">flag.rs</a>", flag)));
+        assert!(index.contains(&format!(
+            "<a href=\"files/{}.html#L155\">opiton_index (155, 175)</a>",
+            flag
+        )));
+        assert!(!index.contains("http"));
+        assert!(page.contains("<h1>src/flag.rs</h1>"));
+        assert!(page.contains("id=\"L155\""));
+        assert!(page.contains("class=\"covered\""));
+        assert!(page.contains("fn-simple"));
+        assert!(index.contains("<th>COVERED BY</th>"));
+        assert!(index.contains("<td>unit</td>"));
+        assert!(page.contains("<td>unit</td>"));
+    }
+
+    #[test]
+    fn test_files_html() {
+        let reports = [CoverageReport::new(JSON)];
+        let options = Options::default();
+        let (metrics, files_ignored, _, project_coverage, diagnostics) = get_metrics_concurrent(
+            FOLDER,
+            &reports,
+            Complexity::Cyclomatic,
+            8,
+            &[30., 1.5, 35., 30.],
+            &options,
+        )
+        .unwrap();
+        let html_path = Path::new("./data/test_project/html_files");
+        print_metrics_to_html(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            html_path,
+        )
+        .unwrap();
+        let index = fs::read_to_string(html_path.join("index.html")).unwrap();
+        fs::remove_dir_all(html_path).unwrap();
+
+        assert_eq!(index.matches("<tr class=\"file\">").count(), 1);
+        assert!(index.contains("<a href=\"files/0.html\">flag.rs</a>"));
+        assert_eq!(index.matches("<tr class=\"project\">").count(), 4);
+        assert!(index.contains("<td>PROJECT</td>"));
+        assert!(!index.contains("COVERED BY"));
+    }
+
+    #[test]
+    fn test_html_sources() {
+        let html_path = Path::new("./data/test_project/html_sources");
+        fs::create_dir_all(html_path).unwrap();
+        // A source that is not valid UTF-8 and a source removed after the analysis
+        let latin1 = html_path.join("latin1.rs");
+        fs::write(&latin1, b"// caf\xe9\nfn main() {}\n").unwrap();
+        let lines = vec![Value::Null, Value::from(1)];
+        let metrics = [
+            FileMetrics::new(Metrics::default(), "latin1.rs".into(), "latin1.rs".into())
+                .source(SourceCoverage::new(&latin1, lines.clone())),
+            FileMetrics::new(Metrics::default(), "gone.rs".into(), "gone.rs".into())
+                .source(SourceCoverage::new(html_path.join("gone.rs"), lines)),
+            FileMetrics::avg(Metrics::default()),
+        ];
+        print_metrics_to_html(&metrics, &[], &Diagnostics::default(), 0., html_path).unwrap();
+        let index = fs::read_to_string(html_path.join("index.html")).unwrap();
+        let page = fs::read_to_string(html_path.join("files/0.html")).unwrap();
+        let pages = fs::read_dir(html_path.join("files")).unwrap().count();
+        fs::remove_dir_all(html_path).unwrap();
+
+        assert!(page.contains("caf\u{fffd}"));
+        assert!(page.contains("fn main() {}"));
+        assert_eq!(pages, 1);
+        assert!(index.contains("<a href=\"files/0.html\">latin1.rs</a>"));
+        assert!(index.contains("<td>gone.rs</td>"));
+    }
+}
@@ -0,0 +1,495 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::*;
+
+use tracing::debug;
+
+use crate::error::*;
+use crate::files::{FileMetrics, Metrics};
+use crate::functions::{RootMetrics, Suppression};
+use crate::output::create_output;
+use crate::utility::Diagnostics;
+
+/// Default number of worst entries listed for each metric
+pub const MARKDOWN_TOP: usize = 5;
+/// Default max size in bytes of the markdown output, below the limit of the GitHub comments
+pub const MARKDOWN_MAX_SIZE: usize = 65000;
+
+const TRUNCATED: &str = "\n_Output truncated, some sections are not shown._\n";
+// Max number of rows of the failed files and mismatches tables
+const DIAGNOSTICS_ROWS: usize = 20;
+
+// Entry of the markdown tables, it can be a file or a function
+struct Entry<'a> {
+    name: &'a str,
+    path: &'a str,
+    metrics: &'a Metrics,
+    suppression: Option<&'a Suppression>,
+    // Labels of the reports covering the entry, only for the functions
+    covered_by: Option<&'a [String]>,
+}
+
+type MetricGetter = fn(&Metrics) -> f64;
+
+const METRICS: [(&str, MetricGetter); 4] = [
+    ("WCC PLAIN", |m| m.sifis_plain),
+    ("WCC QUANTIZED", |m| m.sifis_quantized),
+    ("CRAP", |m| m.crap),
+    ("SKUNKSCORE", |m| m.skunk),
+];
+
+// Escape the characters that break the markdown tables
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn summary(
+    aggregates: &[Entry],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    complex: usize,
+    project_coverage: f64,
+) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "## Weighted code coverage\n")?;
+    writeln!(out, "**Coverage:** {:.2}%\n", project_coverage)?;
+    writeln!(
+        out,
+        "| | WCC PLAIN | WCC QUANTIZED | CRAP | SKUNKSCORE | COMPLEXITY | PLOC | COVERED | INSTRUMENTED |"
+    )?;
+    writeln!(out, "|---|---:|---:|---:|---:|---:|---:|---:|---:|")?;
+    aggregates.iter().try_for_each(|e| {
+        writeln!(
+            out,
+            "| **{}** | {:.3} | {:.3} | {:.3} | {:.3} | {:.2} | {:.2} | {:.2} | {:.2} |",
+            e.name,
+            e.metrics.sifis_plain,
+            e.metrics.sifis_quantized,
+            e.metrics.crap,
+            e.metrics.skunk,
+            e.metrics.complexity,
+            e.metrics.ploc,
+            e.metrics.covered_lines,
+            e.metrics.instrumented_lines
+        )
+    })?;
+    writeln!(out)?;
+    write!(
+        out,
+        "Complex: {} · Files ignored: {}",
+        complex,
+        files_ignored.len()
+    )?;
+    if !diagnostics.files_missing.is_empty() {
+        write!(out, " · Files missing: {}", diagnostics.files_missing.len())?;
+    }
+    if !diagnostics.files_failed.is_empty() {
+        write!(out, " · Files failed: {}", diagnostics.files_failed.len())?;
+    }
+    if !diagnostics.mismatches.is_empty() {
+        write!(out, " · Mismatches: {}", diagnostics.mismatches.len())?;
+    }
+    writeln!(out)?;
+    Ok(out)
+}
+
+// Line closing a diagnostics table whose rows are more than the shown ones
+fn more_rows(out: &mut String, total: usize) -> Result<()> {
+    if total > DIAGNOSTICS_ROWS {
+        writeln!(out, "| … and {} more | |", total - DIAGNOSTICS_ROWS)?;
+    }
+    Ok(())
+}
+
+// Table with the files whose analysis failed
+fn failed_section(diagnostics: &Diagnostics) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "\n| FAILED FILE | ERROR |")?;
+    writeln!(out, "|---|---|")?;
+    diagnostics
+        .files_failed
+        .iter()
+        .take(DIAGNOSTICS_ROWS)
+        .try_for_each(|f| writeln!(out, "| {} | {} |", escape(&f.file), escape(&f.error)))?;
+    more_rows(&mut out, diagnostics.files_failed.len())?;
+    Ok(out)
+}
+
+// Table with the files whose coverage does not match the source
+fn mismatches_section(diagnostics: &Diagnostics) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "\n| MISMATCHED FILE | CHECK | DETAIL | SKIPPED |")?;
+    writeln!(out, "|---|---|---|---|")?;
+    diagnostics
+        .mismatches
+        .iter()
+        .take(DIAGNOSTICS_ROWS)
+        .try_for_each(|m| {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                escape(&m.file),
+                m.check,
+                escape(&m.detail),
+                m.skipped
+            )
+        })?;
+    more_rows(&mut out, diagnostics.mismatches.len())?;
+    Ok(out)
+}
+
+// Table with the top entries with the highest value of a metric
+fn top(entries: &[Entry], n: usize, title: &str, getter: MetricGetter) -> Result<String> {
+    let mut sorted = entries.iter().collect::<Vec<&Entry>>();
+    sorted.sort_by(|a, b| getter(b.metrics).total_cmp(&getter(a.metrics)));
+    let mut out = String::new();
+    writeln!(out, "\n### Top {} by {}\n", n, title)?;
+    writeln!(out, "| NAME | PATH | {} |", title)?;
+    writeln!(out, "|---|---|---:|")?;
+    sorted.iter().take(n).try_for_each(|e| {
+        writeln!(
+            out,
+            "| {} | {} | {:.3} |",
+            escape(e.name),
+            escape(e.path),
+            getter(e.metrics)
+        )
+    })?;
+    Ok(out)
+}
+
+// Collapsible section with the complex entries of a single file
+fn complex_section(path: &str, entries: &[&Entry]) -> Result<String> {
+    let mut out = String::new();
+    let covered_by = entries.iter().any(|e| e.covered_by.is_some());
+    writeln!(
+        out,
+        "\n<details>\n<summary>{} ({})</summary>\n",
+        escape(path),
+        entries.len()
+    )?;
+    writeln!(
+        out,
+        "| NAME | WCC PLAIN | WCC QUANTIZED | CRAP | SKUNKSCORE | COVERAGE | COMPLEXITY | PLOC | COVERED | INSTRUMENTED |{}",
+        if covered_by { " COVERED BY |" } else { "" }
+    )?;
+    writeln!(
+        out,
+        "|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|{}",
+        if covered_by { "---|" } else { "" }
+    )?;
+    entries.iter().try_for_each(|e| {
+        let labels = match e.covered_by {
+            Some(labels) => format!(" {} |", escape(&labels.join(", "))),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "| {} | {:.3} | {:.3} | {:.3} | {:.3} | {:.2} | {:.2} | {:.2} | {:.2} | {:.2} |{}",
+            escape(e.name),
+            e.metrics.sifis_plain,
+            e.metrics.sifis_quantized,
+            e.metrics.crap,
+            e.metrics.skunk,
+            e.metrics.coverage,
+            e.metrics.complexity,
+            e.metrics.ploc,
+            e.metrics.covered_lines,
+            e.metrics.instrumented_lines,
+            labels
+        )
+    })?;
+    writeln!(out, "\n</details>")?;
+    Ok(out)
+}
+
+// Table with the entries whose complexity checks are suppressed in the source
+fn suppressed_section(entries: &[(&Entry, &Suppression)]) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, "\n### Suppressed ({})\n", entries.len())?;
+    writeln!(out, "| NAME | PATH | SUPPRESSION |")?;
+    writeln!(out, "|---|---|---|")?;
+    entries.iter().try_for_each(|(e, s)| {
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            escape(e.name),
+            escape(e.path),
+            escape(&s.describe())
+        )
+    })?;
+    Ok(out)
+}
+
+// Build the markdown adding the sections until the max size is reached
+fn export_entries(
+    aggregates: &[Entry],
+    entries: &[Entry],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    n: usize,
+    max_size: usize,
+) -> Result<String> {
+    let complex = entries
+        .iter()
+        .filter(|e| e.metrics.is_complex)
+        .collect::<Vec<&Entry>>();
+    let mut sections = vec![];
+    if !diagnostics.files_failed.is_empty() {
+        sections.push(failed_section(diagnostics)?);
+    }
+    if !diagnostics.mismatches.is_empty() {
+        sections.push(mismatches_section(diagnostics)?);
+    }
+    METRICS
+        .iter()
+        .try_for_each(|(title, getter)| -> Result<()> {
+            sections.push(top(entries, n, title, *getter)?);
+            Ok(())
+        })?;
+    if !complex.is_empty() {
+        sections.push(format!("\n### Complex ({})\n", complex.len()));
+    }
+    // The entries of the same file are contiguous
+    let mut start = 0;
+    while start < complex.len() {
+        let path = complex[start].path;
+        let len = complex[start..]
+            .iter()
+            .take_while(|e| e.path == path)
+            .count();
+        sections.push(complex_section(path, &complex[start..start + len])?);
+        start += len;
+    }
+    let suppressed = entries
+        .iter()
+        .filter_map(|e| e.suppression.map(|s| (e, s)))
+        .collect::<Vec<(&Entry, &Suppression)>>();
+    if !suppressed.is_empty() {
+        sections.push(suppressed_section(&suppressed)?);
+    }
+    let mut out = summary(
+        aggregates,
+        files_ignored,
+        diagnostics,
+        complex.len(),
+        project_coverage,
+    )?;
+    for section in sections {
+        if out.len() + section.len() + TRUNCATED.len() > max_size {
+            debug!("Markdown output truncated at {} bytes", out.len());
+            out.push_str(TRUNCATED);
+            break;
+        }
+        out.push_str(&section);
+    }
+    Ok(out)
+}
+
+/// Export the files metrics as a markdown summary for pull request comments
+/// It lists the n worst files for each metric and the complex files
+/// The sections that do not fit in max_size bytes are not shown
+pub fn export_to_markdown(
+    metrics: &[FileMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    n: usize,
+    max_size: usize,
+) -> Result<String> {
+    let (aggregates, files): (Vec<Entry>, Vec<Entry>) = metrics
+        .iter()
+        .map(|m| Entry {
+            name: &m.file,
+            path: &m.file_path,
+            metrics: &m.metrics,
+            suppression: None,
+            covered_by: None,
+        })
+        .partition(|e| e.path == "-");
+    export_entries(
+        &aggregates,
+        &files,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        n,
+        max_size,
+    )
+}
+
+/// Export the functions metrics as a markdown summary for pull request comments
+/// It lists the n worst functions for each metric and the complex functions grouped by file
+/// The sections that do not fit in max_size bytes are not shown
+pub fn export_to_markdown_function(
+    metrics: &[RootMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    n: usize,
+    max_size: usize,
+) -> Result<String> {
+    let aggregates = metrics
+        .iter()
+        .filter(|m| m.file_path == "-")
+        .map(|m| Entry {
+            name: &m.file_name,
+            path: &m.file_path,
+            metrics: &m.metrics,
+            suppression: None,
+            covered_by: None,
+        })
+        .collect::<Vec<Entry>>();
+    let functions = metrics
+        .iter()
+        .flat_map(|m| {
+            m.functions.iter().map(move |f| Entry {
+                name: &f.function_name,
+                path: &m.file_path,
+                metrics: &f.metrics,
+                suppression: f.suppression.as_ref(),
+                covered_by: Some(&f.covered_by),
+            })
+        })
+        .collect::<Vec<Entry>>();
+    export_entries(
+        &aggregates,
+        &functions,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        n,
+        max_size,
+    )
+}
+
+/// Print the markdown summary of the files metrics to a file
+pub fn print_metrics_to_markdown<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    n: usize,
+    max_size: usize,
+    markdown_path: A,
+) -> Result<()> {
+    let markdown = export_to_markdown(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        n,
+        max_size,
+    )?;
+    let mut out = create_output(markdown_path.as_ref())?;
+    out.write_all(markdown.as_bytes())?;
+    out.finish()
+        .map_err(|e| e.with_output(markdown_path.as_ref()))
+}
+
+/// Print the markdown summary of the functions metrics to a file
+pub fn print_metrics_to_markdown_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    n: usize,
+    max_size: usize,
+    markdown_path: A,
+) -> Result<()> {
+    let markdown = export_to_markdown_function(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        n,
+        max_size,
+    )?;
+    let mut out = create_output(markdown_path.as_ref())?;
+    out.write_all(markdown.as_bytes())?;
+    out.finish()
+        .map_err(|e| e.with_output(markdown_path.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::*;
+    use crate::utility::*;
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const FOLDER: &str = "./data/test_project/";
+
+    #[test]
+    fn test_functions_markdown() {
+        let (metrics, files_ignored, complex_files, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                FOLDER,
+                &[CoverageReport::new(JSON).label(Some("unit".into()))],
+                Complexity::Cyclomatic,
+                8,
+                &[5., 1.5, 5., 5.],
+                &Options::default(),
+            )
+            .unwrap();
+        let markdown = export_to_markdown_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            3,
+            MARKDOWN_MAX_SIZE,
+        )
+        .unwrap();
+
+        assert!(markdown.starts_with("## Weighted code coverage"));
+        assert!(markdown.contains(&format!("**Coverage:** {:.2}%", project_coverage)));
+        assert!(markdown.contains("| **PROJECT** |"));
+        assert_eq!(markdown.matches("### Top 3 by").count(), 4);
+        assert!(!complex_files.is_empty());
+        assert!(markdown.contains(&format!("### Complex ({})", complex_files.len())));
+        assert!(markdown.contains("<summary>src/flag.rs"));
+        assert!(markdown.contains("| INSTRUMENTED | COVERED BY |"));
+        assert!(markdown.contains(" | unit |"));
+        assert!(!markdown.contains("truncated"));
+
+        let truncated = export_to_markdown_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            3,
+            1000,
+        )
+        .unwrap();
+        assert!(truncated.len() <= 1000);
+        assert!(truncated.starts_with("## Weighted code coverage"));
+        assert!(truncated.ends_with(TRUNCATED));
+        assert!(!truncated.contains("<details>"));
+    }
+
+    #[test]
+    fn test_markdown_many_failures() {
+        let diagnostics = Diagnostics {
+            files_failed: (0..1000)
+                .map(|i| FileFailure {
+                    file: format!("src/file_{}.rs", i),
+                    error: "Error while reading src/file.rs".into(),
+                })
+                .collect(),
+            ..Diagnostics::default()
+        };
+        let markdown = export_to_markdown(&[], &[], &diagnostics, 0., 3, 1000).unwrap();
+        assert!(markdown.len() <= 1000);
+        assert!(markdown.contains("Files failed: 1000"));
+        assert!(markdown.ends_with(TRUNCATED));
+
+        let markdown =
+            export_to_markdown(&[], &[], &diagnostics, 0., 3, MARKDOWN_MAX_SIZE).unwrap();
+        assert_eq!(markdown.matches("| src/file_").count(), DIAGNOSTICS_ROWS);
+        assert!(markdown.contains("| … and 980 more | |"));
+    }
+}
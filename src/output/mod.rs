@@ -0,0 +1,1770 @@
+mod filter;
+mod html;
+mod markdown;
+mod terminal;
+mod tidy;
+mod trend;
+pub use filter::*;
+pub use html::*;
+pub use markdown::*;
+pub use terminal::Console;
+pub use tidy::*;
+pub use trend::*;
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::*;
+
+use csv;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::compress::{compressed_writer, CompressedWriter};
+use crate::error::*;
+use crate::files::FileMetrics;
+use crate::functions::{FunctionMetrics, RootMetrics, Suppression};
+use crate::utility::{is_stdio, Diagnostics, FileFailure, Mismatch};
+use terminal::{Style, Table};
+
+// Struct for JSON for files
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JSONOutput {
+    project_folder: String,
+    number_of_files_ignored: usize,
+    number_of_files_missing: usize,
+    number_of_files_failed: usize,
+    number_of_mismatches: usize,
+    number_of_complex_files: usize,
+    metrics: Vec<FileMetrics>,
+    files_ignored: Vec<String>,
+    files_missing: Vec<String>,
+    files_failed: Vec<FileFailure>,
+    mismatches: Vec<Mismatch>,
+    complex_files: Vec<FileMetrics>,
+    project_coverage: f64,
+}
+
+// Struct for JSON for functions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JSONOutputFunc {
+    project_folder: String,
+    number_of_files_ignored: usize,
+    number_of_files_missing: usize,
+    number_of_files_failed: usize,
+    number_of_mismatches: usize,
+    number_of_complex_functions: usize,
+    files: Vec<RootMetrics>,
+    files_ignored: Vec<String>,
+    files_missing: Vec<String>,
+    files_failed: Vec<FileFailure>,
+    mismatches: Vec<Mismatch>,
+    complex_functions: Vec<FunctionMetrics>,
+    project_coverage: f64,
+}
+
+trait PrintResult<T> {
+    fn print_result(
+        result: &T,
+        files_ignored: usize,
+        complex_files: usize,
+        diagnostics: &Diagnostics,
+        thresholds: &[f64],
+        console: Console,
+    ) -> Result<()>;
+    fn print_json_to_file(
+        result: &T,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        json_path: &Path,
+        project_folder: &Path,
+    ) -> Result<()>;
+    fn print_csv_to_file(
+        result: &T,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        csv_path: &Path,
+    ) -> Result<()>;
+}
+struct Text;
+
+impl PrintResult<Vec<FileMetrics>> for Text {
+    fn print_result(
+        result: &Vec<FileMetrics>,
+        files_ignored: usize,
+        complex_files: usize,
+        diagnostics: &Diagnostics,
+        thresholds: &[f64],
+        console: Console,
+    ) -> Result<()> {
+        let mut out = console.writer();
+        let mut table = Table::new("FILE", thresholds);
+        result
+            .iter()
+            .for_each(|m| table.push(&m.file, &m.metrics, &m.file_path, false));
+        writeln!(out, "{}", table.render(Style::detect(console)))?;
+        print_files_skipped(&mut out, files_ignored, diagnostics)?;
+        writeln!(out, "COMPLEX FILES: {}", complex_files)?;
+        Ok(())
+    }
+    fn print_csv_to_file(
+        result: &Vec<FileMetrics>,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        csv_path: &Path,
+    ) -> Result<()> {
+        let complex_files = result
+            .iter()
+            .filter(|m| m.metrics.is_complex)
+            .cloned()
+            .collect::<Vec<FileMetrics>>();
+        let mut writer = csv::Writer::from_writer(create_output(csv_path)?);
+        writer.write_record([
+            "FILE",
+            "SIFIS PLAIN",
+            "SIFIS QUANTIZED",
+            "CRAP",
+            "SKUNK",
+            "IGNORED",
+            "IS COMPLEX",
+            "FILE PATH",
+            "COVERAGE",
+            "COMPLEXITY",
+            "PLOC",
+            "COVERED LINES",
+            "INSTRUMENTED LINES",
+        ])?;
+        result.iter().try_for_each(|m| -> Result<()> {
+            writer.write_record([
+                &m.file,
+                &format!("{:.3}", m.metrics.sifis_plain),
+                &format!("{:.3}", m.metrics.sifis_quantized),
+                &format!("{:.3}", m.metrics.crap),
+                &format!("{:.3}", m.metrics.skunk),
+                &format!("{}", false),
+                &format!("{}", m.metrics.is_complex),
+                &m.file_path,
+                &format!("{:.2}", m.metrics.coverage),
+                &format!("{:.2}", m.metrics.complexity),
+                &format!("{:.2}", m.metrics.ploc),
+                &format!("{:.2}", m.metrics.covered_lines),
+                &format!("{:.2}", m.metrics.instrumented_lines),
+            ])?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "PROJECT_COVERAGE",
+            format!("{:.3}", project_coverage).as_str(),
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+        ])?;
+        writer.write_record([
+            "LIST OF COMPLEX FILES",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+        ])?;
+        complex_files.iter().try_for_each(|m| -> Result<()> {
+            writer.write_record([
+                &m.file,
+                &format!("{:.3}", m.metrics.sifis_plain),
+                &format!("{:.3}", m.metrics.sifis_quantized),
+                &format!("{:.3}", m.metrics.crap),
+                &format!("{:.3}", m.metrics.skunk),
+                &format!("{}", false),
+                &format!("{}", m.metrics.is_complex),
+                &m.file_path,
+                &format!("{:.2}", m.metrics.coverage),
+                &format!("{:.2}", m.metrics.complexity),
+                &format!("{:.2}", m.metrics.ploc),
+                &format!("{:.2}", m.metrics.covered_lines),
+                &format!("{:.2}", m.metrics.instrumented_lines),
+            ])?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "TOTAL COMPLEX FILES",
+            format!("{:?}", complex_files.len()).as_str(),
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ])?;
+        writer.write_record([
+            "LIST OF IGNORED FILES",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+        ])?;
+        files_ignored.iter().try_for_each(|file| -> Result<()> {
+            writer.write_record([
+                file.as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{}", true).as_str(),
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+            ])?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "TOTAL FILES IGNORED",
+            format!("{:?}", files_ignored.len()).as_str(),
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ])?;
+        write_files_missing(&mut writer, &diagnostics.files_missing, 13)?;
+        write_files_failed(&mut writer, &diagnostics.files_failed, 13)?;
+        write_mismatches(&mut writer, &diagnostics.mismatches, 13)?;
+        finish_csv(writer, csv_path)
+    }
+    fn print_json_to_file(
+        result: &Vec<FileMetrics>,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        json_path: &Path,
+        project_folder: &Path,
+    ) -> Result<()> {
+        let complex_files = result
+            .iter()
+            .filter(|m| m.metrics.is_complex)
+            .cloned()
+            .collect::<Vec<FileMetrics>>();
+        let json = export_to_json(
+            project_folder,
+            result,
+            files_ignored,
+            diagnostics,
+            &complex_files,
+            project_coverage,
+        );
+        let mut out = create_output(json_path)?;
+        serde_json::to_writer(&mut out, &json)?;
+        out.finish().map_err(|e| e.with_output(json_path))
+    }
+}
+impl PrintResult<Vec<RootMetrics>> for Text {
+    fn print_result(
+        result: &Vec<RootMetrics>,
+        files_ignored: usize,
+        complex_files: usize,
+        diagnostics: &Diagnostics,
+        thresholds: &[f64],
+        console: Console,
+    ) -> Result<()> {
+        let mut out = console.writer();
+        let mut table = Table::new("FUNCTION", thresholds);
+        result.iter().for_each(|m| {
+            table.push(&m.file_name, &m.metrics, &m.file_path, false);
+            m.functions.iter().for_each(|f| {
+                table.push(
+                    &f.function_name,
+                    &f.metrics,
+                    &f.file_path,
+                    f.suppression.is_some(),
+                )
+            });
+        });
+        writeln!(out, "{}", table.render(Style::detect(console)))?;
+        print_files_skipped(&mut out, files_ignored, diagnostics)?;
+        writeln!(out, "COMPLEX FUNCTIONS: {}", complex_files)?;
+        let suppressed = result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .filter_map(|f| f.suppression.as_ref().map(|s| (f, s)))
+            .collect::<Vec<(&FunctionMetrics, &Suppression)>>();
+        if !suppressed.is_empty() {
+            writeln!(out, "SUPPRESSED FUNCTIONS: {}", suppressed.len())?;
+        }
+        suppressed.iter().try_for_each(|(f, s)| {
+            writeln!(
+                out,
+                "{} | {} suppressed: {}",
+                f.function_name,
+                f.file_path,
+                s.describe()
+            )
+        })?;
+        result
+            .iter()
+            .flat_map(|m| m.functions.iter())
+            .filter(|f| f.metrics.is_complex && !f.covered_by.is_empty())
+            .try_for_each(|f| {
+                writeln!(
+                    out,
+                    "{} | {} covered by: {}",
+                    f.function_name,
+                    f.file_path,
+                    f.covered_by.join(", ")
+                )
+            })?;
+        Ok(())
+    }
+    fn print_json_to_file(
+        result: &Vec<RootMetrics>,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        json_path: &Path,
+        project_folder: &Path,
+    ) -> Result<()> {
+        let complex_functions: Vec<FunctionMetrics> = result
+            .iter()
+            .flat_map(|m| m.functions.clone())
+            .filter(|m| m.metrics.is_complex)
+            .collect::<Vec<FunctionMetrics>>();
+        let json = export_to_json_function(
+            project_folder,
+            result,
+            files_ignored,
+            diagnostics,
+            &complex_functions,
+            project_coverage,
+        );
+        let mut out = create_output(json_path)?;
+        serde_json::to_writer(&mut out, &json)?;
+        out.finish().map_err(|e| e.with_output(json_path))
+    }
+    fn print_csv_to_file(
+        result: &Vec<RootMetrics>,
+        files_ignored: &[String],
+        diagnostics: &Diagnostics,
+        project_coverage: f64,
+        csv_path: &Path,
+    ) -> Result<()> {
+        let complex_functions: Vec<FunctionMetrics> = result
+            .iter()
+            .flat_map(|m| m.functions.clone())
+            .filter(|m| m.metrics.is_complex)
+            .collect::<Vec<FunctionMetrics>>();
+        let mut writer = csv::Writer::from_writer(create_output(csv_path)?);
+        writer.write_record([
+            "FUNCTION",
+            "SIFIS PLAIN",
+            "SIFIS QUANTIZED",
+            "CRAP",
+            "SKUNK",
+            "IGNORED",
+            "IS COMPLEX",
+            "FILE PATH",
+            "COVERAGE",
+            "COMPLEXITY",
+            "PLOC",
+            "COVERED LINES",
+            "INSTRUMENTED LINES",
+            "QUALIFIED NAME",
+            "KIND CHAIN",
+            "SUPPRESSED",
+            "SUPPRESSION",
+            "COVERED BY",
+        ])?;
+        result.iter().try_for_each(|m| -> Result<()> {
+            writer.write_record([
+                &m.file_name,
+                &format!("{:.3}", m.metrics.sifis_plain),
+                &format!("{:.3}", m.metrics.sifis_quantized),
+                &format!("{:.3}", m.metrics.crap),
+                &format!("{:.3}", m.metrics.skunk),
+                &format!("{}", false),
+                &format!("{}", m.metrics.is_complex),
+                &m.file_path,
+                &format!("{:.2}", m.metrics.coverage),
+                &format!("{:.2}", m.metrics.complexity),
+                &format!("{:.2}", m.metrics.ploc),
+                &format!("{:.2}", m.metrics.covered_lines),
+                &format!("{:.2}", m.metrics.instrumented_lines),
+                &String::new(),
+                &String::new(),
+                &String::new(),
+                &String::new(),
+                &String::new(),
+            ])?;
+            m.functions.iter().try_for_each(|m| -> Result<()> {
+                writer.write_record([
+                    &m.function_name,
+                    &format!("{:.3}", m.metrics.sifis_plain),
+                    &format!("{:.3}", m.metrics.sifis_quantized),
+                    &format!("{:.3}", m.metrics.crap),
+                    &format!("{:.3}", m.metrics.skunk),
+                    &format!("{}", false),
+                    &format!("{}", m.metrics.is_complex),
+                    &m.file_path,
+                    &format!("{:.2}", m.metrics.coverage),
+                    &format!("{:.2}", m.metrics.complexity),
+                    &format!("{:.2}", m.metrics.ploc),
+                    &format!("{:.2}", m.metrics.covered_lines),
+                    &format!("{:.2}", m.metrics.instrumented_lines),
+                    &m.qualified_name,
+                    &m.kind_chain.join(";"),
+                    &m.suppression.is_some().to_string(),
+                    &m.suppression
+                        .as_ref()
+                        .map(|s| s.describe())
+                        .unwrap_or_default(),
+                    &m.covered_by.join(";"),
+                ])?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "PROJECT_COVERAGE",
+            format!("{:.3}", project_coverage).as_str(),
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+            "-",
+        ])?;
+        writer.write_record([
+            "LIST OF COMPLEX FUNCTIONS",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+        ])?;
+        complex_functions.iter().try_for_each(|m| -> Result<()> {
+            writer.write_record([
+                &m.function_name,
+                &format!("{:.3}", m.metrics.sifis_plain),
+                &format!("{:.3}", m.metrics.sifis_quantized),
+                &format!("{:.3}", m.metrics.crap),
+                &format!("{:.3}", m.metrics.skunk),
+                &format!("{}", false),
+                &format!("{}", m.metrics.is_complex),
+                &m.file_path,
+                &format!("{:.2}", m.metrics.coverage),
+                &format!("{:.2}", m.metrics.complexity),
+                &format!("{:.2}", m.metrics.ploc),
+                &format!("{:.2}", m.metrics.covered_lines),
+                &format!("{:.2}", m.metrics.instrumented_lines),
+                &m.qualified_name,
+                &m.kind_chain.join(";"),
+                &m.suppression.is_some().to_string(),
+                &m.suppression
+                    .as_ref()
+                    .map(|s| s.describe())
+                    .unwrap_or_default(),
+                &m.covered_by.join(";"),
+            ])?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "TOTAL COMPLEX FUNCTIONS",
+            format!("{:?}", complex_functions.len()).as_str(),
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ])?;
+        writer.write_record([
+            "LIST OF IGNORED FILES",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+            "----------",
+        ])?;
+        files_ignored.iter().try_for_each(|file| -> Result<()> {
+            writer.write_record([
+                file.as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{:.3}", 0.).as_str(),
+                format!("{}", true).as_str(),
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+                "-",
+            ])?;
+            Ok(())
+        })?;
+        writer.write_record([
+            "TOTAL FILES IGNORED",
+            format!("{:?}", files_ignored.len()).as_str(),
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ])?;
+        write_files_missing(&mut writer, &diagnostics.files_missing, 18)?;
+        write_files_failed(&mut writer, &diagnostics.files_failed, 18)?;
+        write_mismatches(&mut writer, &diagnostics.mismatches, 18)?;
+        finish_csv(writer, csv_path)
+    }
+}
+
+// Write the files of the coverage report not found on disk
+// The section is written only when some files are missing
+// The records are padded to the number of columns of the csv
+fn write_files_missing<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    files_missing: &[String],
+    columns: usize,
+) -> Result<()> {
+    if files_missing.is_empty() {
+        return Ok(());
+    }
+    let mut record = vec!["LIST OF MISSING FILES".to_string()];
+    record.resize(columns, "----------".into());
+    writer.write_record(&record)?;
+    files_missing.iter().try_for_each(|file| -> Result<()> {
+        let mut record = vec![
+            file.to_string(),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{}", true),
+        ];
+        record.resize(columns, "-".into());
+        writer.write_record(&record)?;
+        Ok(())
+    })?;
+    let mut record = vec![
+        "TOTAL FILES MISSING".to_string(),
+        format!("{:?}", files_missing.len()),
+    ];
+    record.resize(columns, String::new());
+    writer.write_record(&record)?;
+    Ok(())
+}
+
+// Write the files whose analysis failed with their error in the FILE PATH column
+// The section is written only when some files failed
+fn write_files_failed<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    files_failed: &[FileFailure],
+    columns: usize,
+) -> Result<()> {
+    if files_failed.is_empty() {
+        return Ok(());
+    }
+    let mut record = vec!["LIST OF FAILED FILES".to_string()];
+    record.resize(columns, "----------".into());
+    writer.write_record(&record)?;
+    files_failed.iter().try_for_each(|failure| -> Result<()> {
+        let mut record = vec![
+            failure.file.to_string(),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{:.3}", 0.),
+            format!("{}", true),
+            "-".into(),
+            failure.error.to_string(),
+        ];
+        record.resize(columns, "-".into());
+        writer.write_record(&record)?;
+        Ok(())
+    })?;
+    let mut record = vec![
+        "TOTAL FILES FAILED".to_string(),
+        format!("{:?}", files_failed.len()),
+    ];
+    record.resize(columns, String::new());
+    writer.write_record(&record)?;
+    Ok(())
+}
+
+// Write the checks of the coverage against the sources that failed with their detail
+// The section is written only when some checks failed
+fn write_mismatches<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    mismatches: &[Mismatch],
+    columns: usize,
+) -> Result<()> {
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let mut record = vec!["LIST OF MISMATCHES".to_string()];
+    record.resize(columns, "----------".into());
+    writer.write_record(&record)?;
+    mismatches.iter().try_for_each(|mismatch| -> Result<()> {
+        let mut record = vec![
+            mismatch.file.to_string(),
+            mismatch.check.to_string(),
+            mismatch.detail.to_string(),
+            format!("{}", mismatch.skipped),
+        ];
+        record.resize(columns, "-".into());
+        writer.write_record(&record)?;
+        Ok(())
+    })?;
+    let mut record = vec![
+        "TOTAL MISMATCHES".to_string(),
+        format!("{:?}", mismatches.len()),
+    ];
+    record.resize(columns, String::new());
+    writer.write_record(&record)?;
+    Ok(())
+}
+
+// Print the number of files not analyzed, the error of each failed file
+// and the checks of the coverage that failed
+fn print_files_skipped(
+    out: &mut dyn Write,
+    files_ignored: usize,
+    diagnostics: &Diagnostics,
+) -> Result<()> {
+    writeln!(out, "FILES IGNORED: {}", files_ignored)?;
+    if !diagnostics.files_missing.is_empty() {
+        writeln!(out, "FILES MISSING: {}", diagnostics.files_missing.len())?;
+    }
+    if !diagnostics.files_failed.is_empty() {
+        writeln!(out, "FILES FAILED: {}", diagnostics.files_failed.len())?;
+    }
+    diagnostics
+        .files_failed
+        .iter()
+        .try_for_each(|f| writeln!(out, "{} failed: {}", f.file, f.error))?;
+    if !diagnostics.mismatches.is_empty() {
+        writeln!(out, "MISMATCHES: {}", diagnostics.mismatches.len())?;
+    }
+    diagnostics.mismatches.iter().try_for_each(|m| {
+        let skipped = if m.skipped { ", skipped" } else { "" };
+        writeln!(
+            out,
+            "{} does not match ({}{}): {}",
+            m.file, m.check, skipped, m.detail
+        )
+    })?;
+    Ok(())
+}
+
+// Writer of an output, to a file or to the standard output
+pub(crate) type Output = CompressedWriter<Box<dyn Write>>;
+
+// Create the file of an output, the path - writes the output to the standard output
+// The outputs whose path ends with .gz, .zst or .xz are compressed
+// The output must be finished once written
+pub(crate) fn create_output(path: &Path) -> Result<Output> {
+    if is_stdio(path) {
+        return Ok(CompressedWriter::Plain(Box::new(io::stdout())));
+    }
+    File::create(path)
+        .map_err(Error::from)
+        .and_then(|file| compressed_writer(path, Box::new(file) as Box<dyn Write>))
+        .map_err(|e| e.with_output(path))
+}
+
+// Flush a csv writer and finish its output written to path
+pub(crate) fn finish_csv(writer: csv::Writer<Output>, path: &Path) -> Result<()> {
+    writer
+        .into_inner()
+        .map_err(|e| Error::from(io::Error::new(e.error().kind(), e.error().to_string())))
+        .and_then(|out| out.finish())
+        .map_err(|e| e.with_output(path))
+}
+
+// Export all metrics to a json file
+pub fn export_to_json(
+    project_folder: &Path,
+    metrics: &[FileMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    complex_files: &[FileMetrics],
+    project_coverage: f64,
+) -> JSONOutput {
+    let number_of_files_ignored = files_ignored.len();
+    let number_of_files_missing = diagnostics.files_missing.len();
+    let number_of_complex_files = complex_files.len();
+
+    JSONOutput {
+        project_folder: project_folder.display().to_string(),
+        number_of_files_ignored,
+        number_of_files_missing,
+        number_of_files_failed: diagnostics.files_failed.len(),
+        number_of_mismatches: diagnostics.mismatches.len(),
+        number_of_complex_files,
+        metrics: metrics.to_vec(),
+        files_ignored: files_ignored.to_vec(),
+        files_missing: diagnostics.files_missing.to_vec(),
+        files_failed: diagnostics.files_failed.to_vec(),
+        mismatches: diagnostics.mismatches.to_vec(),
+        complex_files: complex_files.to_vec(),
+        project_coverage,
+    }
+}
+
+// Export all metrics to a json file for functions mode
+pub fn export_to_json_function(
+    project_folder: &Path,
+    metrics: &[RootMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    complex_functions: &[FunctionMetrics],
+    project_coverage: f64,
+) -> JSONOutputFunc {
+    let number_of_files_ignored = files_ignored.len();
+    let number_of_files_missing = diagnostics.files_missing.len();
+    let number_of_complex_functions = complex_functions.len();
+    JSONOutputFunc {
+        project_folder: project_folder.display().to_string(),
+        number_of_files_ignored,
+        number_of_files_missing,
+        number_of_files_failed: diagnostics.files_failed.len(),
+        number_of_mismatches: diagnostics.mismatches.len(),
+        number_of_complex_functions,
+        files: metrics.to_vec(),
+        files_ignored: files_ignored.to_vec(),
+        files_missing: diagnostics.files_missing.to_vec(),
+        files_failed: diagnostics.files_failed.to_vec(),
+        mismatches: diagnostics.mismatches.to_vec(),
+        complex_functions: complex_functions.to_vec(),
+        project_coverage,
+    }
+}
+
+/// This Function get the folder of the repo to analyzed and the path to the json obtained using grcov
+/// It prints all the SIFIS, CRAP and SkunkScore values for all the files in the folders
+/// the output will be print as follows:
+/// FILE       | WCC PLAIN | WCC QUANTIZED | CRAP       | SKUNKSCORE | ... | IS_COMPLEX | PATH
+/// the table fits the terminal width and it is coloured using the thresholds
+/// if the a file is not found in the json that files will be skipped
+pub fn get_metrics_output(
+    metrics: &Vec<FileMetrics>,
+    files_ignored: &[String],
+    complex_files: &[FileMetrics],
+    diagnostics: &Diagnostics,
+    thresholds: &[f64],
+    console: Console,
+) -> Result<()> {
+    Text::print_result(
+        metrics,
+        files_ignored.len(),
+        complex_files.len(),
+        diagnostics,
+        thresholds,
+        console,
+    )
+}
+
+/// Prints the the given  metrics ,files ignored and complex files  in a csv format
+/// The structure is the following :
+/// "FILE","SIFIS PLAIN","SIFIS QUANTIZED","CRAP","SKUNK","IGNORED","IS COMPLEX","FILE PATH",
+pub fn print_metrics_to_csv<A: AsRef<Path> + Copy>(
+    metrics: &Vec<FileMetrics>,
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    csv_path: A,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to csv...");
+    Text::print_csv_to_file(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        csv_path.as_ref(),
+    )
+}
+
+/// Prints the the given  metrics ,files ignored and complex files  in a json format
+pub fn print_metrics_to_json<A: AsRef<Path> + Copy>(
+    metrics: &Vec<FileMetrics>,
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    json_output: A,
+    project_folder: A,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to json...");
+    Text::print_json_to_file(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        json_output.as_ref(),
+        project_folder.as_ref(),
+    )
+}
+
+pub fn get_metrics_output_function(
+    metrics: &Vec<RootMetrics>,
+    files_ignored: &[String],
+    complex_files: &[FunctionMetrics],
+    diagnostics: &Diagnostics,
+    thresholds: &[f64],
+    console: Console,
+) -> Result<()> {
+    Text::print_result(
+        metrics,
+        files_ignored.len(),
+        complex_files.len(),
+        diagnostics,
+        thresholds,
+        console,
+    )
+}
+
+/// Prints the the given  metrics per function ,files ignored and complex function  in a csv format
+/// The structure is the following :
+/// "FUNCTION","SIFIS PLAIN","SIFIS QUANTIZED","CRAP","SKUNK","IGNORED","IS COMPLEX","FILE PATH",
+pub fn print_metrics_to_csv_function<A: AsRef<Path> + Copy>(
+    metrics: &Vec<RootMetrics>,
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    csv_path: A,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to csv...");
+    Text::print_csv_to_file(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        csv_path.as_ref(),
+    )
+}
+
+/// Prints the the given  metrics per function,files ignored and complex functions  in a json format
+pub fn print_metrics_to_json_function<A: AsRef<Path> + Copy>(
+    metrics: &Vec<RootMetrics>,
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    json_output: A,
+    project_folder: A,
+    project_coverage: f64,
+) -> Result<()> {
+    debug!("Exporting to json...");
+    Text::print_json_to_file(
+        metrics,
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        json_output.as_ref(),
+        project_folder.as_ref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::*;
+    use crate::functions::*;
+    use crate::utility::*;
+    use std::fs;
+    use std::path::Path;
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const FOLDER: &str = "./data/test_project/";
+
+    #[test]
+    fn test_file_csv() {
+        let json = Path::new(JSON);
+        let (metrics, files_ignored, _complex_files, project_coverage, diagnostics) =
+            get_metrics_concurrent(
+                "./data/test_project/",
+                &[CoverageReport::new(json)],
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                &Options::default(),
+            )
+            .unwrap();
+        Text::print_csv_to_file(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            Path::new("./data/test_project/to_compare.csv"),
+        )
+        .unwrap();
+        let to_compare = fs::read_to_string("./data/test_project/to_compare.csv").unwrap();
+        let expected = fs::read_to_string("./data/test_project/test.csv")
+            .unwrap()
+            .replace('\r', "");
+        assert!(to_compare == expected);
+        fs::remove_file("./data/test_project/to_compare.csv").unwrap();
+    }
+
+    #[test]
+    fn test_file_json() {
+        let json = Path::new(JSON);
+        let path = Path::new(FOLDER);
+        let (metrics, files_ignored, complex_files, project_coverage, diagnostics) =
+            get_metrics_concurrent(
+                "./data/test_project/",
+                &[CoverageReport::new(json)],
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                &Options::default(),
+            )
+            .unwrap();
+        let mut to_compare = export_to_json(
+            path,
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            &complex_files,
+            project_coverage,
+        );
+        // The sources are not part of the json
+        to_compare
+            .metrics
+            .iter_mut()
+            .chain(to_compare.complex_files.iter_mut())
+            .for_each(|m| m.source = None);
+        let expected = JSONOutput {
+            project_folder: "./data/test_project/".into(),
+            number_of_files_ignored: 0,
+            number_of_files_missing: 0,
+            number_of_files_failed: 0,
+            number_of_mismatches: 0,
+            number_of_complex_files: 1,
+            metrics: vec![
+                FileMetrics {
+                    metrics: Metrics {
+                        sifis_plain: 34.696335078534034,
+                        sifis_quantized: 0.7382198952879581,
+                        crap: 48.32881221072737,
+                        skunk: 15.87012987012987,
+                        is_complex: true,
+                        coverage: 91.56,
+                        complexity: 47.0,
+                        ploc: 191.0,
+                        covered_lines: 141.0,
+                        instrumented_lines: 154.0,
+                    },
+                    file: "flag.rs".into(),
+                    file_path: "src/flag.rs".into(),
+                    source: None,
+                },
+                FileMetrics {
+                    metrics: Metrics {
+                        sifis_plain: 34.696335078534034,
+                        sifis_quantized: 0.7382198952879581,
+                        crap: 48.32881221072737,
+                        skunk: 15.87012987012987,
+                        is_complex: false,
+                        coverage: 91.55844155844156,
+                        complexity: 47.0,
+                        ploc: 191.0,
+                        covered_lines: 141.0,
+                        instrumented_lines: 154.0,
+                    },
+                    file: "PROJECT".into(),
+                    file_path: "-".into(),
+                    source: None,
+                },
+                FileMetrics {
+                    metrics: Metrics {
+                        sifis_plain: 34.696335078534034,
+                        sifis_quantized: 0.7382198952879581,
+                        crap: 48.32881221072737,
+                        skunk: 15.87012987012987,
+                        is_complex: false,
+                        coverage: 91.56,
+                        complexity: 47.0,
+                        ploc: 191.0,
+                        covered_lines: 141.0,
+                        instrumented_lines: 154.0,
+                    },
+                    file: "AVG".into(),
+                    file_path: "-".into(),
+                    source: None,
+                },
+                FileMetrics {
+                    metrics: Metrics {
+                        sifis_plain: 34.696335078534034,
+                        sifis_quantized: 0.7382198952879581,
+                        crap: 48.32881221072737,
+                        skunk: 15.87012987012987,
+                        is_complex: false,
+                        coverage: 91.56,
+                        complexity: 47.0,
+                        ploc: 191.0,
+                        covered_lines: 141.0,
+                        instrumented_lines: 154.0,
+                    },
+                    file: "MAX".into(),
+                    file_path: "-".into(),
+                    source: None,
+                },
+                FileMetrics {
+                    metrics: Metrics {
+                        sifis_plain: 34.696335078534034,
+                        sifis_quantized: 0.7382198952879581,
+                        crap: 48.32881221072737,
+                        skunk: 15.87012987012987,
+                        is_complex: false,
+                        coverage: 91.56,
+                        complexity: 47.0,
+                        ploc: 191.0,
+                        covered_lines: 141.0,
+                        instrumented_lines: 154.0,
+                    },
+                    file: "MIN".into(),
+                    file_path: "-".into(),
+                    source: None,
+                },
+            ],
+            files_ignored: Vec::<String>::new(),
+            files_missing: Vec::<String>::new(),
+            files_failed: Vec::<FileFailure>::new(),
+            mismatches: Vec::<Mismatch>::new(),
+            complex_files: vec![FileMetrics {
+                metrics: Metrics {
+                    sifis_plain: 34.696335078534034,
+                    sifis_quantized: 0.7382198952879581,
+                    crap: 48.32881221072737,
+                    skunk: 15.87012987012987,
+                    is_complex: true,
+                    coverage: 91.56,
+                    complexity: 47.0,
+                    ploc: 191.0,
+                    covered_lines: 141.0,
+                    instrumented_lines: 154.0,
+                },
+                file: "flag.rs".into(),
+                file_path: "src/flag.rs".into(),
+                source: None,
+            }],
+            project_coverage: 91.56,
+        };
+        assert!(to_compare == expected);
+    }
+
+    #[test]
+    fn test_functions_csv() {
+        let json = Path::new(JSON);
+        let (metrics, files_ignored, _complex_files, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                "./data/test_project/",
+                &[CoverageReport::new(json)],
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                &Options::default(),
+            )
+            .unwrap();
+        Text::print_csv_to_file(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            Path::new("./data/test_project/to_compare_fun.csv"),
+        )
+        .unwrap();
+        let to_compare = fs::read_to_string("./data/test_project/to_compare_fun.csv").unwrap();
+        let expected = fs::read_to_string("./data/test_project/test_fun.csv")
+            .unwrap()
+            .replace('\r', "");
+        assert!(to_compare == expected);
+        fs::remove_file("./data/test_project/to_compare_fun.csv").unwrap();
+    }
+
+    #[test]
+    fn test_functions_json() {
+        let json = Path::new(JSON);
+        let (metrics, files_ignored, complex_files, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                "./data/test_project/",
+                &[CoverageReport::new(json)],
+                Complexity::Cyclomatic,
+                8,
+                &[30., 1.5, 35., 30.],
+                &Options::default(),
+            )
+            .unwrap();
+        let path = Path::new(FOLDER);
+        let mut to_compare = export_to_json_function(
+            path,
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            &complex_files,
+            project_coverage,
+        );
+        // The sources are not part of the json
+        to_compare.files.iter_mut().for_each(|m| m.source = None);
+        let expected= JSONOutputFunc {
+                project_folder: "./data/test_project/".into(),
+                number_of_files_ignored: 0,
+                number_of_files_missing: 0,
+                number_of_files_failed: 0,
+            number_of_mismatches: 0,
+                number_of_complex_functions: 0,
+                files: vec![
+                    RootMetrics {
+                        metrics: Metrics {
+                            sifis_plain: 34.696335078534034,
+                            sifis_quantized: 0.7382198952879581,
+                            crap: 48.32881221072737,
+                            skunk: 15.87012987012987,
+                            is_complex: true,
+                            coverage: 91.56,
+                            complexity: 47.0,
+                            ploc: 191.0,
+                            covered_lines: 141.0,
+                            instrumented_lines: 154.0,
+                        },
+                        file_name: "flag.rs".into(),
+                        file_path: "src/flag.rs".into(),
+                        start_line: 1,
+                        end_line: 261,
+                        functions: vec![
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 0.7619047619047619,
+                                    sifis_quantized: 0.7619047619047619,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 21.0,
+                                    covered_lines: 16.0,
+                                    instrumented_lines: 16.0,
+                                },
+                                function_name: "opiton_index (155, 175)".into(),
+                                file_path: "/opiton_index (155,175)".into(),
+                                start_line: 155,
+                                end_line: 175,
+                                qualified_name: "opiton_index".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics{
+                                    sifis_plain: 1.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 3.0,
+                                    covered_lines: 3.0,
+                                    instrumented_lines: 3.0,
+                                },
+                                function_name: "construct_fail_1 (179, 181)".into(),
+                                file_path: "/construct_fail_1 (179,181)".into(),
+                                start_line: 179,
+                                end_line: 181,
+                                qualified_name: "construct_fail_1".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics{
+                                    sifis_plain: 1.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 3.0,
+                                    covered_lines: 3.0,
+                                    instrumented_lines: 3.0,
+                                },
+                                function_name: "construct_fail_2 (185, 187)".into(),
+                                file_path: "/construct_fail_2 (185,187)".into(),
+                                start_line: 185,
+                                end_line: 187,
+                                qualified_name: "construct_fail_2".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 1.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 3.0,
+                                    covered_lines: 3.0,
+                                    instrumented_lines: 3.0,
+                                },
+                                function_name: "construct_fail_3 (191, 193)".into(),
+                                file_path: "/construct_fail_3 (191,193)".into(),
+                                start_line: 191,
+                                end_line: 193,
+                                qualified_name: "construct_fail_3".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 2.769230769230769,
+                                    sifis_quantized: 0.9230769230769231,
+                                    crap: 3.0040964952207556,
+                                    skunk: 0.9230769230769231,
+                                    is_complex: false,
+                                    coverage: 92.31,
+                                    complexity: 3.0,
+                                    ploc: 13.0,
+                                    covered_lines: 12.0,
+                                    instrumented_lines: 13.0,
+                                },
+                                function_name: "bool_flag_test (196, 209)".into(),
+                                file_path: "/bool_flag_test (196,209)".into(),
+                                start_line: 196,
+                                end_line: 209,
+                                qualified_name: "bool_flag_test".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics{
+                                    sifis_plain: 2.7857142857142856,
+                                    sifis_quantized: 0.9285714285714286,
+                                    crap: 3.003279883381924,
+                                    skunk: 0.8571428571428567,
+                                    is_complex: false,
+                                    coverage: 92.86,
+                                    complexity: 3.0,
+                                    ploc: 14.0,
+                                    covered_lines: 13.0,
+                                    instrumented_lines: 14.0,
+                                },
+                                function_name: "string_flag_test (212, 226)".into(),
+                                file_path: "/string_flag_test (212,226)".into(),
+                                start_line: 212,
+                                end_line: 226,
+                                qualified_name: "string_flag_test".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 2.7857142857142856,
+                                    sifis_quantized: 0.9285714285714286,
+                                    crap: 3.003279883381924,
+                                    skunk: 0.8571428571428567,
+                                    is_complex: false,
+                                    coverage: 92.86,
+                                    complexity: 3.0,
+                                    ploc: 14.0,
+                                    covered_lines: 13.0,
+                                    instrumented_lines: 14.0,
+                                },
+                                function_name: "int_flag_test (229, 243)".into(),
+                                file_path: "/int_flag_test (229,243)".into(),
+                                start_line: 229,
+                                end_line: 243,
+                                qualified_name: "int_flag_test".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 2.7857142857142856,
+                                    sifis_quantized: 0.9285714285714286,
+                                    crap: 3.003279883381924,
+                                    skunk: 0.8571428571428567,
+                                    is_complex: false,
+                                    coverage: 92.86,
+                                    complexity: 3.0,
+                                    ploc: 14.0,
+                                    covered_lines: 13.0,
+                                    instrumented_lines: 14.0,
+                                },
+                                function_name: "float_flag_test (246, 260)".into(),
+                                file_path: "/float_flag_test (246,260)".into(),
+                                start_line: 246,
+                                end_line: 260,
+                                qualified_name: "float_flag_test".into(),
+                                kind_chain: vec!["function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 4.666666666666667,
+                                    sifis_quantized: 1.1666666666666667,
+                                    crap: 4.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 4.0,
+                                    ploc: 24.0,
+                                    covered_lines: 28.0,
+                                    instrumented_lines: 28.0,
+                                },
+                                function_name: "new (47, 74)".into(),
+                                file_path: "/Flag (36,148)/new (47,74)".into(),
+                                start_line: 47,
+                                end_line: 74,
+                                qualified_name: "Flag::new".into(),
+                                kind_chain: vec!["impl".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 0.0,
+                                    sifis_quantized: 0.0,
+                                    crap: 2.0,
+                                    skunk: 4.0,
+                                    is_complex: false,
+                                    coverage: 0.0,
+                                    complexity: 1.0,
+                                    ploc: 4.0,
+                                    covered_lines: 0.0,
+                                    instrumented_lines: 4.0,
+                                },
+                                function_name: "description (86, 89)".into(),
+                                file_path: "/Flag (36,148)/description (86,89)".into(),
+                                start_line: 86,
+                                end_line: 89,
+                                qualified_name: "Flag::description".into(),
+                                kind_chain: vec!["impl".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 1.5,
+                                    sifis_quantized: 0.75,
+                                    crap: 2.011661807580175,
+                                    skunk: 1.1428571428571435,
+                                    is_complex: false,
+                                    coverage: 85.71,
+                                    complexity: 2.0,
+                                    ploc: 8.0,
+                                    covered_lines: 6.0,
+                                    instrumented_lines: 7.0,
+                                },
+                                function_name: "alias (105, 112)".into(),
+                                file_path: "/Flag (36,148)/alias (105,112)".into(),
+                                start_line: 105,
+                                end_line: 112,
+                                qualified_name: "Flag::alias".into(),
+                                kind_chain: vec!["impl".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 6.125,
+                                    sifis_quantized: 0.875,
+                                    crap: 7.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 7.0,
+                                    ploc: 8.0,
+                                    covered_lines: 7.0,
+                                    instrumented_lines: 7.0,
+                                },
+                                function_name: "option_index (115, 122)".into(),
+                                file_path: "/Flag (36,148)/option_index (115,122)".into(),
+                                start_line: 115,
+                                end_line: 122,
+                                qualified_name: "Flag::option_index".into(),
+                                kind_chain: vec!["impl".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics{
+                                    sifis_plain: 8.478260869565217,
+                                    sifis_quantized: 0.5652173913043478,
+                                    crap: 17.93099938937513,
+                                    skunk: 14.11764705882353,
+                                    is_complex: false,
+                                    coverage: 76.47,
+                                    complexity: 15.0,
+                                    ploc: 23.0,
+                                    covered_lines: 13.0,
+                                    instrumented_lines: 17.0,
+                                },
+                                function_name: "value (125, 147)".into(),
+                                file_path: "/Flag (36,148)/value (125,147)".into(),
+                                start_line: 125,
+                                end_line: 147,
+                                qualified_name: "Flag::value".into(),
+                                kind_chain: vec!["impl".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics{
+                                    sifis_plain: 3.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 3.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 3.0,
+                                    ploc: 3.0,
+                                    covered_lines: 3.0,
+                                    instrumented_lines: 3.0,
+                                },
+                                function_name: "<anonymous> (117, 119)".into(),
+                                file_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (117,119)".into(),
+                                start_line: 117,
+                                end_line: 119,
+                                qualified_name: "Flag::option_index::<anonymous>@117".into(),
+                                kind_chain: vec!["impl".into(), "function".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 1.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 1.0,
+                                    covered_lines: 1.0,
+                                    instrumented_lines: 1.0,
+                                },
+                                function_name: "<anonymous> (120, 120)".into(),
+                                file_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (120,120)".into(),
+                                start_line: 120,
+                                end_line: 120,
+                                qualified_name: "Flag::option_index::<anonymous>@120".into(),
+                                kind_chain: vec!["impl".into(), "function".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            },
+                            FunctionMetrics {
+                                metrics: Metrics {
+                                    sifis_plain: 1.0,
+                                    sifis_quantized: 1.0,
+                                    crap: 1.0,
+                                    skunk: 0.0,
+                                    is_complex: false,
+                                    coverage: 100.0,
+                                    complexity: 1.0,
+                                    ploc: 1.0,
+                                    covered_lines: 1.0,
+                                    instrumented_lines: 1.0,
+                                },
+                                function_name: "<anonymous> (118, 118)".into(),
+                                file_path: "/Flag (36,148)/option_index (115,122)/<anonymous> (117,119)/<anonymous> (118,118)".into(),
+                                start_line: 118,
+                                end_line: 118,
+                                qualified_name: "Flag::option_index::<anonymous>::<anonymous>".into(),
+                                kind_chain: vec!["impl".into(), "function".into(), "function".into(), "function".into()],
+                                covered_by: vec![],
+                                suppression: None,
+                            }
+                        ],
+                        source: None,
+                    },
+                    RootMetrics {
+                        metrics: Metrics {
+                            sifis_plain: 34.696335078534034,
+                            sifis_quantized: 0.7382198952879581,
+                            crap: 48.32881221072737,
+                            skunk: 15.87012987012987,
+                            is_complex: false,
+                            coverage: 91.55844155844156,
+                            complexity: 47.0,
+                            ploc: 191.0,
+                            covered_lines: 141.0,
+                            instrumented_lines: 154.0,
+                        },
+                        file_name: "PROJECT".into(),
+                        file_path: "-".into(),
+                        start_line: 0,
+                        end_line: 0,
+                        functions: Vec::<FunctionMetrics>::new(),
+                        source: None,
+                    },
+                    RootMetrics {
+                        metrics: Metrics {
+                            sifis_plain: 34.696335078534034,
+                            sifis_quantized: 0.7382198952879581,
+                            crap: 48.32881221072737,
+                            skunk: 15.87012987012987,
+                            is_complex: false,
+                            coverage: 91.56,
+                            complexity: 47.0,
+                            ploc: 191.0,
+                            covered_lines: 141.0,
+                            instrumented_lines: 154.0,
+                        },
+                        file_name: "AVG".into(),
+                        file_path: "-".into(),
+                        start_line: 0,
+                        end_line: 0,
+                        functions: Vec::<FunctionMetrics>::new(),
+                        source: None,
+                    },
+                    RootMetrics {
+                        metrics: Metrics {
+                            sifis_plain: 34.696335078534034,
+                            sifis_quantized: 0.7382198952879581,
+                            crap: 48.32881221072737,
+                            skunk: 15.87012987012987,
+                            is_complex: false,
+                            coverage: 91.56,
+                            complexity: 47.0,
+                            ploc: 191.0,
+                            covered_lines: 141.0,
+                            instrumented_lines: 154.0,
+                        },
+                        file_name: "MAX".into(),
+                        file_path: "-".into(),
+                        start_line: 0,
+                        end_line: 0,
+                        functions: Vec::<FunctionMetrics>::new(),
+                        source: None,
+                    },
+                    RootMetrics {
+                        metrics: Metrics {
+                            sifis_plain: 34.696335078534034,
+                            sifis_quantized: 0.7382198952879581,
+                            crap: 48.32881221072737,
+                            skunk: 15.87012987012987,
+                            is_complex: false,
+                            coverage: 91.56,
+                            complexity: 47.0,
+                            ploc: 191.0,
+                            covered_lines: 141.0,
+                            instrumented_lines: 154.0,
+                        },
+                        file_name: "MIN".into(),
+                        file_path: "-".into(),
+                        start_line: 0,
+                        end_line: 0,
+                        functions: Vec::<FunctionMetrics>::new(),
+                        source: None,
+                    }
+                ],
+                files_ignored: Vec::<String>::new(),
+                files_missing: Vec::<String>::new(),
+                files_failed: Vec::<FileFailure>::new(),
+            mismatches: Vec::<Mismatch>::new(),
+                complex_functions: Vec::<FunctionMetrics>::new(),
+                project_coverage: 91.56
+        };
+        assert!(to_compare == expected);
+    }
+
+    #[test]
+    fn test_functions_qualified_names() {
+        let (metrics, files_ignored, _complex_files, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                "./data/names_project/",
+                &[CoverageReport::new("./data/names_project/coveralls.json")],
+                Complexity::Cyclomatic,
+                2,
+                &[30., 1.5, 35., 30.],
+                &Options::default().space_kinds(vec![Space::Impl, Space::Function]),
+            )
+            .unwrap();
+        let expected = [
+            ("App@7", "impl"),
+            ("App::fmt@8", "impl;function"),
+            ("App@13", "impl"),
+            ("App::fmt@14", "impl;function"),
+            ("App@19", "impl"),
+            ("App::new", "impl;function"),
+            ("App::new::trim", "impl;function;function"),
+        ];
+
+        Text::print_json_to_file(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            Path::new("./data/names_project/to_compare.json"),
+            Path::new("./data/names_project/"),
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string("./data/names_project/to_compare.json").unwrap(),
+        )
+        .unwrap();
+        fs::remove_file("./data/names_project/to_compare.json").unwrap();
+        let mut names = json["files"][0]["functions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                (
+                    f["qualified_name"].as_str().unwrap().to_string(),
+                    f["kind_chain"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|k| k.as_str().unwrap())
+                        .collect::<Vec<&str>>()
+                        .join(";"),
+                )
+            })
+            .collect::<Vec<(String, String)>>();
+        names.sort();
+        let mut sorted = expected.to_vec();
+        sorted.sort();
+        assert_eq!(
+            names,
+            sorted
+                .iter()
+                .map(|(name, kinds)| (name.to_string(), kinds.to_string()))
+                .collect::<Vec<(String, String)>>()
+        );
+
+        Text::print_csv_to_file(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            Path::new("./data/names_project/to_compare.csv"),
+        )
+        .unwrap();
+        let csv = fs::read_to_string("./data/names_project/to_compare.csv").unwrap();
+        fs::remove_file("./data/names_project/to_compare.csv").unwrap();
+        expected.iter().for_each(|(name, kinds)| {
+            assert!(
+                csv.contains(&format!(",{},{},", name, kinds)),
+                "{} not in {}",
+                name,
+                csv
+            )
+        });
+    }
+
+    #[test]
+    fn test_output_errors() {
+        let path = Path::new("./data/missing_dir/out.csv.gz");
+        let error = create_output(path).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Error while writing ./data/missing_dir/out.csv.gz"
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::io::{self, IsTerminal, Write};
+
+use crate::files::Metrics;
+
+pub(crate) const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+pub(crate) const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// Values above this fraction of the threshold are shown in yellow
+const WARNING_RATIO: f64 = 0.75;
+
+// Min width of the name and path columns when the table is shrunk
+const MIN_WIDTH: usize = 12;
+
+const SEPARATOR: &str = " | ";
+
+/// Stream where the table and the summary are printed
+/// They go to stderr when stdout carries a report, so the report can be piped
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Console {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+impl Console {
+    pub(crate) fn writer(&self) -> Box<dyn Write> {
+        match self {
+            Console::Stdout => Box::new(io::stdout()),
+            Console::Stderr => Box::new(io::stderr()),
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        match self {
+            Console::Stdout => io::stdout().is_terminal(),
+            Console::Stderr => io::stderr().is_terminal(),
+        }
+    }
+}
+
+/// How the table is rendered on the terminal
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Style {
+    /// Max width of the table, the name and path columns are elided to fit it
+    pub(crate) width: Option<usize>,
+    /// Colour the metrics using ANSI escape codes
+    pub(crate) color: bool,
+}
+
+impl Style {
+    // Use the terminal width and colours only when the console is a terminal
+    // Colours are disabled also when NO_COLOR is set
+    pub(crate) fn detect(console: Console) -> Self {
+        if !console.is_terminal() {
+            return Self::default();
+        }
+        let width = terminal_size::terminal_size().map(|(w, _)| w.0 as usize);
+        let color = std::env::var_os("NO_COLOR").unwrap_or_default().is_empty();
+        Self { width, color }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+}
+
+// Cell of the table with the colour chosen for its value
+pub(crate) struct Cell {
+    pub(crate) text: String,
+    pub(crate) color: Option<&'static str>,
+}
+
+/// Table of metrics printed on the terminal
+/// The first column is the name and the last one is the path, both can be elided
+pub(crate) struct Table {
+    rows: Vec<Vec<Cell>>,
+    thresholds: Vec<f64>,
+    // Leading columns with text, aligned to the left like the last one
+    text_columns: usize,
+}
+
+const HEADER: [&str; 10] = [
+    "WCC PLAIN",
+    "WCC QUANTIZED",
+    "CRAP",
+    "SKUNKSCORE",
+    "COVERAGE",
+    "COMPLEXITY",
+    "PLOC",
+    "COVERED",
+    "INSTRUMENTED",
+    "IS_COMPLEX",
+];
+
+// Colour of a value given its threshold, red when it is exceeded
+fn threshold_color(value: f64, threshold: Option<&f64>) -> Option<&'static str> {
+    let threshold = *threshold?;
+    if value > threshold {
+        Some(RED)
+    } else if value > threshold * WARNING_RATIO {
+        Some(YELLOW)
+    } else {
+        Some(GREEN)
+    }
+}
+
+// Elide the middle of a string keeping its start and end
+pub(crate) fn elide_middle(s: &str, width: usize) -> String {
+    let chars = s.chars().collect::<Vec<char>>();
+    if chars.len() <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let tail = (width - 1) / 2;
+    let head = width - 1 - tail;
+    chars[..head]
+        .iter()
+        .chain(['…'].iter())
+        .chain(chars[chars.len() - tail..].iter())
+        .collect()
+}
+
+impl Table {
+    pub(crate) fn new(name: &str, thresholds: &[f64]) -> Self {
+        let header = std::iter::once(name)
+            .chain(HEADER)
+            .chain(std::iter::once("PATH"))
+            .collect::<Vec<&str>>();
+        let mut table = Self::with_header(&header);
+        table.thresholds = thresholds.to_vec();
+        table
+    }
+
+    // Table with other columns, the first and the last ones are still the ones elided
+    pub(crate) fn with_header(header: &[&str]) -> Self {
+        let header = header
+            .iter()
+            .map(|h| Cell {
+                text: h.to_string(),
+                color: None,
+            })
+            .collect();
+        Self {
+            rows: vec![header],
+            thresholds: vec![],
+            text_columns: 1,
+        }
+    }
+
+    pub(crate) fn text_columns(mut self, text_columns: usize) -> Self {
+        self.text_columns = text_columns;
+        self
+    }
+
+    pub(crate) fn push_cells(&mut self, row: Vec<Cell>) {
+        self.rows.push(row);
+    }
+
+    // The suppressed functions are shown as suppressed instead of not complex
+    pub(crate) fn push(&mut self, name: &str, metrics: &Metrics, path: &str, suppressed: bool) {
+        let plain = |text: String| Cell { text, color: None };
+        let scores = [
+            metrics.sifis_plain,
+            metrics.sifis_quantized,
+            metrics.crap,
+            metrics.skunk,
+        ];
+        let mut row = vec![plain(name.into())];
+        row.extend(scores.iter().enumerate().map(|(i, value)| Cell {
+            text: format!("{:.3}", value),
+            color: threshold_color(*value, self.thresholds.get(i)),
+        }));
+        row.extend([
+            plain(format!("{:.2}", metrics.coverage)),
+            plain(format!("{:.2}", metrics.complexity)),
+            plain(format!("{:.2}", metrics.ploc)),
+            plain(format!("{:.2}", metrics.covered_lines)),
+            plain(format!("{:.2}", metrics.instrumented_lines)),
+            match (metrics.is_complex, suppressed) {
+                (false, true) => Cell {
+                    text: "suppressed".into(),
+                    color: Some(YELLOW),
+                },
+                (is_complex, _) => Cell {
+                    text: is_complex.to_string(),
+                    color: is_complex.then_some(RED),
+                },
+            },
+            plain(path.into()),
+        ]);
+        self.rows.push(row);
+    }
+
+    // Natural width of each column, then shrink the path and the name to fit the width
+    fn widths(&self, width: Option<usize>) -> Vec<usize> {
+        let columns = self.rows[0].len();
+        let mut widths = (0..columns)
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|r| r[i].text.chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<usize>>();
+        if let Some(width) = width {
+            let total = widths.iter().sum::<usize>() + SEPARATOR.len() * (columns - 1);
+            let mut exceeding = total.saturating_sub(width);
+            [columns - 1, 0].iter().for_each(|&i| {
+                let shrink = exceeding.min(widths[i].saturating_sub(MIN_WIDTH));
+                widths[i] -= shrink;
+                exceeding -= shrink;
+            });
+        }
+        widths
+    }
+
+    pub(crate) fn render(&self, style: Style) -> String {
+        let widths = self.widths(style.width);
+        let last = widths.len() - 1;
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        let align = if r > 0 && i >= self.text_columns && i < last {
+                            Align::Right
+                        } else {
+                            Align::Left
+                        };
+                        let text = elide_middle(&cell.text, widths[i]);
+                        // The last column is not padded to avoid trailing spaces
+                        let text = match align {
+                            Align::Left if i == last => text,
+                            Align::Left => format!("{:<w$}", text, w = widths[i]),
+                            Align::Right => format!("{:>w$}", text, w = widths[i]),
+                        };
+                        match cell.color {
+                            Some(color) if style.color => format!("{}{}{}", color, text, RESET),
+                            _ => text,
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(SEPARATOR)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elide_middle() {
+        assert_eq!(elide_middle("src/flag.rs", 20), "src/flag.rs");
+        assert_eq!(elide_middle("src/metrics/crap.rs", 9), "src/…p.rs");
+        assert_eq!(elide_middle("abc", 1), "…");
+    }
+
+    #[test]
+    fn test_render() {
+        let mut table = Table::new("FILE", &[30., 1.5, 35., 30.]);
+        table.push(
+            "a_very_long_file_name_for_the_table.rs",
+            &Metrics::default().sifis_plain(25.).crap(40.),
+            "src/some/deeply/nested/folder/a_very_long_file_name_for_the_table.rs",
+            false,
+        );
+        table.push("parse", &Metrics::default(), "src/parser.rs", true);
+
+        let plain = table.render(Style::default());
+        assert!(!plain.contains('\x1b'));
+        assert!(
+            plain.contains("src/some/deeply/nested/folder/a_very_long_file_name_for_the_table.rs")
+        );
+
+        let styled = table.render(Style {
+            width: Some(140),
+            color: true,
+        });
+        assert!(styled
+            .lines()
+            .all(|l| !l.contains("src/some/deeply/nested")));
+        assert!(styled.contains('…'));
+        assert!(styled.contains(&format!("{}{:>10}{}", YELLOW, "suppressed", RESET)));
+        assert!(styled.contains(&format!("{}{:>9}{}", YELLOW, "25.000", RESET)));
+        assert!(styled.contains(&format!("{}{:>6}{}", RED, "40.000", RESET)));
+        assert!(styled.contains(&format!("{}{:>13}{}", GREEN, "0.000", RESET)));
+        let header = styled.lines().next().unwrap();
+        assert!(header.chars().count() <= 140);
+    }
+}
@@ -0,0 +1,451 @@
+use std::path::*;
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::compress::Compression;
+use crate::error::*;
+use crate::files::{FileMetrics, Metrics};
+use crate::functions::{FunctionMetrics, RootMetrics};
+use crate::output::{create_output, finish_csv};
+use crate::utility::*;
+
+// Row of the tidy csv, one for each file, function or aggregate
+#[derive(Serialize)]
+struct TidyRow<'a> {
+    kind: &'a str,
+    path: &'a str,
+    name: &'a str,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    coverage: f64,
+    sifis_plain: f64,
+    sifis_quantized: f64,
+    crap: f64,
+    skunk: f64,
+    complexity: f64,
+    ploc: f64,
+    covered_lines: f64,
+    instrumented_lines: f64,
+    is_complex: bool,
+    exceeded_thresholds: String,
+    qualified_name: &'a str,
+    kind_chain: String,
+    suppressed: bool,
+    suppression: String,
+    covered_by: String,
+}
+
+impl<'a> TidyRow<'a> {
+    fn new(
+        kind: &'a str,
+        path: &'a str,
+        name: &'a str,
+        lines: Option<(usize, usize)>,
+        metrics: &Metrics,
+        thresholds: &[f64],
+    ) -> Self {
+        // Aggregates are never complex, so the thresholds are not checked
+        let exceeded_thresholds = match kind {
+            "aggregate" => String::new(),
+            _ => get_exceeded_thresholds(metrics, thresholds).join(";"),
+        };
+        Self {
+            kind,
+            path,
+            name,
+            start_line: lines.map(|(start, _)| start),
+            end_line: lines.map(|(_, end)| end),
+            coverage: metrics.coverage,
+            sifis_plain: metrics.sifis_plain,
+            sifis_quantized: metrics.sifis_quantized,
+            crap: metrics.crap,
+            skunk: metrics.skunk,
+            complexity: metrics.complexity,
+            ploc: metrics.ploc,
+            covered_lines: metrics.covered_lines,
+            instrumented_lines: metrics.instrumented_lines,
+            is_complex: metrics.is_complex,
+            exceeded_thresholds,
+            qualified_name: "",
+            kind_chain: String::new(),
+            suppressed: false,
+            suppression: String::new(),
+            covered_by: String::new(),
+        }
+    }
+
+    // Add the qualified name, the kind chain, the suppression and the covering reports of a function
+    // The suppressed thresholds are not listed as exceeded
+    fn function(mut self, function: &'a FunctionMetrics, thresholds: &[f64]) -> Self {
+        self.qualified_name = &function.qualified_name;
+        self.kind_chain = function.kind_chain.join(";");
+        self.covered_by = function.covered_by.join(";");
+        if let Some(suppression) = &function.suppression {
+            self.suppressed = true;
+            self.suppression = suppression.describe();
+            self.exceeded_thresholds = suppression
+                .exceeded_thresholds(&function.metrics, thresholds)
+                .join(";");
+        }
+        self
+    }
+}
+
+// Row of the csv with the files not analyzed
+#[derive(Serialize)]
+struct SkippedRow<'a> {
+    path: &'a str,
+    status: &'a str,
+    /// Error of the failed files and mismatch of the skipped ones, empty for the others
+    error: &'a str,
+}
+
+// Row of the csv with the summary of the analysis
+#[derive(Serialize)]
+struct SummaryRow<'a> {
+    name: &'a str,
+    value: String,
+}
+
+// Path of a csv written next to the main one, e.g. out.csv -> out_ignored.csv
+fn sibling_path(csv_path: &Path, suffix: &str) -> PathBuf {
+    // The side files of a compressed csv are compressed in the same way
+    let (csv_path, compression) = match Compression::from_extension(csv_path) {
+        Some(_) => (csv_path.with_extension(""), csv_path.extension()),
+        None => (csv_path.to_path_buf(), None),
+    };
+    let stem = csv_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match compression {
+        Some(ext) => format!("{}_{}.csv.{}", stem, suffix, ext.to_string_lossy()),
+        None => format!("{}_{}.csv", stem, suffix),
+    };
+    csv_path.with_file_name(name)
+}
+
+fn kind(file_path: &str) -> &'static str {
+    if file_path == "-" {
+        "aggregate"
+    } else {
+        "file"
+    }
+}
+
+// Write the csv of the files not analyzed and the csv with the summary
+fn write_side_files(
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    complex: usize,
+    csv_path: &Path,
+) -> Result<()> {
+    // The side files have no name when the rows are written to the standard output
+    if is_stdio(csv_path) {
+        warn!(
+            "The summary and the {} rows of the ignored files are not written with the tidy csv on stdout",
+            files_ignored.len()
+                + diagnostics.files_missing.len()
+                + diagnostics.files_failed.len()
+                + diagnostics.mismatches.len()
+        );
+        return Ok(());
+    }
+    // The header is written also when there are no rows
+    let ignored_path = sibling_path(csv_path, "ignored");
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(create_output(&ignored_path)?);
+    writer.write_record(["path", "status", "error"])?;
+    files_ignored
+        .iter()
+        .map(|path| SkippedRow {
+            path,
+            status: "ignored",
+            error: "",
+        })
+        .chain(diagnostics.files_missing.iter().map(|path| SkippedRow {
+            path,
+            status: "missing",
+            error: "",
+        }))
+        .chain(diagnostics.files_failed.iter().map(|f| SkippedRow {
+            path: &f.file,
+            status: "failed",
+            error: &f.error,
+        }))
+        .chain(
+            diagnostics
+                .mismatches
+                .iter()
+                .filter(|m| m.skipped)
+                .map(|m| SkippedRow {
+                    path: &m.file,
+                    status: "skipped",
+                    error: &m.detail,
+                }),
+        )
+        .try_for_each(|row| writer.serialize(row))?;
+    finish_csv(writer, &ignored_path)?;
+    let summary_path = sibling_path(csv_path, "summary");
+    let mut writer = csv::Writer::from_writer(create_output(&summary_path)?);
+    [
+        ("project_coverage", project_coverage.to_string()),
+        ("complex", complex.to_string()),
+        ("files_ignored", files_ignored.len().to_string()),
+        ("files_missing", diagnostics.files_missing.len().to_string()),
+        ("files_failed", diagnostics.files_failed.len().to_string()),
+        ("mismatches", diagnostics.mismatches.len().to_string()),
+    ]
+    .into_iter()
+    .try_for_each(|(name, value)| writer.serialize(SummaryRow { name, value }))?;
+    finish_csv(writer, &summary_path)?;
+    Ok(())
+}
+
+/// Print the files metrics to a tidy csv, with one row for each file or aggregate
+/// The ignored, missing, failed and skipped files are written in <name>_ignored.csv
+/// The project coverage and the totals are written in <name>_summary.csv
+pub fn print_metrics_to_tidy_csv<A: AsRef<Path>>(
+    metrics: &[FileMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    thresholds: &[f64],
+    csv_path: A,
+) -> Result<()> {
+    let csv_path = csv_path.as_ref();
+    let mut writer = csv::Writer::from_writer(create_output(csv_path)?);
+    metrics.iter().try_for_each(|m| {
+        writer.serialize(TidyRow::new(
+            kind(&m.file_path),
+            &m.file_path,
+            &m.file,
+            None,
+            &m.metrics,
+            thresholds,
+        ))
+    })?;
+    finish_csv(writer, csv_path)?;
+    let complex = metrics.iter().filter(|m| m.metrics.is_complex).count();
+    write_side_files(
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        complex,
+        csv_path,
+    )
+}
+
+/// Print the functions metrics to a tidy csv, with one row for each file, function or aggregate
+/// The ignored, missing, failed and skipped files are written in <name>_ignored.csv
+/// The project coverage and the totals are written in <name>_summary.csv
+pub fn print_metrics_to_tidy_csv_function<A: AsRef<Path>>(
+    metrics: &[RootMetrics],
+    files_ignored: &[String],
+    diagnostics: &Diagnostics,
+    project_coverage: f64,
+    thresholds: &[f64],
+    csv_path: A,
+) -> Result<()> {
+    let csv_path = csv_path.as_ref();
+    let mut writer = csv::Writer::from_writer(create_output(csv_path)?);
+    metrics.iter().try_for_each(|m| -> Result<()> {
+        let kind = kind(&m.file_path);
+        let lines = (kind == "file").then_some((m.start_line, m.end_line));
+        writer.serialize(TidyRow::new(
+            kind,
+            &m.file_path,
+            &m.file_name,
+            lines,
+            &m.metrics,
+            thresholds,
+        ))?;
+        m.functions.iter().try_for_each(|f| {
+            writer.serialize(
+                TidyRow::new(
+                    "function",
+                    &m.file_path,
+                    &f.function_name,
+                    Some((f.start_line, f.end_line)),
+                    &f.metrics,
+                    thresholds,
+                )
+                .function(f, thresholds),
+            )
+        })?;
+        Ok(())
+    })?;
+    finish_csv(writer, csv_path)?;
+    let complex = metrics
+        .iter()
+        .flat_map(|m| m.functions.iter())
+        .filter(|f| f.metrics.is_complex)
+        .count();
+    write_side_files(
+        files_ignored,
+        diagnostics,
+        project_coverage,
+        complex,
+        csv_path,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::functions::*;
+
+    const JSON: &str = "./data/seahorse/seahorse.json";
+    const FOLDER: &str = "./data/test_project/";
+
+    #[test]
+    fn test_sibling_path() {
+        assert_eq!(
+            sibling_path(Path::new("out/metrics.csv"), "summary"),
+            Path::new("out/metrics_summary.csv")
+        );
+        assert_eq!(
+            sibling_path(Path::new("out/metrics.csv.gz"), "ignored"),
+            Path::new("out/metrics_ignored.csv.gz")
+        );
+    }
+
+    #[test]
+    fn test_side_files_stdout() {
+        let csv_path = Path::new("-");
+        write_side_files(&[], &Diagnostics::default(), 100., 0, csv_path).unwrap();
+        assert!(!sibling_path(csv_path, "summary").exists());
+        assert!(!sibling_path(csv_path, "ignored").exists());
+    }
+
+    #[test]
+    fn test_functions_tidy_csv() {
+        let thresholds = [5., 1.5, 5., 5.];
+        let (metrics, files_ignored, complex_functions, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                FOLDER,
+                &[CoverageReport::new(JSON).label(Some("unit".into()))],
+                Complexity::Cyclomatic,
+                8,
+                &thresholds,
+                &Options::default(),
+            )
+            .unwrap();
+        let csv_path = Path::new("./data/test_project/to_compare_tidy.csv");
+        print_metrics_to_tidy_csv_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            &thresholds,
+            csv_path,
+        )
+        .unwrap();
+        let mut reader = csv::Reader::from_path(csv_path).unwrap();
+        let header = reader.headers().unwrap().clone();
+        let rows = reader
+            .records()
+            .collect::<std::result::Result<Vec<csv::StringRecord>, csv::Error>>()
+            .unwrap();
+        let summary = fs::read_to_string(sibling_path(csv_path, "summary")).unwrap();
+        let ignored = fs::read_to_string(sibling_path(csv_path, "ignored")).unwrap();
+        fs::remove_file(csv_path).unwrap();
+        fs::remove_file(sibling_path(csv_path, "summary")).unwrap();
+        fs::remove_file(sibling_path(csv_path, "ignored")).unwrap();
+
+        assert_eq!(
+            header.iter().collect::<Vec<&str>>(),
+            vec![
+                "kind",
+                "path",
+                "name",
+                "start_line",
+                "end_line",
+                "coverage",
+                "sifis_plain",
+                "sifis_quantized",
+                "crap",
+                "skunk",
+                "complexity",
+                "ploc",
+                "covered_lines",
+                "instrumented_lines",
+                "is_complex",
+                "exceeded_thresholds",
+                "qualified_name",
+                "kind_chain",
+                "suppressed",
+                "suppression",
+                "covered_by"
+            ]
+        );
+        assert!(rows.iter().all(|r| r.len() == header.len()));
+        assert!(rows.iter().any(|r| &r[0] == "function" && &r[20] == "unit"));
+        assert!(rows.iter().all(|r| &r[0] == "function" || r[20].is_empty()));
+        let functions = rows.iter().filter(|r| &r[0] == "function").count();
+        assert_eq!(functions, metrics[0].functions.len());
+        assert_eq!(rows.iter().filter(|r| &r[0] == "aggregate").count(), 4);
+        assert_eq!(rows.iter().filter(|r| &r[0] == "file").count(), 1);
+        let complex = rows
+            .iter()
+            .filter(|r| &r[0] == "function" && !r[15].is_empty())
+            .count();
+        assert_eq!(complex, complex_functions.len());
+        assert!(summary.contains(&format!("complex,{}", complex_functions.len())));
+        assert_eq!(ignored, "path,status,error\n");
+        assert!(rows
+            .iter()
+            .any(|r| &r[16] == "Flag::new" && &r[17] == "impl;function"));
+    }
+
+    #[test]
+    fn test_tidy_csv_suppression() {
+        let thresholds = [5., 1.5, 5., 5.];
+        let (metrics, files_ignored, _, project_coverage, diagnostics) =
+            get_functions_metrics_concurrent(
+                "./data/suppression_project/",
+                &[CoverageReport::new(
+                    "./data/suppression_project/coveralls.json",
+                )],
+                Complexity::Cyclomatic,
+                8,
+                &thresholds,
+                &Options::default(),
+            )
+            .unwrap();
+        let csv_path = Path::new("./data/suppression_project/to_compare_tidy.csv");
+        print_metrics_to_tidy_csv_function(
+            &metrics,
+            &files_ignored,
+            &diagnostics,
+            project_coverage,
+            &thresholds,
+            csv_path,
+        )
+        .unwrap();
+        let mut reader = csv::Reader::from_path(csv_path).unwrap();
+        let rows = reader
+            .records()
+            .collect::<std::result::Result<Vec<csv::StringRecord>, csv::Error>>()
+            .unwrap();
+        fs::remove_file(csv_path).unwrap();
+        fs::remove_file(sibling_path(csv_path, "summary")).unwrap();
+        fs::remove_file(sibling_path(csv_path, "ignored")).unwrap();
+
+        let exceeded = |name: &str| {
+            rows.iter()
+                .find(|r| &r[0] == "function" && &r[16] == name)
+                .map(|r| r[15].to_string())
+                .unwrap()
+        };
+        // The suppressed thresholds are not exceeded
+        assert_eq!(exceeded("parse"), "");
+        assert_eq!(exceeded("run"), "");
+        assert!(!exceeded("check").is_empty());
+    }
+}
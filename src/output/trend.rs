@@ -0,0 +1,283 @@
+use std::collections::BTreeSet;
+use std::io::Write as _;
+
+use tracing::warn;
+
+use super::terminal::{Cell, Style, Table, GREEN, RED};
+use super::Console;
+use crate::error::*;
+use crate::files::Metrics;
+use crate::history::Run;
+use crate::utility::{normalize_path, TrendMetric};
+
+/// Default number of the last runs shown in the trends
+pub const TREND_LAST: usize = 20;
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Metrics shown when none is chosen
+const DEFAULT_METRICS: [TrendMetric; 5] = [
+    TrendMetric::SifisPlain,
+    TrendMetric::SifisQuantized,
+    TrendMetric::Crap,
+    TrendMetric::Skunk,
+    TrendMetric::Coverage,
+];
+
+const HEADER: [&str; 8] = [
+    "ENTRY",
+    "METRIC",
+    "FIRST",
+    "PREVIOUS",
+    "LAST",
+    "DELTA",
+    "TOTAL DELTA",
+    "TREND",
+];
+
+fn label(metric: TrendMetric) -> &'static str {
+    match metric {
+        TrendMetric::SifisPlain => "WCC PLAIN",
+        TrendMetric::SifisQuantized => "WCC QUANTIZED",
+        TrendMetric::Crap => "CRAP",
+        TrendMetric::Skunk => "SKUNKSCORE",
+        TrendMetric::Coverage => "COVERAGE",
+        TrendMetric::Complexity => "COMPLEXITY",
+    }
+}
+
+fn value(metric: TrendMetric, metrics: &Metrics) -> f64 {
+    match metric {
+        TrendMetric::SifisPlain => metrics.sifis_plain,
+        TrendMetric::SifisQuantized => metrics.sifis_quantized,
+        TrendMetric::Crap => metrics.crap,
+        TrendMetric::Skunk => metrics.skunk,
+        TrendMetric::Coverage => metrics.coverage,
+        TrendMetric::Complexity => metrics.complexity,
+    }
+}
+
+// Digits of the values, the same used by the table of the metrics
+fn precision(metric: TrendMetric) -> usize {
+    match metric {
+        TrendMetric::Coverage | TrendMetric::Complexity => 2,
+        _ => 3,
+    }
+}
+
+// Draw the values with bars scaled between their min and max, the missing values are blank
+fn sparkline(values: &[Option<f64>]) -> String {
+    let (min, max) = values
+        .iter()
+        .flatten()
+        .fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+    values
+        .iter()
+        .map(|value| match value {
+            None => ' ',
+            Some(_) if max <= min => BARS[0],
+            Some(v) => BARS[((v - min) / (max - min) * (BARS.len() - 1) as f64).round() as usize],
+        })
+        .collect()
+}
+
+// Files of the runs matching the chosen paths, a path matches also the end of the path of a file
+fn select_files(runs: &[Run], paths: &[String]) -> Result<Vec<String>> {
+    let keys = runs
+        .iter()
+        .flat_map(|run| run.files.keys())
+        .collect::<BTreeSet<&String>>();
+    let mut files = Vec::<String>::new();
+    paths.iter().try_for_each(|path| {
+        let path = normalize_path(path, false);
+        let suffix = format!("/{}", path);
+        let matches = keys
+            .iter()
+            .filter(|key| ***key == path || key.ends_with(&suffix))
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return Err(Error::history(format!("no run has the file {}", path)));
+        }
+        matches.into_iter().for_each(|key| {
+            if !files.contains(key) {
+                files.push(key.to_string());
+            }
+        });
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+fn text_cell(text: &str) -> Cell {
+    Cell {
+        text: text.into(),
+        color: None,
+    }
+}
+
+fn value_cell(value: Option<f64>, precision: usize) -> Cell {
+    Cell {
+        text: value.map_or_else(|| "-".into(), |v| format!("{:.p$}", v, p = precision)),
+        color: None,
+    }
+}
+
+// Green when the metric improves, red when it gets worse
+fn delta_cell(metric: TrendMetric, from: Option<f64>, to: Option<f64>) -> Cell {
+    match (from, to) {
+        (Some(from), Some(to)) => {
+            let delta = to - from;
+            let text = format!("{:+.p$}", delta, p = precision(metric));
+            let color = if text.trim_start_matches(['+', '-', '0', '.']).is_empty() {
+                None
+            } else if (delta > 0.) == (metric == TrendMetric::Coverage) {
+                Some(GREEN)
+            } else {
+                Some(RED)
+            };
+            Cell { text, color }
+        }
+        _ => text_cell("-"),
+    }
+}
+
+// Build the table with the trends of the project and of the chosen files
+fn trend_table(runs: &[Run], files: &[String], metrics: &[TrendMetric]) -> Table {
+    let mut table = Table::with_header(&HEADER).text_columns(2);
+    let entries = std::iter::once(("PROJECT", None)).chain(
+        files
+            .iter()
+            .map(|file| (file.as_str(), Some(file.as_str()))),
+    );
+    entries.for_each(|(name, file)| {
+        metrics.iter().for_each(|metric| {
+            let values = runs
+                .iter()
+                .map(|run| match file {
+                    None => Some(&run.project),
+                    Some(file) => run.files.get(file),
+                })
+                .map(|m| m.map(|m| value(*metric, m)))
+                .collect::<Vec<Option<f64>>>();
+            let first = values.iter().flatten().next().copied();
+            let previous = values.len().checked_sub(2).and_then(|i| values[i]);
+            let last = values.last().copied().flatten();
+            table.push_cells(vec![
+                text_cell(name),
+                text_cell(label(*metric)),
+                value_cell(first, precision(*metric)),
+                value_cell(previous, precision(*metric)),
+                value_cell(last, precision(*metric)),
+                delta_cell(*metric, previous, last),
+                delta_cell(*metric, first, last),
+                text_cell(&sparkline(&values)),
+            ]);
+        });
+    });
+    table
+}
+
+/// Print the trends of the metrics over the last runs of the history
+/// The project is always shown, the files are chosen with their paths
+/// The sparklines go from the oldest run to the newest one
+pub fn print_trend(
+    runs: &[Run],
+    files: &[String],
+    metrics: &[TrendMetric],
+    last: usize,
+    console: Console,
+) -> Result<()> {
+    let runs = &runs[runs.len().saturating_sub(last.max(1))..];
+    let (first, newest) = match runs {
+        [first, .., newest] => (first, newest),
+        [run] => (run, run),
+        [] => return Err(Error::history("no run to show")),
+    };
+    if runs.iter().any(|run| run.mode != first.mode) {
+        warn!("The runs were saved in different modes, their metrics may not be comparable");
+    }
+    let files = select_files(runs, files)?;
+    let metrics = if metrics.is_empty() {
+        &DEFAULT_METRICS[..]
+    } else {
+        metrics
+    };
+    let mut out = console.writer();
+    writeln!(
+        out,
+        "Trend of {} runs, from {} to {}",
+        runs.len(),
+        first.revision,
+        newest.revision
+    )?;
+    writeln!(
+        out,
+        "{}",
+        trend_table(runs, &files, metrics).render(Style::detect(console))
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Run;
+
+    fn run(revision: &str, coverage: f64, file: Option<f64>) -> Run {
+        Run {
+            format: 1,
+            revision: revision.into(),
+            timestamp: 0,
+            mode: "files".into(),
+            project: Metrics::default().coverage(coverage).crap(100. - coverage),
+            files: file
+                .map(|c| ("src/app.rs".to_string(), Metrics::default().coverage(c)))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[Some(0.), Some(7.), Some(3.5)]), "▁█▅");
+        assert_eq!(sparkline(&[Some(2.), None, Some(2.)]), "▁ ▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_trend_table() {
+        let runs = [
+            run("a", 50., None),
+            run("b", 60., Some(40.)),
+            run("c", 55., Some(45.)),
+        ];
+        let files = select_files(&runs, &["app.rs".into()]).unwrap();
+        assert_eq!(files, vec!["src/app.rs"]);
+        assert!(select_files(&runs, &["src/main.rs".into()])
+            .unwrap_err()
+            .to_string()
+            .contains("no run has the file src/main.rs"));
+
+        let table = trend_table(&runs, &files, &[TrendMetric::Coverage, TrendMetric::Crap]);
+        let styled = table.render(Style {
+            width: None,
+            color: true,
+        });
+        let lines = styled.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("PROJECT    | COVERAGE |  50.00 |    60.00 |  55.00 |"));
+        assert!(lines[1].contains(&format!("{} -5.00", RED)));
+        assert!(lines[1].contains(&format!("{}      +5.00", GREEN)));
+        assert!(lines[1].ends_with("▁█▅"));
+        // Crap is better when it goes down
+        assert!(lines[2].contains(&format!("{}+5.000", RED)));
+        assert!(lines[2].contains(&format!("{}     -5.000", GREEN)));
+        // The file is missing from the first run
+        assert!(lines[3].contains("|  40.00 |    40.00 |  45.00 |"));
+        assert!(lines[3].ends_with(" ▁█"));
+        // No colour when the metric does not change
+        assert!(lines[4].contains("| +0.000 |      +0.000 |"));
+    }
+}
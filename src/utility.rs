@@ -2,16 +2,25 @@ use core::cmp::Ordering;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
+use std::io;
 use std::path::*;
+use std::sync::{Arc, OnceLock};
 
 use arg_enum_proc_macro::ArgEnum;
-use rust_code_analysis::{get_function_spaces, guess_language, read_file, FuncSpace, SpaceKind};
+use rust_code_analysis::SpaceKind;
+use serde::{Deserialize, Serialize};
 use serde_json::Map;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, info};
 
+use crate::complexity::*;
+use crate::compress::read_text;
 use crate::error::*;
+use crate::exclude::*;
 use crate::files::*;
+use crate::formats::{
+    detect_format, read_coveragepy, read_coverprofile, read_istanbul, read_jacoco,
+};
 use crate::metrics::crap::*;
 use crate::metrics::sifis::*;
 use crate::metrics::skunk::*;
@@ -44,12 +53,35 @@ pub enum JsonFormat {
     /// Cognitive metric.
     #[arg_enum(name = "coveralls")]
     Coveralls,
+    /// Go coverage profile written by go test -coverprofile.
+    #[arg_enum(name = "coverprofile")]
+    Coverprofile,
+    /// XML report written by JaCoCo.
+    #[arg_enum(name = "jacoco")]
+    Jacoco,
+    /// coverage-final.json written by Istanbul or NYC.
+    #[arg_enum(name = "istanbul")]
+    Istanbul,
+    /// coverage.json written by coverage.py.
+    #[arg_enum(name = "coveragepy")]
+    Coveragepy,
 }
 impl JsonFormat {
     /// Default output format.
     pub const fn default() -> &'static str {
         "coveralls"
     }
+    // Name of the format as written on the command line
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            JsonFormat::Covdir => "covdir",
+            JsonFormat::Coveralls => "coveralls",
+            JsonFormat::Coverprofile => "coverprofile",
+            JsonFormat::Jacoco => "jacoco",
+            JsonFormat::Istanbul => "istanbul",
+            JsonFormat::Coveragepy => "coveragepy",
+        }
+    }
 }
 
 /// Mode
@@ -69,9 +101,464 @@ impl Mode {
     }
 }
 
+/// CSV output format
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CsvFormat {
+    /// Metrics rows mixed with lists and totals.
+    #[arg_enum(name = "legacy")]
+    Legacy,
+    /// One row for each file, function or aggregate.
+    #[arg_enum(name = "tidy")]
+    Tidy,
+}
+impl CsvFormat {
+    /// Default csv format.
+    pub const fn default() -> &'static str {
+        "legacy"
+    }
+}
+
+/// Key used to sort the entries of the reports
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    /// Name of the file or function, ascending.
+    #[arg_enum(name = "name")]
+    Name,
+    /// First line of the function, ascending.
+    #[arg_enum(name = "line")]
+    Line,
+    /// Wcc plain, descending.
+    #[arg_enum(name = "sifis_plain")]
+    SifisPlain,
+    /// Wcc quantized, descending.
+    #[arg_enum(name = "sifis_quantized")]
+    SifisQuantized,
+    /// Crap, descending.
+    #[arg_enum(name = "crap")]
+    Crap,
+    /// Skunk, descending.
+    #[arg_enum(name = "skunk")]
+    Skunk,
+    /// Coverage, ascending.
+    #[arg_enum(name = "coverage")]
+    Coverage,
+    /// Complexity, descending.
+    #[arg_enum(name = "complexity")]
+    Complexity,
+}
+
+/// Metric shown in the trends of the history
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TrendMetric {
+    /// Wcc plain, lower is better.
+    #[arg_enum(name = "sifis_plain")]
+    SifisPlain,
+    /// Wcc quantized, lower is better.
+    #[arg_enum(name = "sifis_quantized")]
+    SifisQuantized,
+    /// Crap, lower is better.
+    #[arg_enum(name = "crap")]
+    Crap,
+    /// Skunk, lower is better.
+    #[arg_enum(name = "skunk")]
+    Skunk,
+    /// Coverage, higher is better.
+    #[arg_enum(name = "coverage")]
+    Coverage,
+    /// Complexity, lower is better.
+    #[arg_enum(name = "complexity")]
+    Complexity,
+}
+
+/// Coverage report given as input
+/// The label is used to tell which reports cover each function
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoverageReport {
+    pub path: PathBuf,
+    pub format: JsonFormat,
+    pub label: Option<String>,
+}
+
+impl CoverageReport {
+    pub fn new<A: AsRef<Path>>(path: A) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            format: JsonFormat::Coveralls,
+            label: None,
+        }
+    }
+    pub fn format(mut self, format: JsonFormat) -> Self {
+        self.format = format;
+        self
+    }
+    pub fn label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+    /// Set the format detected from the content of the report
+    pub fn detect_format(mut self) -> Result<Self> {
+        let file = read_report(&self.path)?;
+        self.format = detect_format(&file).map_err(|e| e.with_file(&self.path))?;
+        Ok(self)
+    }
+}
+
+// Arrays of covered lines of a file, merged from all the reports
+// The arrays of the labelled reports are kept to attribute the coverage
+#[derive(Clone, Default, Debug)]
+pub(crate) struct LinesCoverage {
+    pub(crate) arr: Vec<Value>,
+    pub(crate) suites: Vec<(String, Vec<Value>)>,
+}
+
+/// How the files to analyze are found
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileDiscovery {
+    /// Walk the project folder on disk.
+    #[arg_enum(name = "walk")]
+    Walk,
+    /// Take the files listed in the coverage report.
+    #[arg_enum(name = "report")]
+    Report,
+}
+impl FileDiscovery {
+    /// Default discovery mode.
+    pub const fn default() -> &'static str {
+        "walk"
+    }
+}
+
+/// Kinds of spaces reported in functions mode
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Space {
+    /// Functions, methods, closures and lambdas.
+    #[arg_enum(name = "function")]
+    Function,
+    /// Class bodies.
+    #[arg_enum(name = "class")]
+    Class,
+    /// Struct bodies.
+    #[arg_enum(name = "struct")]
+    Struct,
+    /// Trait bodies.
+    #[arg_enum(name = "trait")]
+    Trait,
+    /// Impl blocks.
+    #[arg_enum(name = "impl")]
+    Impl,
+    /// Namespaces and modules.
+    #[arg_enum(name = "namespace")]
+    Namespace,
+}
+impl Space {
+    /// Default space kind.
+    pub const fn default() -> &'static str {
+        "function"
+    }
+
+    pub(crate) fn kind(&self) -> SpaceKind {
+        match self {
+            Space::Function => SpaceKind::Function,
+            Space::Class => SpaceKind::Class,
+            Space::Struct => SpaceKind::Struct,
+            Space::Trait => SpaceKind::Trait,
+            Space::Impl => SpaceKind::Impl,
+            Space::Namespace => SpaceKind::Namespace,
+        }
+    }
+}
+
+/// What to do when the coverage of a file does not match its source
+#[derive(ArgEnum, Copy, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Do not run the check.
+    #[arg_enum(name = "ignore")]
+    Ignore,
+    /// Analyze the file and list the mismatch.
+    #[arg_enum(name = "warn")]
+    Warn,
+    /// List the mismatch and leave the file out of the metrics.
+    #[arg_enum(name = "skip")]
+    Skip,
+    /// Stop the analysis with an error.
+    #[arg_enum(name = "fail")]
+    Fail,
+}
+impl Policy {
+    /// Default policy.
+    pub const fn default() -> &'static str {
+        "warn"
+    }
+}
+
+/// Policies of the checks of the coverage arrays against the source files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Validation {
+    /// The coverage array is longer than the file
+    pub length: Policy,
+    /// Blank or comment lines are instrumented
+    pub blank_hits: Policy,
+    /// Functions start after the end of the coverage array
+    pub ranges: Policy,
+    /// Functions of the complexity providers end after the file or have code but no instrumented lines
+    pub complexity: Policy,
+}
+
+impl Default for Validation {
+    // Region based coverage, like the llvm one, gives hits also to the blank lines inside a region
+    // so that check is off by default
+    fn default() -> Self {
+        Self {
+            length: Policy::Warn,
+            blank_hits: Policy::Ignore,
+            ranges: Policy::Warn,
+            complexity: Policy::Warn,
+        }
+    }
+}
+
+impl Validation {
+    pub fn length(mut self, length: Policy) -> Self {
+        self.length = length;
+        self
+    }
+    pub fn blank_hits(mut self, blank_hits: Policy) -> Self {
+        self.blank_hits = blank_hits;
+        self
+    }
+    pub fn ranges(mut self, ranges: Policy) -> Self {
+        self.ranges = ranges;
+        self
+    }
+    pub fn complexity(mut self, complexity: Policy) -> Self {
+        self.complexity = complexity;
+        self
+    }
+}
+
+/// Options used to tune the analysis
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub discovery: FileDiscovery,
+    pub source_roots: Vec<PathBuf>,
+    pub strip_prefixes: Vec<String>,
+    pub replace_prefixes: Vec<(String, String)>,
+    pub ignore_case: bool,
+    /// Kinds of the spaces reported in functions mode
+    pub space_kinds: Vec<Space>,
+    /// Report only the outermost spaces, the nested ones are rolled up into them
+    pub rollup: bool,
+    /// Drop the test files and the test code inside the files
+    pub exclude_tests: bool,
+    /// Collect the files whose analysis fails instead of stopping at the first error
+    pub keep_going: bool,
+    /// Checks of the coverage arrays against the source files
+    pub validation: Validation,
+    /// Sources of complexity tried before rust-code-analysis
+    pub complexity_providers: Vec<Arc<dyn ComplexityProvider>>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            discovery: FileDiscovery::Walk,
+            source_roots: Vec::<PathBuf>::new(),
+            strip_prefixes: Vec::<String>::new(),
+            replace_prefixes: Vec::<(String, String)>::new(),
+            ignore_case: false,
+            space_kinds: vec![Space::Function],
+            rollup: false,
+            exclude_tests: false,
+            keep_going: false,
+            validation: Validation::default(),
+            complexity_providers: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    pub fn discovery(mut self, discovery: FileDiscovery) -> Self {
+        self.discovery = discovery;
+        self
+    }
+    pub fn source_roots(mut self, source_roots: Vec<PathBuf>) -> Self {
+        self.source_roots = source_roots;
+        self
+    }
+    pub fn strip_prefixes(mut self, strip_prefixes: Vec<String>) -> Self {
+        self.strip_prefixes = strip_prefixes;
+        self
+    }
+    pub fn replace_prefixes(mut self, replace_prefixes: Vec<(String, String)>) -> Self {
+        self.replace_prefixes = replace_prefixes;
+        self
+    }
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+    pub fn space_kinds(mut self, space_kinds: Vec<Space>) -> Self {
+        self.space_kinds = space_kinds;
+        self
+    }
+    pub fn rollup(mut self, rollup: bool) -> Self {
+        self.rollup = rollup;
+        self
+    }
+    pub fn exclude_tests(mut self, exclude_tests: bool) -> Self {
+        self.exclude_tests = exclude_tests;
+        self
+    }
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+    pub fn complexity_providers(
+        mut self,
+        complexity_providers: Vec<Arc<dyn ComplexityProvider>>,
+    ) -> Self {
+        self.complexity_providers = complexity_providers;
+        self
+    }
+
+    // Get the kinds of the spaces to report
+    pub(crate) fn kinds(&self) -> Vec<SpaceKind> {
+        self.space_kinds.iter().map(|s| s.kind()).collect()
+    }
+
+    // Get the folders where the files are searched, the project folder if none is given
+    pub(crate) fn roots(&self, files_path: &Path) -> Result<Vec<String>> {
+        if self.source_roots.is_empty() || self.discovery == FileDiscovery::Walk {
+            return Ok(vec![files_path
+                .to_str()
+                .ok_or_else(|| Error::path(files_path))?
+                .replace('\\', "/")]);
+        }
+        self.source_roots
+            .iter()
+            .map(|root| -> Result<String> {
+                Ok(root
+                    .to_str()
+                    .ok_or_else(|| Error::path(root))?
+                    .replace('\\', "/"))
+            })
+            .collect()
+    }
+
+    // Get the prefix to add to the paths found in the coverage report
+    fn coverage_prefix<'a>(&self, files_path: &'a Path) -> Result<&'a str> {
+        match self.discovery {
+            FileDiscovery::Walk => files_path.to_str().ok_or_else(|| Error::path(files_path)),
+            FileDiscovery::Report => Ok(""),
+        }
+    }
+
+    // Apply the remapping rules to a path of the coverage report
+    // The first replace rule matching is applied, then the first strip rule matching
+    fn remap(&self, name: &str) -> String {
+        let mut name = name.replace('\\', "/");
+        let starts_with = |name: &str, prefix: &str| {
+            let prefix = prefix.replace('\\', "/");
+            match name.get(..prefix.len()) {
+                Some(start) if self.ignore_case => start.eq_ignore_ascii_case(&prefix),
+                Some(start) => start == prefix,
+                None => false,
+            }
+        };
+        if let Some((from, to)) = self
+            .replace_prefixes
+            .iter()
+            .find(|(from, _)| starts_with(&name, from))
+        {
+            name = format!("{}{}", to.replace('\\', "/"), &name[from.len()..]);
+        }
+        if let Some(prefix) = self
+            .strip_prefixes
+            .iter()
+            .find(|prefix| starts_with(&name, prefix))
+        {
+            name = name[prefix.len()..].trim_start_matches('/').to_string();
+        }
+        name
+    }
+}
+
+// Normalize a path so paths coming from different systems can be compared
+// Separators are converted to '/', repeated separators and "./" components are removed
+pub(crate) fn normalize_path(path: &str, ignore_case: bool) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let normalized = path
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<&str>>()
+        .join("/");
+    let normalized = if absolute {
+        format!("/{}", normalized)
+    } else {
+        normalized
+    };
+    if ignore_case {
+        normalized.to_lowercase()
+    } else {
+        normalized
+    }
+}
+
+/// Information collected during the analysis about files that are not part of the metrics
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Diagnostics {
+    /// Files listed in the coverage report that are not found in any source root
+    pub files_missing: Vec<String>,
+    /// Files whose analysis failed, they are not part of the aggregated metrics
+    #[serde(default)]
+    pub files_failed: Vec<FileFailure>,
+    /// Files whose coverage does not match the source
+    #[serde(default)]
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Check of the coverage of a file against its source that failed
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Mismatch {
+    pub file: String,
+    /// Name of the check: length, blank_hits, ranges or complexity
+    pub check: String,
+    pub detail: String,
+    /// The file is left out of the metrics
+    pub skipped: bool,
+}
+
+/// File whose analysis failed with the error that stopped it
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileFailure {
+    pub file: String,
+    /// Error followed by its causes
+    pub error: String,
+}
+
+impl FileFailure {
+    pub(crate) fn new(file: &str, error: &Error) -> Self {
+        let mut causes = vec![error.to_string()];
+        let mut source = std::error::Error::source(error);
+        while let Some(cause) = source {
+            causes.push(cause.to_string());
+            source = cause.source();
+        }
+        Self {
+            file: file.into(),
+            error: causes.join(": "),
+        }
+    }
+}
+
 pub(crate) trait Visit {
     fn get_metrics_from_space(
-        space: &FuncSpace,
+        space: &CodeSpace,
         covs: &[Value],
         metric: Complexity,
         coverage: Option<f64>,
@@ -82,7 +569,7 @@ pub(crate) struct Tree;
 
 impl Visit for Tree {
     fn get_metrics_from_space(
-        space: &FuncSpace,
+        space: &CodeSpace,
         covs: &[Value],
         metric: Complexity,
         coverage: Option<f64>,
@@ -104,6 +591,9 @@ impl Visit for Tree {
                 (covl / tl) * 100.0
             }
         };
+        let comp = space.metrics.complexity_sum(metric);
+        let (covered_lines, instrumented_lines) =
+            count_lines(covs, space.start_line, space.end_line, covdir)?;
         let m = Metrics::new(
             sifis_plain,
             sifis_quantized,
@@ -111,7 +601,11 @@ impl Visit for Tree {
             skunk,
             is_complex,
             f64::round(coverage * 100.0) / 100.0,
-        );
+        )
+        .complexity(comp)
+        .ploc(space.metrics.ploc)
+        .covered_lines(covered_lines)
+        .instrumented_lines(instrumented_lines);
         Ok((m, (sp_sum, sq_sum)))
     }
 }
@@ -134,6 +628,7 @@ fn check_ext(ext: &OsStr) -> bool {
         || ext == "tsx"
         || ext == "ts"
         || ext == "jsm"
+        || ext == "go"
 }
 
 // This function read all  the files in the project folder
@@ -155,7 +650,7 @@ pub(crate) fn read_files(files_path: &Path) -> Result<Vec<String>> {
         } else {
             let ext = path.extension();
 
-            if ext.is_some() && check_ext(ext.ok_or(Error::PathConversionError())?) {
+            if ext.is_some() && check_ext(ext.ok_or_else(|| Error::path(&path))?) {
                 vec.push(path.display().to_string().replace('\\', "/"));
             }
         }
@@ -163,6 +658,243 @@ pub(crate) fn read_files(files_path: &Path) -> Result<Vec<String>> {
     Ok(vec)
 }
 
+// Files to analyze, coverage map using the same paths as keys and files of the report not found
+type FilesToAnalyze<T> = (Vec<String>, HashMap<String, T>, Vec<String>);
+
+// This function get the files listed in the coverage report and look for them in the source roots
+// The coverage map is returned using the path found on disk as key.
+// Keys that are not source files (like PROJECT_ROOT for covdir) are kept as they are.
+// Returns (files, coverage map, files missing)
+pub(crate) fn read_files_from_report<T>(
+    covs: HashMap<String, T>,
+    roots: &[String],
+) -> Result<FilesToAnalyze<T>> {
+    debug!("Looking for report files in: {:?}", roots);
+    let mut vec = vec![];
+    let mut files_missing = vec![];
+    let mut res = HashMap::<String, T>::new();
+    for (name, value) in covs {
+        let ext = Path::new(&name).extension();
+        if ext.is_none() || !check_ext(ext.ok_or_else(|| Error::path(&name))?) {
+            res.insert(name, value);
+            continue;
+        }
+        let path = Path::new(&name);
+        let found = if path.is_absolute() && path.is_file() {
+            Some(path.to_path_buf())
+        } else {
+            roots
+                .iter()
+                .map(|root| Path::new(root).join(name.trim_start_matches('/')))
+                .find(|p| p.is_file())
+        };
+        match found {
+            Some(p) => {
+                let file = p.display().to_string().replace('\\', "/");
+                vec.push(file.clone());
+                res.insert(file, value);
+            }
+            None => files_missing.push(name),
+        }
+    }
+    files_missing.sort();
+    Ok((vec, res, files_missing))
+}
+
+// Apply the remapping rules to the paths of the coverage report and join them to the prefix
+// Keys that are not source files (like PROJECT_ROOT for covdir) are kept as they are.
+pub(crate) fn remap_coverage<T>(
+    covs: HashMap<String, T>,
+    prefix: &str,
+    options: &Options,
+) -> HashMap<String, T> {
+    covs.into_iter()
+        .map(|(name, value)| {
+            let ext = Path::new(&name).extension();
+            if ext.is_none() || !ext.map(check_ext).unwrap_or(false) {
+                return (name, value);
+            }
+            let name = Path::new(prefix).join(options.remap(&name));
+            (name.display().to_string().replace('\\', "/"), value)
+        })
+        .collect()
+}
+
+// Count the number of trailing components two paths have in common
+fn common_suffix(a: &str, b: &str) -> usize {
+    a.rsplit('/')
+        .zip(b.rsplit('/'))
+        .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+        .count()
+}
+
+// Match the files found on disk with the paths of the coverage report comparing normalized paths
+// The coverage map is returned using the path found on disk as key.
+// For each file without coverage the closest unmatched paths of the report are printed.
+fn match_files<T>(
+    files: &[String],
+    covs: HashMap<String, T>,
+    ignore_case: bool,
+) -> HashMap<String, T> {
+    let mut normalized = covs
+        .into_iter()
+        .map(|(name, value)| (normalize_path(&name, ignore_case), (name, value)))
+        .collect::<HashMap<String, (String, T)>>();
+    let mut res = HashMap::<String, T>::new();
+    let mut files_ignored = vec![];
+    for file in files {
+        match normalized.remove(&normalize_path(file, ignore_case)) {
+            Some((_, value)) => {
+                res.insert(file.to_string(), value);
+            }
+            None => files_ignored.push(file),
+        }
+    }
+    let unmatched = normalized
+        .into_values()
+        .filter_map(|(name, value)| {
+            let ext = Path::new(&name).extension().map(check_ext).unwrap_or(false);
+            let unmatched = if ext { Some(name.clone()) } else { None };
+            res.insert(name, value);
+            unmatched
+        })
+        .collect::<Vec<String>>();
+    for file in files_ignored {
+        let mut closest = unmatched
+            .iter()
+            .map(|name| (common_suffix(file, name), name))
+            .filter(|(n, _)| *n > 0)
+            .collect::<Vec<(usize, &String)>>();
+        closest.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        if closest.is_empty() {
+            info!("{} not found in the coverage report", file);
+        } else {
+            let closest = closest
+                .iter()
+                .take(3)
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<&str>>();
+            info!(
+                "{} not found in the coverage report, closest unmatched paths: {}",
+                file,
+                closest.join(", ")
+            );
+        }
+    }
+    res
+}
+
+// Resolve the Java paths of the coverage report written as package paths, like com/example/App.java
+// Each of them is looked up in the source roots as the file whose path ends with the package path,
+// the shortest one is taken when more files match. Paths already found under a root are kept.
+// The coverage map is returned using the path relative to the root containing the file as key.
+pub(crate) fn resolve_packages<T>(
+    covs: HashMap<String, T>,
+    roots: &[String],
+) -> Result<HashMap<String, T>> {
+    let is_package = |name: &str| {
+        let path = Path::new(name);
+        path.extension().map(|ext| ext == "java").unwrap_or(false)
+            && path.is_relative()
+            && !roots
+                .iter()
+                .any(|root| Path::new(root).join(name).is_file())
+    };
+    if !covs.keys().any(|name| is_package(name)) {
+        return Ok(covs);
+    }
+    let sources = roots
+        .iter()
+        .map(|root| -> Result<Vec<(String, String)>> {
+            let root = root.trim_end_matches('/');
+            Ok(read_files(Path::new(root))?
+                .into_iter()
+                .filter_map(|file| {
+                    file.strip_prefix(root)
+                        .map(|relative| relative.trim_start_matches('/').to_string())
+                })
+                .map(|relative| (normalize_path(&relative, false), relative))
+                .collect())
+        })
+        .collect::<Result<Vec<Vec<(String, String)>>>>()?
+        .concat();
+    Ok(covs
+        .into_iter()
+        .map(|(name, value)| {
+            if !is_package(&name) {
+                return (name, value);
+            }
+            let package = normalize_path(&name, false);
+            let suffix = format!("/{}", package);
+            let mut found = sources
+                .iter()
+                .filter(|(normalized, _)| *normalized == package || normalized.ends_with(&suffix))
+                .map(|(_, relative)| relative)
+                .collect::<Vec<&String>>();
+            found.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+            match found.first() {
+                Some(relative) => {
+                    if found.len() > 1 {
+                        info!(
+                            "{} matches more files, {} is used among: {}",
+                            name,
+                            relative,
+                            found[1..]
+                                .iter()
+                                .map(|f| f.as_str())
+                                .collect::<Vec<&str>>()
+                                .join(", ")
+                        );
+                    }
+                    debug!("Package path {} resolved to {}", name, relative);
+                    (relative.to_string(), value)
+                }
+                None => (name, value),
+            }
+        })
+        .collect())
+}
+
+// Get the files to analyze and the coverage map with the same keys.
+// The paths of the coverage map must be the ones written in the report.
+// Returns (files, coverage map, files missing)
+pub(crate) fn get_files<T>(
+    files_path: &Path,
+    covs: HashMap<String, T>,
+    options: &Options,
+) -> Result<FilesToAnalyze<T>> {
+    let covs = resolve_packages(covs, &options.roots(files_path)?)?;
+    let covs = remap_coverage(covs, options.coverage_prefix(files_path)?, options);
+    let (mut files, covs, files_missing) = match options.discovery {
+        FileDiscovery::Walk => {
+            let files = read_files(files_path)?;
+            let covs = match_files(&files, covs, options.ignore_case);
+            (files, covs, Vec::<String>::new())
+        }
+        FileDiscovery::Report => read_files_from_report(covs, &options.roots(files_path)?)?,
+    };
+    if options.exclude_tests {
+        let roots = options.roots(files_path)?;
+        files.retain(|file| {
+            let test = is_test_file(&get_file_path(file, &roots));
+            if test {
+                debug!("Test file excluded: {}", file);
+            }
+            !test
+        });
+    }
+    Ok((files, covs, files_missing))
+}
+
+// Get the path of the file relative to the source root that contains it
+pub(crate) fn get_file_path(file: &str, roots: &[String]) -> String {
+    roots
+        .iter()
+        .find_map(|root| file.strip_prefix(root.as_str()))
+        .unwrap_or(file)
+        .to_string()
+}
+
 // This function read the content of the coveralls  json file obtain by using grcov
 // Return a HashMap with all the files arrays of covered lines using the path to the file as key
 pub(crate) fn read_json(file: String, prefix: &str) -> Result<HashMap<String, Vec<Value>>> {
@@ -170,17 +902,29 @@ pub(crate) fn read_json(file: String, prefix: &str) -> Result<HashMap<String, Ve
     let val: Value = serde_json::from_str(file.as_str())?;
     let vec = val["source_files"]
         .as_array()
-        .ok_or(Error::ReadingJSONError())?;
+        .ok_or_else(|| Error::reading_json("/source_files"))?;
     let mut covs = HashMap::<String, Vec<Value>>::new();
-    vec.iter().try_for_each(|x| -> Result<()> {
-        let name = Path::new(prefix).join(x["name"].as_str().ok_or(Error::PathConversionError())?);
-        let value = x["coverage"]
-            .as_array()
-            .ok_or(Error::ConversionError())?
-            .to_vec();
-        covs.insert(name.display().to_string().replace('\\', "/"), value);
-        Ok(())
-    })?;
+    vec.iter()
+        .enumerate()
+        .try_for_each(|(i, x)| -> Result<()> {
+            let name = Path::new(prefix).join(x["name"].as_str().ok_or_else(|| {
+                Error::conversion(
+                    Location::Pointer(format!("/source_files/{}/name", i)),
+                    "a string",
+                )
+            })?);
+            let value = x["coverage"]
+                .as_array()
+                .ok_or_else(|| {
+                    Error::conversion(
+                        Location::Pointer(format!("/source_files/{}/coverage", i)),
+                        "an array",
+                    )
+                })?
+                .to_vec();
+            covs.insert(name.display().to_string().replace('\\', "/"), value);
+            Ok(())
+        })?;
     Ok(covs)
 }
 
@@ -199,59 +943,72 @@ pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap
     debug!("Reading covdir json...");
     let val: Map<String, Value> = serde_json::from_str(file.as_str())?;
     let mut res: HashMap<String, Covdir> = HashMap::<String, Covdir>::new();
+    let at =
+        |pointer: &str, expected| Error::conversion(Location::Pointer(pointer.into()), expected);
     let mut stack = vec![(
         val["children"]
             .as_object()
-            .ok_or(Error::ConversionError())?,
+            .ok_or_else(|| at("/children", "an object"))?,
         "".to_string(),
+        "/children".to_string(),
     )];
     let covdir = Covdir {
-        name: val["name"].as_str().ok_or(Error::ConversionError())?.into(),
+        name: val["name"]
+            .as_str()
+            .ok_or_else(|| at("/name", "a string"))?
+            .into(),
         arr: vec![],
         coverage: val["coveragePercent"]
             .as_f64()
-            .ok_or(Error::ConversionError())?,
+            .ok_or_else(|| at("/coveragePercent", "a number"))?,
     };
     res.insert("PROJECT_ROOT".into(), covdir);
-    while let Some((val, prefix)) = stack.pop() {
+    while let Some((val, prefix, pointer)) = stack.pop() {
         val.iter().try_for_each(|(key, value)| -> Result<()> {
+            // Pointer to the current value in the covdir json
+            let pointer = format!("{}/{}", pointer, pointer_token(key));
             if value["children"].is_object() {
+                let children = value["children"]
+                    .as_object()
+                    .ok_or_else(|| at(&format!("{}/children", pointer), "an object"))?;
                 if prefix.is_empty() {
                     stack.push((
-                        value["children"]
-                            .as_object()
-                            .ok_or(Error::ConversionError())?,
+                        children,
                         prefix.to_owned() + key.as_str(),
+                        format!("{}/children", pointer),
                     ));
                 } else {
                     let slash = if cfg!(windows) { "\\" } else { "/" };
                     stack.push((
-                        value["children"]
-                            .as_object()
-                            .ok_or(Error::ConversionError())?,
+                        children,
                         prefix.to_owned() + slash + key.as_str(),
+                        format!("{}/children", pointer),
                     ));
                 }
             }
             let name = value["name"]
                 .as_str()
-                .ok_or(Error::ConversionError())?
+                .ok_or_else(|| at(&format!("{}/name", pointer), "a string"))?
                 .into();
             let path = Path::new(&name);
             let ext = path.extension();
 
-            if ext.is_some() && check_ext(ext.ok_or(Error::PathConversionError())?) {
+            if ext.is_some() && check_ext(ext.ok_or_else(|| Error::path(path))?) {
                 let covdir = Covdir {
                     name,
                     arr: value["coverage"]
                         .as_array()
-                        .ok_or(Error::ConversionError())?
+                        .ok_or_else(|| at(&format!("{}/coverage", pointer), "an array"))?
                         .to_vec(),
                     coverage: value["coveragePercent"]
                         .as_f64()
-                        .ok_or(Error::ConversionError())?,
+                        .ok_or_else(|| at(&format!("{}/coveragePercent", pointer), "a number"))?,
+                };
+                let name_path = if prefix.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}/{}", prefix, key)
                 };
-                let name_path = format!("{}/{}", prefix, key);
                 res.insert(map_prefix.to_owned() + name_path.as_str(), covdir);
             }
             Ok(())
@@ -260,16 +1017,140 @@ pub(crate) fn read_json_covdir(file: String, map_prefix: &str) -> Result<HashMap
     Ok(res)
 }
 
+// Read a coverage report and return the arrays of covered lines in the coveralls format
+// In covdir format not instrumented lines are -1, they are converted to null
+pub(crate) fn read_coverage(report: &CoverageReport) -> Result<HashMap<String, Vec<Value>>> {
+    debug!("Reading coverage report: {:?}", report.path);
+    let file = read_report(&report.path)?;
+    let covs = match report.format {
+        JsonFormat::Coveralls => read_json(file, ""),
+        JsonFormat::Covdir => Ok(read_json_covdir(file, "")?
+            .into_iter()
+            .filter(|(name, _)| name != "PROJECT_ROOT")
+            .map(|(name, covdir)| {
+                let arr = covdir
+                    .arr
+                    .into_iter()
+                    .map(|line| match line.as_i64() {
+                        Some(-1) => Value::Null,
+                        _ => line,
+                    })
+                    .collect();
+                (name, arr)
+            })
+            .collect()),
+        JsonFormat::Coverprofile => read_coverprofile(&file),
+        JsonFormat::Jacoco => read_jacoco(&file),
+        JsonFormat::Istanbul => read_istanbul(&file),
+        JsonFormat::Coveragepy => read_coveragepy(&file),
+    };
+    covs.map_err(|e| e.with_file(&report.path))
+}
+
+// Content of the standard input, it can be read only once and the reports are read more times
+static STDIN: OnceLock<String> = OnceLock::new();
+
+// Read a coverage report to string, the errors carry the path of the report
+// The path - reads the report from the standard input
+// Reports compressed with gzip, zstd or xz are decompressed
+pub(crate) fn read_report<A: AsRef<Path>>(path: A) -> Result<String> {
+    if is_stdio(path.as_ref()) {
+        if let Some(content) = STDIN.get() {
+            return Ok(content.clone());
+        }
+        let content = read_text(io::stdin()).map_err(|e| e.with_file(&path))?;
+        return Ok(STDIN.get_or_init(|| content).clone());
+    }
+    fs::File::open(path.as_ref())
+        .map_err(Error::from)
+        .and_then(read_text)
+        .map_err(|e| e.with_file(path))
+}
+
+/// Check if a path is -, which stands for the standard input or output
+pub fn is_stdio<A: AsRef<Path>>(path: A) -> bool {
+    path.as_ref() == Path::new("-")
+}
+
+// Merge two arrays of covered lines summing the hits of each line
+// A line is instrumented if it is instrumented in at least one of the arrays
+pub(crate) fn merge_lines(a: &[Value], b: &[Value]) -> Result<Vec<Value>> {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            let a = a.get(i).unwrap_or(&Value::Null);
+            let b = b.get(i).unwrap_or(&Value::Null);
+            Ok(match (a.is_null(), b.is_null()) {
+                (true, true) => Value::Null,
+                (false, true) => a.clone(),
+                (true, false) => b.clone(),
+                (false, false) => Value::from(
+                    a.as_u64()
+                        .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?
+                        + b.as_u64()
+                            .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?,
+                ),
+            })
+        })
+        .collect()
+}
+
+// Read all the coverage reports and merge them line by line
+// Return a HashMap with the merged coverage of each file using the path in the reports as key
+pub(crate) fn read_coverage_reports(
+    reports: &[CoverageReport],
+) -> Result<HashMap<String, LinesCoverage>> {
+    if reports.is_empty() {
+        return Err(Error::NoCoverageError());
+    }
+    let mut res = HashMap::<String, LinesCoverage>::new();
+    reports.iter().try_for_each(|report| -> Result<()> {
+        read_coverage(report)?
+            .into_iter()
+            .try_for_each(|(name, arr)| -> Result<()> {
+                let in_file = |e: Error| e.with_file(&name);
+                let lines = res.entry(name.clone()).or_default();
+                lines.arr = merge_lines(&lines.arr, &arr).map_err(in_file)?;
+                if let Some(label) = &report.label {
+                    match lines.suites.iter_mut().find(|(l, _)| l == label) {
+                        Some((_, suite)) => *suite = merge_lines(suite, &arr).map_err(in_file)?,
+                        None => lines.suites.push((label.to_string(), arr)),
+                    }
+                }
+                Ok(())
+            })
+    })?;
+    Ok(res)
+}
+
+// Get the labels of the reports covering at least one line between start and end
+pub(crate) fn get_covered_by(
+    suites: &[(String, Vec<Value>)],
+    start: usize,
+    end: usize,
+) -> Result<Vec<String>> {
+    suites.iter().try_fold(vec![], |mut acc, (label, arr)| {
+        let (covered_lines, _) = get_covered_lines(arr, start, end)?;
+        if covered_lines > 0. {
+            acc.push(label.to_string());
+        }
+        Ok(acc)
+    })
+}
+
 // Get the code coverage in percentage
+#[cfg(test)]
 pub(crate) fn get_coverage_perc(covs: &[Value]) -> Result<f64> {
     // Count the number of covered lines
     let (tot_lines, covered_lines) =
         covs.iter()
-            .try_fold((0., 0.), |acc, line| -> Result<(f64, f64)> {
+            .enumerate()
+            .try_fold((0., 0.), |acc, (i, line)| -> Result<(f64, f64)> {
                 let is_null = line.is_null();
                 let sum;
                 if !is_null {
-                    let cov = line.as_u64().ok_or(Error::ConversionError())?;
+                    let cov = line
+                        .as_u64()
+                        .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
                     if cov > 0 {
                         sum = (acc.0 + 1., acc.1 + 1.);
                     } else {
@@ -285,15 +1166,35 @@ pub(crate) fn get_coverage_perc(covs: &[Value]) -> Result<f64> {
 
 // Get the code coverage in percentage between start and end
 pub(crate) fn get_covered_lines(covs: &[Value], start: usize, end: usize) -> Result<(f64, f64)> {
+    count_lines(covs, start, end, false)
+}
+
+// Get the number of covered and instrumented lines between start and end
+// In covdir format not instrumented lines are -1 instead of null
+// The lines are 1-based, an empty range can start at line 0
+pub(crate) fn count_lines(
+    covs: &[Value],
+    start: usize,
+    end: usize,
+    is_covdir: bool,
+) -> Result<(f64, f64)> {
     // Count the number of covered lines
     let (tot_lines, covered_lines) =
         covs.iter()
             .enumerate()
             .try_fold((0., 0.), |acc, (i, line)| -> Result<(f64, f64)> {
-                let is_null = line.is_null();
+                let is_null = if is_covdir {
+                    line.as_i64()
+                        .ok_or(Error::conversion(Location::Index(i), "an integer"))?
+                        == -1
+                } else {
+                    line.is_null()
+                };
                 let sum;
-                if !is_null && (start - 1..end).contains(&i) {
-                    let cov = line.as_u64().ok_or(Error::ConversionError())?;
+                if !is_null && (start.saturating_sub(1)..end).contains(&i) {
+                    let cov = line
+                        .as_u64()
+                        .ok_or(Error::conversion(Location::Index(i), "an unsigned integer"))?;
                     if cov > 0 {
                         sum = (acc.0 + 1., acc.1 + 1.);
                     } else {
@@ -307,35 +1208,143 @@ pub(crate) fn get_covered_lines(covs: &[Value], start: usize, end: usize) -> Res
     Ok((covered_lines, tot_lines))
 }
 
-// Get the root FuncSpace from a file
-pub(crate) fn get_root<A: AsRef<Path>>(path: A) -> Result<FuncSpace> {
-    let data = read_file(path.as_ref())?;
-    let lang = guess_language(&data, path.as_ref())
-        .0
-        .ok_or(Error::LanguageError())?;
-    debug!("{:?} is written in {:?}", path.as_ref(), lang);
-    let root = get_function_spaces(&lang, data, path.as_ref(), None).ok_or(Error::MetricsError())?;
-    Ok(root)
+// Get the root space of a file using rust-code-analysis
+#[cfg(test)]
+pub(crate) fn get_root<A: AsRef<Path>>(path: A) -> Result<CodeSpace> {
+    let data = rust_code_analysis::read_file(path.as_ref())
+        .map_err(|e| Error::from(e).with_file(&path))?;
+    Ok(get_code_root(path.as_ref(), &data, &[])?.0)
 }
 
-// Get all spaces stating from root.
+// Root space of a file, ranges of lines excluded and true if the root comes from a provider not parsing the source
+type RootExcluding = (CodeSpace, Vec<(usize, usize)>, bool);
+
+// Get the root space of a file and the ranges of lines excluded from it
+// The excluded lines are emptied before the analysis, so they are not part of any space
+// The complexity comes from the first provider knowing the file, rust-code-analysis is the last one
+pub(crate) fn get_root_excluding<A: AsRef<Path>>(
+    path: A,
+    data: &[u8],
+    exclude_tests: bool,
+    providers: &[Arc<dyn ComplexityProvider>],
+) -> Result<RootExcluding> {
+    let excluded = if exclude_tests {
+        get_test_lines(path.as_ref(), data)
+    } else {
+        Vec::new()
+    };
+    debug!("{:?} excluded lines: {:?}", path.as_ref(), excluded);
+    let data = blank_lines(data, &excluded);
+    let (mut root, external) = get_code_root(path.as_ref(), &data, providers)?;
+    // The spaces of the providers not parsing the source can be inside the excluded lines
+    root.spaces.retain(|s| {
+        !excluded
+            .iter()
+            .any(|(start, end)| *start <= s.start_line && s.end_line <= *end)
+    });
+    Ok((root, excluded, external))
+}
+
+// Get the coverage percentage of a covdir array
+pub(crate) fn get_covdir_coverage(arr: &[Value]) -> Result<f64> {
+    let (covered_lines, tot_lines) = count_lines(arr, 1, arr.len(), true)?;
+    if tot_lines == 0. {
+        Ok(0.)
+    } else {
+        Ok(covered_lines / tot_lines * 100.)
+    }
+}
+
+/// Space found inside a root with the spaces enclosing it
+pub(crate) struct SpaceEntry<'a> {
+    pub(crate) space: &'a CodeSpace,
+    /// Path of the space inside the root, e.g. /Foo (3,20)/bar (5,10)
+    pub(crate) path: String,
+    /// Enclosing spaces from the outermost one, the root is not included
+    pub(crate) parents: Vec<&'a CodeSpace>,
+}
+
+impl SpaceEntry<'_> {
+    /// Name of the space qualified with the names of the enclosing spaces, e.g. Foo::bar
+    pub(crate) fn qualified_name(&self) -> Result<String> {
+        Ok(self
+            .parents
+            .iter()
+            .chain(std::iter::once(&self.space))
+            .map(|s| s.name.as_deref().ok_or(Error::space_name(s.start_line)))
+            .collect::<Result<Vec<&str>>>()?
+            .join("::"))
+    }
+
+    /// Kinds of the enclosing spaces and of the space itself, e.g. impl, function
+    pub(crate) fn kind_chain(&self) -> Vec<String> {
+        self.parents
+            .iter()
+            .chain(std::iter::once(&self.space))
+            .map(|s| s.kind.to_string())
+            .collect()
+    }
+}
+
+// Get the qualified names of the spaces of a file
+// The names found more than once, e.g. fmt of impl Display and impl Debug for the same type,
+// get the start line of their space, e.g. App::fmt@12
+pub(crate) fn get_qualified_names(entries: &[SpaceEntry]) -> Result<Vec<String>> {
+    let names = entries
+        .iter()
+        .map(|e| e.qualified_name())
+        .collect::<Result<Vec<String>>>()?;
+    let mut counts = HashMap::<&str, usize>::new();
+    names
+        .iter()
+        .for_each(|name| *counts.entry(name).or_default() += 1);
+    Ok(names
+        .iter()
+        .zip(entries)
+        .map(|(name, e)| {
+            if counts[name.as_str()] > 1 {
+                format!("{}@{}", name, e.space.start_line)
+            } else {
+                name.to_string()
+            }
+        })
+        .collect())
+}
+
+// Get all spaces of the given kinds stating from root.
 // It does not contain the root
-pub(crate) fn get_spaces(root: &FuncSpace) -> Result<Vec<(&FuncSpace, String)>> {
-    let mut stack = vec![(root, String::new())];
+// With rollup the spaces inside a space already reported are skipped,
+// their metrics are already counted in the enclosing one
+pub(crate) fn get_spaces<'a>(
+    root: &'a CodeSpace,
+    kinds: &[SpaceKind],
+    rollup: bool,
+) -> Result<Vec<SpaceEntry<'a>>> {
+    let mut stack = vec![(root, String::new(), Vec::new())];
     let mut result = Vec::new();
-    while let Some((space, path)) = stack.pop() {
+    while let Some((space, path, parents)) = stack.pop() {
         for s in &space.spaces {
             let p = format!(
                 "{}/{} ({},{})",
                 path,
-                s.name.as_ref().ok_or(Error::PathConversionError())?,
+                s.name.as_ref().ok_or(Error::space_name(s.start_line))?,
                 s.start_line,
                 s.end_line
             );
-            stack.push((s, p.to_string()));
-            if s.kind == SpaceKind::Function {
-                result.push((s, p));
+            let reported = kinds.contains(&s.kind);
+            if reported {
+                result.push(SpaceEntry {
+                    space: s,
+                    path: p.clone(),
+                    parents: parents.clone(),
+                });
+            }
+            if reported && rollup {
+                continue;
             }
+            let mut children_parents = parents.clone();
+            children_parents.push(s);
+            stack.push((s, p, children_parents));
         }
     }
     Ok(result)
@@ -357,20 +1366,50 @@ pub(crate) fn check_complexity(
         || skunk > thresholds[3]
 }
 
+// Get the names of the thresholds exceeded by the metrics
+pub(crate) fn get_exceeded_thresholds(metrics: &Metrics, thresholds: &[f64]) -> Vec<&'static str> {
+    [
+        ("sifis_plain", metrics.sifis_plain),
+        ("sifis_quantized", metrics.sifis_quantized),
+        ("crap", metrics.crap),
+        ("skunk", metrics.skunk),
+    ]
+    .iter()
+    .zip(thresholds)
+    .filter(|((_, value), threshold)| value > threshold)
+    .map(|((name, _), _)| *name)
+    .collect()
+}
+
 // GET average, maximum and minimum given all the metrics
-pub(crate) fn get_cumulative_values(metrics: &Vec<Metrics>) -> (Metrics, Metrics, Metrics) {
+pub(crate) fn get_cumulative_values(metrics: &[Metrics]) -> (Metrics, Metrics, Metrics) {
     let mut min = Metrics::min();
     let mut max = Metrics::default();
+    let mut raw = Metrics::default();
     let (sifis, sifisq, crap, skunk, cov) =
         metrics.iter().fold((0.0, 0.0, 0.0, 0.0, 0.0), |acc, m| {
             max.sifis_plain = max.sifis_plain.max(m.sifis_plain);
             max.sifis_quantized = max.sifis_quantized.max(m.sifis_quantized);
             max.crap = max.crap.max(m.crap);
             max.skunk = max.skunk.max(m.skunk);
+            max.complexity = max.complexity.max(m.complexity);
+            max.ploc = max.ploc.max(m.ploc);
+            max.covered_lines = max.covered_lines.max(m.covered_lines);
+            max.instrumented_lines = max.instrumented_lines.max(m.instrumented_lines);
+            max.coverage = max.coverage.max(m.coverage);
             min.sifis_plain = min.sifis_plain.min(m.sifis_plain);
             min.sifis_quantized = min.sifis_quantized.min(m.sifis_quantized);
             min.crap = min.crap.min(m.crap);
             min.skunk = min.skunk.min(m.skunk);
+            min.complexity = min.complexity.min(m.complexity);
+            min.ploc = min.ploc.min(m.ploc);
+            min.covered_lines = min.covered_lines.min(m.covered_lines);
+            min.instrumented_lines = min.instrumented_lines.min(m.instrumented_lines);
+            min.coverage = min.coverage.min(m.coverage);
+            raw.complexity += m.complexity;
+            raw.ploc += m.ploc;
+            raw.covered_lines += m.covered_lines;
+            raw.instrumented_lines += m.instrumented_lines;
             (
                 acc.0 + m.sifis_plain,
                 acc.1 + m.sifis_quantized,
@@ -380,19 +1419,18 @@ pub(crate) fn get_cumulative_values(metrics: &Vec<Metrics>) -> (Metrics, Metrics
             )
         });
     let l = metrics.len() as f64;
-    let avg = Metrics::new(sifis / l, sifisq / l, crap / l, skunk / l, false, cov);
+    let avg = Metrics::new(sifis / l, sifisq / l, crap / l, skunk / l, false, cov / l)
+        .complexity(raw.complexity / l)
+        .ploc(raw.ploc / l)
+        .covered_lines(raw.covered_lines / l)
+        .instrumented_lines(raw.instrumented_lines / l);
     (avg, max, min)
 }
 
 // Calculate SIFIS PLAIN , SIFIS QUANTIZED, CRA and SKUNKSCORE for the entire project
 // Using the sum values computed before
-pub(crate) fn get_project_metrics(
-    values: JobComposer,
-    project_coverage: Option<f64>,
-) -> Result<Metrics> {
-    let project_coverage = if let Some(cov) = project_coverage {
-        cov
-    } else if values.total_lines != 0.0 {
+pub(crate) fn get_project_metrics(values: JobComposer) -> Result<Metrics> {
+    let project_coverage = if values.total_lines != 0.0 {
         (values.covered_lines / values.total_lines) * 100.0
     } else {
         0.0
@@ -405,6 +1443,10 @@ pub(crate) fn get_project_metrics(
     );
     m = m.skunk((values.comp_sum / COMPLEXITY_FACTOR) * (100. - (project_coverage)));
     m = m.coverage(project_coverage);
+    m = m.complexity(values.comp_sum);
+    m = m.ploc(values.ploc_sum);
+    m = m.covered_lines(values.covered_lines);
+    m = m.instrumented_lines(values.total_lines);
     Ok(m)
 }
 
@@ -432,4 +1474,86 @@ mod tests {
         let value_null = vec.get(1).unwrap();
         assert!(value_null.is_null());
     }
+
+    #[test]
+    fn test_merge_lines() {
+        let a = vec![Value::Null, Value::from(0), Value::from(2), Value::Null];
+        let b = vec![
+            Value::Null,
+            Value::Null,
+            Value::from(3),
+            Value::from(0),
+            Value::from(1),
+        ];
+        let merged = merge_lines(&a, &b).unwrap();
+
+        assert_eq!(
+            merged,
+            vec![
+                Value::Null,
+                Value::from(0),
+                Value::from(5),
+                Value::from(0),
+                Value::from(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remap() {
+        let options = Options::default()
+            .strip_prefixes(vec!["/builds/project/".into()])
+            .replace_prefixes(vec![("C:\\work\\".into(), "/home/user/".into())])
+            .ignore_case(true);
+        assert_eq!(options.remap("/builds/project/src/main.rs"), "src/main.rs");
+        assert_eq!(options.remap("/BUILDS/Project/src/main.rs"), "src/main.rs");
+        assert_eq!(
+            options.remap("c:\\work\\src\\main.rs"),
+            "/home/user/src/main.rs"
+        );
+        assert_eq!(options.remap("src/main.rs"), "src/main.rs");
+    }
+
+    #[test]
+    fn test_resolve_packages() {
+        let covs = HashMap::from([
+            ("com/example/Calculator.java".to_string(), 1),
+            ("com/example/Missing.java".to_string(), 2),
+            ("PROJECT_ROOT".to_string(), 3),
+        ]);
+        let resolved = resolve_packages(covs, &["./data/java_project".into()]).unwrap();
+        assert_eq!(resolved["src/main/java/com/example/Calculator.java"], 1);
+        assert_eq!(resolved["com/example/Missing.java"], 2);
+        assert_eq!(resolved["PROJECT_ROOT"], 3);
+        // A path already found under a source root is kept
+        let covs = HashMap::from([("com/example/Calculator.java".to_string(), 1)]);
+        let roots = ["./data/java_project/src/main/java/".to_string()];
+        let resolved = resolve_packages(covs, &roots).unwrap();
+        assert!(resolved.contains_key("com/example/Calculator.java"));
+    }
+
+    #[test]
+    fn test_get_cumulative_values() {
+        let metrics = [
+            Metrics::new(10., 1., 20., 5., true, 40.).complexity(4.),
+            Metrics::new(2., 0.5, 4., 1., false, 90.).complexity(1.),
+            Metrics::new(6., 0.9, 9., 3., false, 65.).complexity(2.),
+        ];
+        let (avg, max, min) = get_cumulative_values(&metrics);
+        assert!(compare_float(avg.coverage, 65.));
+        assert!(compare_float(max.coverage, 90.));
+        assert!(compare_float(min.coverage, 40.));
+        assert!(compare_float(avg.sifis_plain, 6.));
+        assert!(compare_float(max.crap, 20.));
+        assert!(compare_float(min.complexity, 1.));
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("./data//src\\main.rs", false),
+            "data/src/main.rs"
+        );
+        assert_eq!(normalize_path("/data/./Main.rs", true), "/data/main.rs");
+    }
 }
@@ -0,0 +1,381 @@
+use std::sync::Mutex;
+
+use rust_code_analysis::SpaceKind;
+
+use crate::complexity::CodeSpace;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::error::*;
+use crate::utility::{Mismatch, Policy, Validation};
+
+// Checks of the coverage array of a file against its source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Check {
+    Length,
+    BlankHits,
+    Ranges,
+    Complexity,
+}
+
+impl Check {
+    fn name(&self) -> &'static str {
+        match self {
+            Check::Length => "length",
+            Check::BlankHits => "blank_hits",
+            Check::Ranges => "ranges",
+            Check::Complexity => "complexity",
+        }
+    }
+
+    fn policy(&self, validation: &Validation) -> Policy {
+        match self {
+            Check::Length => validation.length,
+            Check::BlankHits => validation.blank_hits,
+            Check::Ranges => validation.ranges,
+            Check::Complexity => validation.complexity,
+        }
+    }
+}
+
+// In covdir format not instrumented lines are -1 instead of null
+fn is_instrumented(line: &Value, is_covdir: bool) -> bool {
+    !(line.is_null() || (is_covdir && line.as_i64() == Some(-1)))
+}
+
+// Blank lines and lines with only a comment, the markers of the most common languages are used
+// Rust attributes and shebangs start with # but they are not comments
+pub(crate) fn is_blank_or_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty()
+        || line.starts_with("//")
+        || line.starts_with("/*")
+        || line.starts_with("*/")
+        || line == "*"
+        || line.starts_with("* ")
+        || (line.starts_with('#') && !line.starts_with("#[") && !line.starts_with("#!"))
+}
+
+// Get the functions starting after the last line of the coverage array
+// The trailing lines not instrumented are dropped from the arrays, so a function
+// is out of range only if its first line is, not when only its closing brace is
+fn functions_out_of_range(root: &CodeSpace, len: usize) -> Vec<&CodeSpace> {
+    let mut stack = vec![root];
+    let mut result = Vec::new();
+    while let Some(space) = stack.pop() {
+        space.spaces.iter().for_each(|s| {
+            if s.kind == SpaceKind::Function && s.start_line > len {
+                result.push(s);
+            }
+            stack.push(s);
+        });
+    }
+    result.sort_by_key(|s| s.start_line);
+    result
+}
+
+// Get all the spaces inside the root sorted by first line
+fn all_spaces(root: &CodeSpace) -> Vec<&CodeSpace> {
+    let mut stack = vec![root];
+    let mut result = Vec::new();
+    while let Some(space) = stack.pop() {
+        result.extend(space.spaces.iter());
+        stack.extend(space.spaces.iter());
+    }
+    result.sort_by_key(|s| s.start_line);
+    result
+}
+
+// Get the spaces of a provider not parsing the source that do not match the file and its coverage:
+// the ones ending after the last line of the file and the ones with code in their body
+// but without instrumented lines, the first and the last line are left out because
+// they can be only a signature and a brace
+fn unmatched_spaces<'a>(
+    lines: &[&str],
+    arr: &[Value],
+    root: &'a CodeSpace,
+    is_covdir: bool,
+) -> (Vec<&'a CodeSpace>, Vec<&'a CodeSpace>) {
+    let spaces = all_spaces(root);
+    let after_end = spaces
+        .iter()
+        .filter(|s| s.end_line > lines.len())
+        .copied()
+        .collect::<Vec<&CodeSpace>>();
+    let not_instrumented = spaces
+        .into_iter()
+        .filter(|s| s.end_line <= lines.len() && s.start_line < s.end_line)
+        .filter(|s| {
+            let has_code = lines[s.start_line..s.end_line - 1]
+                .iter()
+                .any(|l| !is_blank_or_comment(l));
+            let instrumented = arr
+                .iter()
+                .take(s.end_line)
+                .skip(s.start_line.saturating_sub(1))
+                .any(|cov| is_instrumented(cov, is_covdir));
+            has_code && !instrumented
+        })
+        .collect::<Vec<&CodeSpace>>();
+    (after_end, not_instrumented)
+}
+
+// Describe a space as name (start, end)
+fn describe(space: &CodeSpace) -> String {
+    format!(
+        "{} ({}, {})",
+        space.name.as_deref().unwrap_or("-"),
+        space.start_line,
+        space.end_line
+    )
+}
+
+// Run the checks that are not ignored and return the failed ones with their details
+// The spaces of the providers not parsing the source are checked only when external is true
+fn run_checks(
+    lines: &[&str],
+    arr: &[Value],
+    root: &CodeSpace,
+    is_covdir: bool,
+    external: bool,
+    validation: &Validation,
+) -> Vec<(Check, String)> {
+    let mut failed = Vec::new();
+    let enabled = |check: Check| check.policy(validation) != Policy::Ignore;
+    if enabled(Check::Length) && arr.len() > lines.len() {
+        failed.push((
+            Check::Length,
+            format!(
+                "the coverage array has {} lines but the file has {}",
+                arr.len(),
+                lines.len()
+            ),
+        ));
+    }
+    if enabled(Check::BlankHits) {
+        let blank = lines
+            .iter()
+            .zip(arr)
+            .enumerate()
+            .filter(|(_, (line, cov))| is_instrumented(cov, is_covdir) && is_blank_or_comment(line))
+            .map(|(i, _)| i + 1)
+            .collect::<Vec<usize>>();
+        if let Some(first) = blank.first() {
+            failed.push((
+                Check::BlankHits,
+                format!(
+                    "{} blank or comment lines are instrumented, the first one is line {}",
+                    blank.len(),
+                    first
+                ),
+            ));
+        }
+    }
+    if enabled(Check::Ranges) {
+        let functions = functions_out_of_range(root, arr.len());
+        if let Some(first) = functions.first() {
+            failed.push((
+                Check::Ranges,
+                format!(
+                    "{} functions start after the coverage array of {} lines, the first one is {}",
+                    functions.len(),
+                    arr.len(),
+                    describe(first)
+                ),
+            ));
+        }
+    }
+    if external && enabled(Check::Complexity) {
+        let (after_end, not_instrumented) = unmatched_spaces(lines, arr, root, is_covdir);
+        let mut details = Vec::new();
+        if let Some(first) = after_end.first() {
+            details.push(format!(
+                "{} functions end after the file of {} lines, the first one is {}",
+                after_end.len(),
+                lines.len(),
+                describe(first)
+            ));
+        }
+        if let Some(first) = not_instrumented.first() {
+            details.push(format!(
+                "{} functions have code but no instrumented lines, the first one is {}",
+                not_instrumented.len(),
+                describe(first)
+            ));
+        }
+        if !details.is_empty() {
+            failed.push((Check::Complexity, details.join(", ")));
+        }
+    }
+    failed
+}
+
+/// Check the coverage array of a file against its source applying the policy of each check
+/// The mismatches are added to the list, the file is left out when a failed check has the skip policy
+/// Returns false if the file must be skipped and an error if a failed check has the fail policy
+/// External tells the root comes from a complexity provider not parsing the source
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn validate_coverage(
+    file: &str,
+    source: &str,
+    arr: &[Value],
+    root: &CodeSpace,
+    is_covdir: bool,
+    external: bool,
+    validation: &Validation,
+    mismatches: &Mutex<Vec<Mismatch>>,
+) -> Result<bool> {
+    let lines = source.lines().collect::<Vec<&str>>();
+    let failed = run_checks(&lines, arr, root, is_covdir, external, validation);
+    if let Some((check, detail)) = failed
+        .iter()
+        .find(|(check, _)| check.policy(validation) == Policy::Fail)
+    {
+        return Err(Error::ValidationError {
+            file: file.into(),
+            check: check.name(),
+            detail: detail.to_string(),
+        });
+    }
+    let skipped = failed
+        .iter()
+        .any(|(check, _)| check.policy(validation) == Policy::Skip);
+    let mut mismatches = mismatches.lock()?;
+    failed.into_iter().for_each(|(check, detail)| {
+        warn!("Coverage of {} does not match: {}", file, detail);
+        mismatches.push(Mismatch {
+            file: file.into(),
+            check: check.name().into(),
+            detail,
+            skipped,
+        });
+    });
+    Ok(!skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complexity::{ComplexityProvider, Sidecar, SidecarFunction};
+    use crate::formats::read_coverprofile;
+    use crate::utility::get_root;
+    use std::path::Path;
+
+    const FILE: &str = "./data/suppression_project/src/parser.rs";
+
+    #[test]
+    fn test_run_checks() {
+        let root = get_root(FILE).unwrap();
+        let source = std::fs::read_to_string(FILE).unwrap();
+        let lines = source.lines().collect::<Vec<&str>>();
+        let all = Validation::default().blank_hits(Policy::Warn);
+
+        // Only the lines with code are instrumented
+        let arr = lines
+            .iter()
+            .map(|l| {
+                if is_blank_or_comment(l) {
+                    Value::Null
+                } else {
+                    Value::from(1)
+                }
+            })
+            .collect::<Vec<Value>>();
+        assert!(run_checks(&lines, &arr, &root, false, false, &all).is_empty());
+
+        // A report of an older version of the file with more lines
+        let mut longer = vec![Value::from(1)];
+        longer.extend(arr.iter().cloned());
+        let failed = run_checks(&lines, &longer, &root, false, false, &all);
+        assert_eq!(failed[0].0, Check::Length);
+        assert_eq!(failed[1].0, Check::BlankHits);
+
+        // A report of a shorter version of the file
+        let failed = run_checks(&lines, &arr[..10], &root, false, false, &all);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, Check::Ranges);
+        assert!(failed[0].1.contains("coverage array of 10 lines"));
+
+        let ignored = Validation::default()
+            .length(Policy::Ignore)
+            .ranges(Policy::Ignore);
+        assert!(run_checks(&lines, &arr[..10], &root, false, false, &ignored).is_empty());
+    }
+
+    #[test]
+    fn test_validate_coverage() {
+        let root = get_root(FILE).unwrap();
+        let source = std::fs::read_to_string(FILE).unwrap();
+        let arr = vec![Value::from(1); 5];
+        let mismatches = Mutex::new(Vec::new());
+
+        let warn = Validation::default();
+        assert!(
+            validate_coverage(FILE, &source, &arr, &root, false, false, &warn, &mismatches)
+                .unwrap()
+        );
+        let skip = warn.ranges(Policy::Skip);
+        assert!(
+            !validate_coverage(FILE, &source, &arr, &root, false, false, &skip, &mismatches)
+                .unwrap()
+        );
+        let mismatches = mismatches.into_inner().unwrap();
+        assert_eq!(mismatches.len(), 2);
+        assert!(!mismatches[0].skipped);
+        assert!(mismatches[1].skipped);
+
+        let fail = warn.ranges(Policy::Fail);
+        let error = validate_coverage(
+            FILE,
+            &source,
+            &arr,
+            &root,
+            false,
+            false,
+            &fail,
+            &Mutex::default(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("Coverage of"));
+    }
+
+    #[test]
+    fn test_complexity_check() {
+        let file = "./data/go_project/server.go";
+        let profile = std::fs::read_to_string("./data/go_project/cover.out").unwrap();
+        let arr = read_coverprofile(&profile).unwrap()["example.com/app/server.go"].clone();
+        let source = std::fs::read_to_string(file).unwrap();
+        let lines = source.lines().collect::<Vec<&str>>();
+        let function = |name: &str, start_line, end_line| SidecarFunction {
+            file: "server.go".into(),
+            name: name.into(),
+            start_line,
+            end_line,
+            cyclomatic: 1.,
+            cognitive: 0.,
+            ploc: None,
+        };
+        // A sidecar written for another version of the file
+        let sidecar = Sidecar::new(vec![
+            function("Stale", 1, 4),
+            function("Greet", 6, 11),
+            function("Gone", 20, 30),
+        ])
+        .unwrap();
+        let root = sidecar
+            .root(Path::new(file), source.as_bytes())
+            .unwrap()
+            .unwrap();
+        let validation = Validation::default();
+
+        assert!(run_checks(&lines, &arr, &root, false, false, &validation).is_empty());
+        let failed = run_checks(&lines, &arr, &root, false, true, &validation);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, Check::Complexity);
+        assert_eq!(
+            failed[0].1,
+            "1 functions end after the file of 22 lines, the first one is Gone (20, 30), \
+             1 functions have code but no instrumented lines, the first one is Stale (1, 4)"
+        );
+    }
+}